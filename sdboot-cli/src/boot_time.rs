@@ -0,0 +1,75 @@
+//! The pre-kernel boot phases printed by the "boot-time" subcommand.
+
+use std::fmt::Write as _;
+
+use sdboot::BootTimings;
+use serde_json::{json, Value};
+
+use crate::status::seconds;
+
+/// Renders the time spent in the firmware and in the boot loader, like
+/// `systemd-analyze` does.
+pub fn render(timings: &BootTimings) -> String {
+    let mut output = String::new();
+    let _ = writeln!(
+        output,
+        "{:>11}: {}",
+        "Firmware",
+        seconds(timings.firmware_usec())
+    );
+    let _ = write!(
+        output,
+        "{:>11}: {}",
+        "Loader",
+        seconds(timings.loader_usec())
+    );
+    match timings.menu_usec {
+        Some(menu_usec) if menu_usec > 0 => {
+            let _ = write!(
+                output,
+                " (of which {} in the menu)",
+                seconds(Some(menu_usec))
+            );
+        }
+        _ => {}
+    }
+    let _ = writeln!(output);
+    let _ = writeln!(
+        output,
+        "{:>11}: {} after the firmware start",
+        "Handoff",
+        seconds(timings.exec_usec)
+    );
+    output
+}
+
+/// The phases as a JSON object, in microseconds.
+pub fn to_json(timings: &BootTimings) -> Value {
+    json!({
+        "firmware_usec": timings.firmware_usec(),
+        "loader_usec": timings.loader_usec(),
+        "menu_usec": timings.menu_usec,
+        "handoff_usec": timings.exec_usec,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_render() {
+        let timings = BootTimings {
+            init_usec: Some(1_234_567),
+            exec_usec: Some(3_000_100),
+            menu_usec: Some(500_000),
+        };
+        assert_eq!(
+            render(&timings),
+            "   Firmware: 1.234s\n\
+             \x20    Loader: 1.765s (of which 0.500s in the menu)\n\
+             \x20   Handoff: 3.000s after the firmware start\n"
+        );
+        assert_eq!(to_json(&timings)["loader_usec"], 1_765_533);
+    }
+}
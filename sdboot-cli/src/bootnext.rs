@@ -0,0 +1,127 @@
+//! The firmware boot manager options handled by the "bootnext" subcommand.
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use sdboot::{BootOption, ErrorKind};
+use serde_json::{json, Value};
+
+/// Renders the options in the boot order, marking the inactive ones and the
+/// one to boot next.
+pub fn render(options: &[BootOption], next: Option<u16>) -> String {
+    let mut output = String::new();
+    for option in options {
+        let _ = writeln!(
+            output,
+            "{} {} {}{}",
+            option.name(),
+            if option.active { '*' } else { ' ' },
+            option.description,
+            if next == Some(option.number) {
+                "  (next)"
+            } else {
+                ""
+            }
+        );
+    }
+    output
+}
+
+/// Lists the options as a JSON array.
+pub fn to_json(options: &[BootOption], next: Option<u16>) -> Value {
+    options
+        .iter()
+        .map(|option| {
+            json!({
+                "name": option.name(),
+                "number": option.number,
+                "description": option.description,
+                "active": option.active,
+                "next": next == Some(option.number),
+            })
+        })
+        .collect()
+}
+
+/// Finds the option by its variable name ("Boot0003"), hex number ("0003")
+/// or description, which may be abbreviated as long as it is unambiguous.
+pub fn find<'a>(options: &'a [BootOption], query: &str) -> Result<&'a BootOption> {
+    let number = sdboot::parse::boot_option_number(query).or_else(|| {
+        (query.len() <= 4)
+            .then(|| u16::from_str_radix(query, 16).ok())
+            .flatten()
+    });
+    if let Some(option) =
+        number.and_then(|number| options.iter().find(|option| option.number == number))
+    {
+        return Ok(option);
+    }
+
+    let needle = query.to_lowercase();
+    let mut matches = options
+        .iter()
+        .filter(|option| option.description.to_lowercase() == needle)
+        .collect::<Vec<_>>();
+    if matches.is_empty() {
+        matches = options
+            .iter()
+            .filter(|option| option.description.to_lowercase().contains(&needle))
+            .collect();
+    }
+    match matches[..] {
+        [option] => Ok(option),
+        [] => Err(anyhow::Error::new(ErrorKind::EntryNotFound)
+            .context(format!(r#"There is no boot option "{}""#, query))),
+        _ => Err(
+            anyhow::Error::new(ErrorKind::EntryNotFound).context(format!(
+                r#""{}" is ambiguous, it matches {}"#,
+                query,
+                matches
+                    .iter()
+                    .map(|option| format!("{} ({})", option.name(), option.description))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_find() {
+        let options = [
+            BootOption {
+                number: 0,
+                description: "Linux Boot Manager".into(),
+                active: true,
+            },
+            BootOption {
+                number: 3,
+                description: "Windows Boot Manager".into(),
+                active: true,
+            },
+            BootOption {
+                number: 0x1f,
+                description: "UEFI: PXE IPv4".into(),
+                active: false,
+            },
+        ];
+        let find = |query| find(&options, query).map(|option| option.number).ok();
+        assert_eq!(find("Boot0003"), Some(3));
+        assert_eq!(find("1F"), Some(0x1f));
+        assert_eq!(find("windows"), Some(3));
+        assert_eq!(find("linux boot manager"), Some(0));
+        assert_eq!(find("boot manager"), None);
+        assert_eq!(find("shell"), None);
+
+        assert_eq!(
+            render(&options, Some(3)),
+            "Boot0000 * Linux Boot Manager\n\
+             Boot0003 * Windows Boot Manager  (next)\n\
+             Boot001F   UEFI: PXE IPv4\n"
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! Shell completion scripts. On top of the completions clap generates, the
+//! bash, zsh and fish scripts complete the entry identifiers by running the
+//! hidden "__complete-entries" subcommand.
+
+use std::collections::BTreeSet;
+
+use clap_complete::Shell;
+
+/// The hidden subcommand printing the entry identifiers, one per line.
+pub const ENTRIES_COMMAND: &str = "__complete-entries";
+
+/// The subcommands taking an entry identifier as the argument.
+const ENTRY_SUBCOMMANDS: [&str; 3] = ["set-oneshot", "set-default", "schedule"];
+
+const BASH: &str = r#"
+_sdboot_cli_entries() {
+    local i subcommand="" current="${COMP_WORDS[COMP_CWORD]}" previous="${COMP_WORDS[COMP_CWORD-1]}"
+    for i in "${COMP_WORDS[@]:1:COMP_CWORD-1}"; do
+        case "$i" in
+            @SUBCOMMANDS@|reboot) subcommand="$i"; break ;;
+        esac
+    done
+    case "$subcommand,$previous,$current" in
+        *,*,-*|@OPTIONS@|reboot,*,*) ;;
+        ,*,*) ;;
+        *)
+            COMPREPLY=( $(compgen -W "$(@NAME@ @COMMAND@ 2>/dev/null)" -- "$current") )
+            return 0
+            ;;
+    esac
+    if [[ "$previous" == --into && "$subcommand" == reboot ]]; then
+        COMPREPLY=( $(compgen -W "$(@NAME@ @COMMAND@ 2>/dev/null)" -- "$current") )
+        return 0
+    fi
+    @FUNCTION@ "$@"
+}
+complete -F _sdboot_cli_entries -o bashdefault -o default @NAME@
+"#;
+
+const ZSH: &str = r#"
+_sdboot_cli_entries() {
+    local -a entries
+    entries=(${(f)"$(@NAME@ @COMMAND@ 2>/dev/null)"})
+    _describe -t entries 'entries' entries
+}
+"#;
+
+const FISH: &str = r#"
+complete -c @NAME@ -n "__fish_seen_subcommand_from @SUBCOMMANDS@" -f -a "(@NAME@ @COMMAND@ 2>/dev/null)"
+complete -c @NAME@ -n "__fish_seen_subcommand_from reboot" -l into -x -a "(@NAME@ @COMMAND@ 2>/dev/null)"
+"#;
+
+/// Generates the completion script of the command for the shell.
+pub fn generate(shell: Shell, command: &mut clap::Command) -> String {
+    let name = command.get_name().to_string();
+    let mut script = Vec::new();
+    clap_complete::generate(shell, command, &name, &mut script);
+    let script = String::from_utf8_lossy(&script).into_owned();
+
+    let subcommands = ENTRY_SUBCOMMANDS
+        .iter()
+        .filter_map(|name| command.find_subcommand(name))
+        .flat_map(|subcommand| {
+            std::iter::once(subcommand.get_name()).chain(subcommand.get_all_aliases())
+        })
+        .collect::<Vec<_>>();
+    let fill = |template: &str, separator: &str| {
+        template
+            .replace("@NAME@", &name)
+            .replace("@COMMAND@", ENTRIES_COMMAND)
+            .replace("@SUBCOMMANDS@", &subcommands.join(separator))
+    };
+
+    match shell {
+        Shell::Bash => {
+            // The function clap generated, to fall back to.
+            let Some(function) = script
+                .split("complete -F ")
+                .nth(1)
+                .and_then(|rest| rest.split_whitespace().next())
+                .map(str::to_string)
+            else {
+                return script;
+            };
+            let options = value_options(command)
+                .iter()
+                .map(|option| format!("*,--{},*", option))
+                .collect::<Vec<_>>()
+                .join("|");
+            script
+                + &fill(BASH, "|")
+                    .replace("@FUNCTION@", &function)
+                    .replace("@OPTIONS@", &options)
+        }
+        Shell::Zsh => {
+            // The entries are the positional "entry" arguments and the values
+            // of the options named "ENTRY", like "reboot --into".
+            let script = script
+                .lines()
+                .map(|line| {
+                    let entry = line.starts_with("':entry")
+                        || line.starts_with("'::entry")
+                        || line.contains(":ENTRY:_default'");
+                    match line.strip_suffix(":_default' \\") {
+                        Some(head) if entry => format!("{}:_sdboot_cli_entries' \\\n", head),
+                        _ => format!("{}\n", line),
+                    }
+                })
+                .collect::<String>();
+            match script.split_once("autoload -U is-at-least\n") {
+                Some((head, tail)) => {
+                    format!(
+                        "{}autoload -U is-at-least\n{}{}",
+                        head,
+                        fill(ZSH, " "),
+                        tail
+                    )
+                }
+                None => script,
+            }
+        }
+        Shell::Fish => script + &fill(FISH, " "),
+        _ => script,
+    }
+}
+
+/// Lists the long options taking values which may precede an entry, i.e.
+/// the global ones and the ones of the entry subcommands.
+fn value_options(command: &clap::Command) -> Vec<&str> {
+    let subcommands = ENTRY_SUBCOMMANDS
+        .iter()
+        .filter_map(|name| command.find_subcommand(name));
+    std::iter::once(command)
+        .chain(subcommands)
+        .flat_map(|command| command.get_arguments())
+        .filter(|arg| arg.get_action().takes_values())
+        .filter_map(|arg| arg.get_long())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::{Arg, Command};
+
+    fn command() -> Command {
+        Command::new("sdboot-cli")
+            .arg(Arg::new("format").long("format").global(true))
+            .subcommand(
+                Command::new("set-oneshot")
+                    .alias("so")
+                    .arg(Arg::new("entry"))
+                    .arg(Arg::new("in").long("in")),
+            )
+            .subcommand(Command::new("set-default").arg(Arg::new("entry").required(true)))
+            .subcommand(
+                Command::new("reboot").arg(Arg::new("into").long("into").value_name("ENTRY")),
+            )
+    }
+
+    #[test]
+    fn check_generate() {
+        assert_eq!(value_options(&command()), ["format", "in"]);
+
+        let bash = generate(Shell::Bash, &mut command());
+        assert!(bash.contains("set-oneshot|so|set-default|reboot)"));
+        assert!(bash.contains("*,--format,*|*,--in,*|"));
+        assert!(bash.contains("$(sdboot-cli __complete-entries 2>/dev/null)"));
+
+        let zsh = generate(Shell::Zsh, &mut command());
+        assert!(zsh.contains("_sdboot_cli_entries() {"));
+        assert!(zsh.contains("'::entry:_sdboot_cli_entries' \\\n"));
+        assert!(zsh.contains(":ENTRY:_sdboot_cli_entries'"));
+
+        let fish = generate(Shell::Fish, &mut command());
+        assert!(fish.contains("__fish_seen_subcommand_from set-oneshot so set-default"));
+    }
+}
@@ -0,0 +1,152 @@
+//! The configuration file with the defaults of the command line options.
+//!
+//! The system-wide `/etc/sdboot/config.toml` is read first, then the user's
+//! `~/.config/sdboot/config.toml` overrides it, and the command line
+//! overrides both:
+//!
+//! ```toml
+//! color = "off"
+//! format = "json"
+//! # Never ask for confirmations.
+//! yes = true
+//! # What to do after setting the one shot entry: "never", "ask" or "always".
+//! reboot = "ask"
+//!
+//! [aliases]
+//! win = "auto-windows"
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// The system-wide configuration file.
+const SYSTEM_CONFIG: &str = "/etc/sdboot/config.toml";
+
+/// Whether to reboot after setting the one shot entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RebootAfterOneshot {
+    /// Don't reboot.
+    #[default]
+    Never,
+    /// Ask whether to reboot now.
+    Ask,
+    /// Reboot, after the confirmation unless "yes" is set.
+    Always,
+}
+
+/// The configuration file contents. Unset values keep the built-in defaults.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The default of "--color".
+    pub color: Option<String>,
+    /// The default of "--format".
+    pub format: Option<String>,
+    /// The default of "--yes".
+    pub yes: Option<bool>,
+    /// The default of "--quiet".
+    pub quiet: Option<bool>,
+    /// Whether to reboot after setting the one shot entry.
+    pub reboot: Option<RebootAfterOneshot>,
+    /// Short names of the entries, e.g. "win" for "auto-windows".
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Reads the system-wide and the user's configuration files, the missing
+    /// ones are skipped.
+    pub fn load() -> Result<Self> {
+        let mut config = Self::read(Path::new(SYSTEM_CONFIG))?.unwrap_or_default();
+        if let Some(path) = user_config() {
+            if let Some(user) = Self::read(&path)? {
+                config = config.merge(user);
+            }
+        }
+        Ok(config)
+    }
+
+    fn read(path: &Path) -> Result<Option<Self>> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).with_context(|| format!("Unable to read {}", path.display())),
+        };
+        toml::from_str(&contents)
+            .map(Some)
+            .with_context(|| format!("Invalid configuration file {}", path.display()))
+    }
+
+    /// Overrides the values with the ones set in `other`.
+    fn merge(mut self, other: Self) -> Self {
+        self.aliases.extend(other.aliases);
+        Self {
+            color: other.color.or(self.color),
+            format: other.format.or(self.format),
+            yes: other.yes.or(self.yes),
+            quiet: other.quiet.or(self.quiet),
+            reboot: other.reboot.or(self.reboot),
+            aliases: self.aliases,
+        }
+    }
+
+    /// Replaces the entry alias with the entry name.
+    pub fn resolve_alias<'a>(&'a self, entry: &'a str) -> &'a str {
+        self.aliases.get(entry).map_or(entry, String::as_str)
+    }
+}
+
+/// The user's configuration file: `$XDG_CONFIG_HOME/sdboot/config.toml`,
+/// falling back to `~/.config`.
+fn user_config() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+    Some(config_home.join("sdboot").join("config.toml"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_merge() {
+        let system: Config = toml::from_str(
+            r#"
+            format = "json"
+            yes = true
+            [aliases]
+            win = "auto-windows"
+            "#,
+        )
+        .unwrap();
+        let user: Config = toml::from_str(
+            r#"
+            yes = false
+            reboot = "ask"
+            [aliases]
+            arch = "arch-lts.conf"
+            "#,
+        )
+        .unwrap();
+        let config = system.merge(user);
+        assert_eq!(config.format.as_deref(), Some("json"));
+        assert_eq!(config.yes, Some(false));
+        assert_eq!(config.reboot, Some(RebootAfterOneshot::Ask));
+        assert_eq!(config.resolve_alias("win"), "auto-windows");
+        assert_eq!(config.resolve_alias("arch"), "arch-lts.conf");
+        assert_eq!(config.resolve_alias("fedora"), "fedora");
+
+        assert!(toml::from_str::<Config>("colour = \"on\"").is_err());
+    }
+}
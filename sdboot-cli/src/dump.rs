@@ -0,0 +1,58 @@
+//! The variables listing printed by the "dump" subcommand.
+
+use std::fmt::Write as _;
+
+use sdboot::dump::Variable;
+use serde_json::{json, Value};
+
+/// Bytes per line of the hex dumps.
+const HEX_LINE: usize = 16;
+
+/// Renders the variables with their attributes, sizes and decoded values.
+pub fn render(variables: &[Variable]) -> String {
+    let mut output = String::new();
+    for variable in variables {
+        let _ = writeln!(
+            output,
+            "{} ({}; {} bytes)",
+            variable.name,
+            variable.attribute_names().join(", "),
+            variable.value.len()
+        );
+        match variable.as_strings() {
+            Some(strings) => {
+                for string in strings {
+                    let _ = writeln!(output, "    {:?}", string);
+                }
+            }
+            None => {
+                for line in variable.value.chunks(HEX_LINE) {
+                    let line = line
+                        .iter()
+                        .map(|byte| format!("{:02x}", byte))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let _ = writeln!(output, "    {}", line);
+                }
+            }
+        }
+    }
+    output
+}
+
+/// Lists the variables as a JSON array.
+pub fn to_json(variables: &[Variable]) -> Value {
+    variables
+        .iter()
+        .map(|variable| {
+            json!({
+                "name": variable.name,
+                "attributes": variable.attributes,
+                "attribute_names": variable.attribute_names(),
+                "size": variable.value.len(),
+                "strings": variable.as_strings(),
+                "hex": variable.hex(),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,60 @@
+//! Re-running the command as root when it lacks the privileges to change the
+//! variables, which is what desktop users expect instead of a bare
+//! "permission denied".
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, ExitCode},
+};
+
+use anyhow::{Context, Result};
+
+/// Picks the tool to run the command as root with: pkexec in a graphical
+/// session, where it shows an authentication dialog, and sudo otherwise.
+pub fn tool() -> Option<&'static str> {
+    if nix::unistd::geteuid().is_root() {
+        return None;
+    }
+    let graphical = ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|variable| std::env::var_os(variable).is_some());
+    let candidates: &[&'static str] = if graphical {
+        &["pkexec", "sudo"]
+    } else {
+        &["sudo", "pkexec"]
+    };
+    candidates
+        .iter()
+        .copied()
+        .find(|tool| find_in_path(tool).is_some())
+}
+
+/// Re-runs the current command line through the tool, with "--no-elevate"
+/// so it doesn't try again, and returns its exit code.
+pub fn rerun(tool: &str) -> Result<ExitCode> {
+    let program = std::env::current_exe().context("Unable to locate the executable")?;
+    let status = Command::new(tool)
+        .arg(program)
+        .arg("--no-elevate")
+        .args(std::env::args_os().skip(1))
+        .status()
+        .with_context(|| format!("Unable to run {}", tool))?;
+    Ok(status
+        .code()
+        .and_then(|code| u8::try_from(code).ok())
+        .map_or(ExitCode::FAILURE, ExitCode::from))
+}
+
+fn find_in_path(program: &str) -> Option<PathBuf> {
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|directory| directory.join(program))
+        .find(|path| is_executable(path))
+}
+
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
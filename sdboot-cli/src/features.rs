@@ -0,0 +1,109 @@
+//! The feature lists printed by the "features" subcommand.
+
+use std::fmt::Write as _;
+
+use sdboot::{LoaderFeatures, StubFeatures};
+use serde_json::{json, Value};
+
+/// A feature set, with all the known features and whether they are present.
+struct Set {
+    bits: u64,
+    features: Vec<(&'static str, bool)>,
+}
+
+impl From<LoaderFeatures> for Set {
+    fn from(set: LoaderFeatures) -> Self {
+        Self {
+            bits: set.bits(),
+            features: LoaderFeatures::NAMES
+                .iter()
+                .map(|(feature, name)| (*name, set.contains(*feature)))
+                .collect(),
+        }
+    }
+}
+
+impl From<StubFeatures> for Set {
+    fn from(set: StubFeatures) -> Self {
+        Self {
+            bits: set.bits(),
+            features: StubFeatures::NAMES
+                .iter()
+                .map(|(feature, name)| (*name, set.contains(*feature)))
+                .collect(),
+        }
+    }
+}
+
+/// Renders the known features of the boot loader and of the stub (if a UKI
+/// has been booted), checking the supported ones.
+pub fn render(loader: Option<LoaderFeatures>, stub: Option<StubFeatures>) -> String {
+    let mut output = String::new();
+    section(&mut output, "Boot loader", loader.map(Set::from));
+    if let Some(stub) = stub {
+        let _ = writeln!(output);
+        section(&mut output, "Stub", Some(stub.into()));
+    }
+    output
+}
+
+fn section(output: &mut String, name: &str, set: Option<Set>) {
+    let Some(set) = set else {
+        let _ = writeln!(output, "{} features: not reported", name);
+        return;
+    };
+    let _ = writeln!(output, "{} features ({:#x}):", name, set.bits);
+    for (feature, supported) in set.features {
+        let _ = writeln!(
+            output,
+            "  [{}] {}",
+            if supported { 'x' } else { ' ' },
+            feature
+        );
+    }
+}
+
+/// The features as `{"loader": {"bits": ..., "features": [...]}, "stub": ...}`,
+/// listing only the supported ones.
+pub fn to_json(loader: Option<LoaderFeatures>, stub: Option<StubFeatures>) -> Value {
+    let set = |set: Set| {
+        json!({
+            "bits": set.bits,
+            "features": set
+                .features
+                .into_iter()
+                .filter(|(_, supported)| *supported)
+                .map(|(feature, _)| feature)
+                .collect::<Vec<_>>(),
+        })
+    };
+    json!({
+        "loader": loader.map(|loader| set(loader.into())),
+        "stub": stub.map(|stub| set(stub.into())),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_render() {
+        let loader = LoaderFeatures::from_bits(0b1001);
+        let rendered = render(Some(loader), None);
+        assert!(rendered.starts_with(
+            "Boot loader features (0x9):\n  \
+             [x] config-timeout\n  \
+             [ ] config-timeout-oneshot\n"
+        ));
+        assert!(!rendered.contains("Stub"));
+        assert_eq!(
+            to_json(Some(loader), None),
+            json!({
+                "loader": {"bits": 9, "features": ["config-timeout", "entry-oneshot"]},
+                "stub": null,
+            })
+        );
+        assert_eq!(render(None, None), "Boot loader features: not reported\n");
+    }
+}
@@ -0,0 +1,190 @@
+//! Running the command on many hosts at once over SSH, e.g. to reboot a CI
+//! farm into a test kernel.
+
+use std::{
+    path::Path,
+    process::{Command, Stdio},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+
+use crate::remote;
+
+/// The result of the command on a host.
+pub struct Outcome {
+    pub host: String,
+    /// The exit code, [None] if ssh couldn't be run or was killed.
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Outcome {
+    pub fn succeeded(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+/// Reads the hosts, one per line, skipping the empty lines and the comments
+/// starting with "#".
+pub fn read_hosts(path: &Path) -> Result<Vec<String>> {
+    let hosts = std::fs::read_to_string(path)
+        .with_context(|| format!("Unable to read {}", path.display()))?;
+    Ok(parse_hosts(&hosts))
+}
+
+fn parse_hosts(hosts: &str) -> Vec<String> {
+    hosts
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|host| !host.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Runs the CLI with the arguments on the hosts, at most `jobs` at once. The
+/// remote commands can't ask for confirmations or passwords.
+pub fn run(hosts: &[String], remote_cli: &str, args: &[String], jobs: usize) -> Vec<Outcome> {
+    let args = std::iter::once("--yes".to_string())
+        .chain(args.iter().cloned())
+        .collect::<Vec<_>>();
+    let pending = Mutex::new(hosts.iter().enumerate());
+    let outcomes = Mutex::new(Vec::with_capacity(hosts.len()));
+    std::thread::scope(|scope| {
+        for _ in 0..jobs.clamp(1, hosts.len().max(1)) {
+            scope.spawn(|| loop {
+                let next = pending
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .next();
+                let Some((index, host)) = next else {
+                    break;
+                };
+                let outcome = run_on(host, remote_cli, &args);
+                log::debug!(
+                    "{}: {}",
+                    host,
+                    if outcome.succeeded() {
+                        "done"
+                    } else {
+                        "failed"
+                    }
+                );
+                outcomes
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner())
+                    .push((index, outcome));
+            });
+        }
+    });
+    let mut outcomes = outcomes
+        .into_inner()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    // In the order of the hosts file.
+    outcomes.sort_by_key(|(index, _)| *index);
+    outcomes.into_iter().map(|(_, outcome)| outcome).collect()
+}
+
+fn run_on(host: &str, remote_cli: &str, args: &[String]) -> Outcome {
+    let output = Command::new("ssh")
+        .args(["-o", "BatchMode=yes", host, "--"])
+        .arg(remote::shell_command(remote_cli, args))
+        .stdin(Stdio::null())
+        .output();
+    match output {
+        Ok(output) => Outcome {
+            host: host.to_string(),
+            code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        },
+        Err(e) => Outcome {
+            host: host.to_string(),
+            code: None,
+            stdout: String::new(),
+            stderr: format!("Unable to run ssh: {}", e),
+        },
+    }
+}
+
+/// Renders a line per host: "ok", or the exit code and the last line of the
+/// error output.
+pub fn render(outcomes: &[Outcome]) -> String {
+    let width = outcomes
+        .iter()
+        .map(|outcome| outcome.host.chars().count())
+        .max()
+        .unwrap_or_default();
+    outcomes
+        .iter()
+        .map(|outcome| {
+            let result = if outcome.succeeded() {
+                "ok".to_string()
+            } else {
+                let code = outcome.code.map_or_else(
+                    || "no exit code".to_string(),
+                    |code| format!("exit {}", code),
+                );
+                match outcome
+                    .stderr
+                    .lines()
+                    .rev()
+                    .find(|line| !line.trim().is_empty())
+                {
+                    Some(error) => format!("failed ({}): {}", code, error.trim()),
+                    None => format!("failed ({})", code),
+                }
+            };
+            format!("{:width$}  {}\n", outcome.host, result, width = width)
+        })
+        .collect()
+}
+
+/// Lists the outcomes as a JSON array.
+pub fn to_json(outcomes: &[Outcome]) -> Value {
+    outcomes
+        .iter()
+        .map(|outcome| {
+            json!({
+                "host": outcome.host,
+                "success": outcome.succeeded(),
+                "exit_code": outcome.code,
+                "stdout": outcome.stdout,
+                "stderr": outcome.stderr,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_hosts() {
+        let hosts = parse_hosts("# CI farm\nroot@lab-1\n\n  lab-2  # spare\n");
+        assert_eq!(hosts, ["root@lab-1", "lab-2"]);
+
+        let outcomes = [
+            Outcome {
+                host: "root@lab-1".into(),
+                code: Some(0),
+                stdout: String::new(),
+                stderr: String::new(),
+            },
+            Outcome {
+                host: "lab-2".into(),
+                code: Some(2),
+                stdout: String::new(),
+                stderr: "Error: There is no entry \"test\"\n".into(),
+            },
+        ];
+        assert_eq!(
+            render(&outcomes),
+            "root@lab-1  ok\n\
+             lab-2       failed (exit 2): Error: There is no entry \"test\"\n"
+        );
+    }
+}
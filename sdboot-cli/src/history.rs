@@ -0,0 +1,66 @@
+//! The boots listing printed by the "history show" subcommand.
+
+use std::time::{Duration, UNIX_EPOCH};
+
+use sdboot::history::BootRecord;
+use serde_json::{json, Value};
+
+use crate::{log_file::timestamp, status::seconds};
+
+/// Renders the boots as a table, the most recent first.
+pub fn render(records: &[BootRecord]) -> String {
+    let rows = records.iter().map(|record| {
+        [
+            timestamp(UNIX_EPOCH + Duration::from_secs(record.timestamp)),
+            record.selected.clone().unwrap_or_else(|| "-".into()),
+            record.default.clone().unwrap_or_else(|| "-".into()),
+            if record.is_non_default() { "yes" } else { "no" }.to_string(),
+            seconds(record.timings.loader_usec()),
+        ]
+    });
+    crate::table::render(["TIME", "ENTRY", "DEFAULT", "NON-DEFAULT", "LOADER"], rows)
+}
+
+/// Lists the boots as a JSON array, the most recent first.
+pub fn to_json(records: &[BootRecord]) -> Value {
+    records
+        .iter()
+        .map(|record| {
+            json!({
+                "timestamp": record.timestamp,
+                "boot_id": record.boot_id,
+                "selected": record.selected,
+                "default": record.default,
+                "non_default": record.is_non_default(),
+                "timings": record.timings,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use sdboot::BootTimings;
+
+    use super::*;
+
+    #[test]
+    fn check_render() {
+        let records = [BootRecord {
+            timestamp: 1_714_564_800,
+            boot_id: None,
+            selected: Some("arch-lts.conf".into()),
+            default: Some("arch.conf".into()),
+            timings: BootTimings {
+                init_usec: Some(1_000_000),
+                exec_usec: Some(2_500_000),
+                menu_usec: None,
+            },
+        }];
+        assert_eq!(
+            render(&records),
+            "TIME                  ENTRY          DEFAULT    NON-DEFAULT  LOADER\n\
+             2024-05-01T12:00:00Z  arch-lts.conf  arch.conf  yes          1.500s\n"
+        );
+    }
+}
@@ -0,0 +1,165 @@
+//! HTTP service for managing machines remotely.
+//!
+//! Every request must carry the `Authorization: Bearer <token>` header. The
+//! server speaks plain HTTP, so it should either listen on a trusted network
+//! or sit behind a TLS-terminating reverse proxy.
+//!
+//! | Method   | Path       | Body                  | Reply                   |
+//! |----------|------------|-----------------------|-------------------------|
+//! | `GET`    | `/status`  |                       | the boot loader status  |
+//! | `GET`    | `/entries` |                       | `{"entries": [...]}`    |
+//! | `GET`    | `/oneshot` |                       | `{"entry": ...}`        |
+//! | `PUT`    | `/oneshot` | `{"entry": "..."}`    | `{}`                    |
+//! | `DELETE` | `/oneshot` |                       | `{}`                    |
+//! | `GET`    | `/default` |                       | `{"entry": ...}`        |
+//! | `PUT`    | `/default` | `{"entry": "..."}`    | `{}`                    |
+//! | `DELETE` | `/default` |                       | `{}`                    |
+
+use std::io::Read;
+
+use anyhow::{Context, Result};
+use sdboot::{
+    service::{self, Request},
+    Backend, ErrorKind,
+};
+use serde_json::{json, Value};
+use tiny_http::{Header, Method, Response, Server};
+
+/// Default listening address.
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8465";
+
+/// Requests with larger bodies are rejected.
+const MAX_BODY_SIZE: u64 = 64 * 1024;
+
+/// Serves the requests on the given address, one at a time. Never returns
+/// unless the server can't be started.
+pub fn serve(backend: &mut Backend, address: &str, token: &str) -> Result<()> {
+    anyhow::ensure!(!token.is_empty(), "The authentication token is empty");
+    let server = Server::http(address)
+        .map_err(|e| anyhow::anyhow!("{}", e))
+        .with_context(|| format!("Unable to listen on {}", address))?;
+    let content_type = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+        .map_err(|()| anyhow::anyhow!("Invalid Content-Type header"))?;
+    log::info!("Serving HTTP requests at {}", address);
+
+    for mut request in server.incoming_requests() {
+        let (status, body) = if is_authorized(&request, token) {
+            handle(backend, &mut request)
+        } else {
+            log::warn!(
+                "Unauthorized {} {} from {:?}",
+                request.method(),
+                request.url(),
+                request.remote_addr()
+            );
+            (401, error_body("Unauthorized", "Missing or invalid token"))
+        };
+        let response = Response::from_string(body.to_string())
+            .with_status_code(status)
+            .with_header(content_type.clone());
+        if let Err(e) = request.respond(response) {
+            log::warn!("Unable to send the response: {:#}", e);
+        }
+    }
+    Ok(())
+}
+
+fn is_authorized(request: &tiny_http::Request, token: &str) -> bool {
+    request
+        .headers()
+        .iter()
+        .filter(|header| header.field.equiv("Authorization"))
+        .filter_map(|header| header.value.as_str().strip_prefix("Bearer "))
+        .any(|provided| constant_time_eq(provided.trim().as_bytes(), token.as_bytes()))
+}
+
+/// Compares the byte strings in time independent of their contents.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .fold(0, |difference, (l, r)| difference | (l ^ r))
+            == 0
+}
+
+fn handle(backend: &mut Backend, request: &mut tiny_http::Request) -> (u16, Value) {
+    let request = match parse_request(request) {
+        Ok(request) => request,
+        Err((status, body)) => return (status, body),
+    };
+    log::debug!("HTTP request {:?}", request);
+    match service::handle(backend, request) {
+        Ok(reply) => (200, reply),
+        Err(e) => {
+            log::warn!("Request failed: {:#}", e);
+            let (status, name) = match ErrorKind::of(&e) {
+                Some(ErrorKind::EntryNotFound) => (404, "EntryNotFound"),
+                Some(ErrorKind::VariableNotSet) => (404, "VariableNotSet"),
+                Some(ErrorKind::PermissionDenied) => (403, "PermissionDenied"),
+                Some(ErrorKind::OneshotNotSupported) => (409, "OneshotNotSupported"),
+                Some(ErrorKind::ReadOnly) => (503, "ReadOnly"),
+                Some(ErrorKind::NoSpace) => (507, "NoSpace"),
+                Some(ErrorKind::NotSystemdBoot) => (503, "NotSystemdBoot"),
+                _ => (500, "Failed"),
+            };
+            (status, error_body(name, &format!("{:#}", e)))
+        }
+    }
+}
+
+/// Maps the HTTP request to a service request.
+fn parse_request(request: &mut tiny_http::Request) -> Result<Request, (u16, Value)> {
+    let method = request.method().clone();
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let request = match (method, path.trim_end_matches('/')) {
+        (Method::Get, "/status") => Request::GetStatus {},
+        (Method::Get, "/entries") => Request::ListEntries {},
+        (Method::Get, "/oneshot") => Request::GetOneshot {},
+        (Method::Put, "/oneshot") => Request::SetOneshot {
+            entry: read_entry(request)?,
+        },
+        (Method::Delete, "/oneshot") => Request::RemoveOneshot {},
+        (Method::Get, "/default") => Request::GetDefault {},
+        (Method::Put, "/default") => Request::SetDefault {
+            entry: read_entry(request)?,
+        },
+        (Method::Delete, "/default") => Request::RemoveDefault {},
+        (_, "/status" | "/entries" | "/oneshot" | "/default") => {
+            return Err((405, error_body("MethodNotAllowed", "Method not allowed")))
+        }
+        _ => return Err((404, error_body("NotFound", "No such resource"))),
+    };
+    Ok(request)
+}
+
+/// Reads the `{"entry": "..."}` body.
+fn read_entry(request: &mut tiny_http::Request) -> Result<String, (u16, Value)> {
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_SIZE)
+        .read_to_end(&mut body)
+        .map_err(|e| (400, error_body("BadRequest", &e.to_string())))?;
+    let value: Value = serde_json::from_slice(&body)
+        .map_err(|e| (400, error_body("BadRequest", &e.to_string())))?;
+    value
+        .get("entry")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| {
+            (
+                400,
+                error_body("BadRequest", r#"Expected {"entry": "..."}"#),
+            )
+        })
+}
+
+fn error_body(name: &str, message: &str) -> Value {
+    json!({ "error": name, "message": message })
+}
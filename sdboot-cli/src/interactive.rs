@@ -1,244 +1,915 @@
-//! Interactive mode helper.
+//! Interactive mode.
 
+use std::{borrow::Cow, io::IsTerminal, path::PathBuf};
+
+use anyhow::{Context, Result};
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use rustyline::{
     completion::{Completer, Pair},
+    error::ReadlineError,
     highlight::Highlighter,
     hint::Hinter,
+    history::DefaultHistory,
     validate::Validator,
-    Helper,
+    Editor, Helper,
 };
+use sdboot::{parse::fuzzy_match, Backend, Entries, Entry};
 
-/// A [Helper] implementation.
-pub struct RustylineHelper {
-    entries: Vec<String>,
+use crate::{config::Config, EntryCommand, LoaderCommand, NotSet, Options, SystemdBootCommand};
+
+/// A line of the interactive mode: one of the boot loader commands of the CLI,
+/// parsed by the same grammar, which also drives the completion and the help,
+/// or one of the commands of the interactive mode itself.
+#[derive(Parser)]
+#[command(multicall = true, help_template = "Commands:\n{subcommands}")]
+enum Line {
+    #[command(flatten)]
+    Loader(LoaderCommand),
+
+    /// Re-reads the entries, e.g. after a kernel update.
+    Reload,
+
+    /// Walks through the changes step by step, with numbered menus.
+    Wizard,
+
+    /// Exits the interactive mode.
+    Exit,
 }
 
-impl RustylineHelper {
-    /// Creates a new helper from the given entries.
-    pub fn new<Entries>(entries: Entries) -> Self
-    where
-        Entries: IntoIterator,
-        Entries::Item: Into<String>,
-    {
-        let entries = entries.into_iter().map(Into::into).collect::<Vec<_>>();
-        Self { entries }
+impl Line {
+    /// Parses the input line. The help requested with "help" or "--help"
+    /// comes as an error as well.
+    fn parse(line: &str) -> Result<Self, clap::Error> {
+        Self::try_parse_from(line.split_whitespace())
+    }
+
+    /// The grammar, with the "help" subcommand.
+    fn grammar() -> clap::Command {
+        let mut command = Self::command();
+        command.build();
+        command
     }
 }
 
-impl Hinter for RustylineHelper {
-    type Hint = String;
+/// Where the commands come from.
+enum Input {
+    /// The line editor, with the completion and the history.
+    Terminal {
+        editor: Box<Editor<RustylineHelper, DefaultHistory>>,
+        history: Option<PathBuf>,
+    },
+    /// The lines piped in, e.g. by a script, without prompts or colors.
+    Batch(std::io::Lines<std::io::StdinLock<'static>>),
 }
 
-impl Highlighter for RustylineHelper {}
+impl Input {
+    /// Uses the line editor if stdin is a terminal, or reads it line by line
+    /// otherwise.
+    fn open(entries: &Entries, config: &Config) -> Result<Self> {
+        if !std::io::stdin().is_terminal() {
+            return Ok(Input::Batch(std::io::stdin().lines()));
+        }
+        let mut editor = Box::new(
+            Editor::<RustylineHelper, DefaultHistory>::new()
+                .context("Unable to initialize the line editor")?,
+        );
+        editor.set_helper(Some(
+            RustylineHelper::new(entries.iter().cloned()).with_aliases(config.aliases.clone()),
+        ));
+        let history = history_file();
+        if let Some(history) = &history {
+            match editor.load_history(history) {
+                Ok(()) => {}
+                Err(ReadlineError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => log::warn!("Unable to load the history {}: {:#}", history.display(), e),
+            }
+        }
+        Ok(Input::Terminal { editor, history })
+    }
 
-impl Validator for RustylineHelper {}
+    /// Reads the next line, [None] at the end of the input or on the second
+    /// Ctrl-C in a row. The first one only discards the typed line.
+    fn read_line(&mut self) -> Result<Option<String>> {
+        match self {
+            Input::Terminal { editor, .. } => {
+                let mut interrupted = false;
+                loop {
+                    match editor.readline("> ") {
+                        Ok(line) => {
+                            if !line.trim().is_empty() {
+                                if let Err(e) = editor.add_history_entry(line.as_str()) {
+                                    log::debug!("Unable to add the line to the history: {:#}", e);
+                                }
+                            }
+                            return Ok(Some(line));
+                        }
+                        Err(ReadlineError::Interrupted) if !interrupted => {
+                            interrupted = true;
+                            eprintln!(r#"(To exit, press Ctrl-C again or type "exit")"#);
+                        }
+                        Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(None),
+                        Err(e) => return Err(e).context("Unable to read the input"),
+                    }
+                }
+            }
+            Input::Batch(lines) => lines.next().transpose().context("Unable to read the input"),
+        }
+    }
 
-impl Helper for RustylineHelper {}
+    /// Reads the answer to the question, [None] on Ctrl-C or at the end of
+    /// the input. The piped answers are read without the question.
+    fn ask(&mut self, question: &str) -> Option<String> {
+        match self {
+            Input::Terminal { editor, .. } => editor.readline(question).ok(),
+            Input::Batch(lines) => lines.next()?.ok(),
+        }
+    }
+
+    /// Asks the yes/no question, in the batch mode as well.
+    fn agree(&mut self, question: &str) -> bool {
+        self.ask(&format!("{} [y/N] ", question))
+            .is_some_and(|answer| matches!(answer.trim(), "y" | "Y" | "yes"))
+    }
+
+    /// Shows the numbered menu and asks for the number until a valid one is
+    /// given. Returns the index of the chosen item, [None] if cancelled.
+    fn choose(&mut self, question: &str, items: &[String]) -> Option<usize> {
+        print!("{}", menu(items));
+        loop {
+            let answer = self.ask(&format!(
+                "{} [1-{}, empty to cancel] ",
+                question,
+                items.len()
+            ))?;
+            if answer.trim().is_empty() {
+                return None;
+            }
+            match choice(&answer, items.len()) {
+                Some(index) => return Some(index),
+                None => eprintln!("Please enter a number from 1 to {}", items.len()),
+            }
+        }
+    }
+
+    /// Updates the entries offered in the completion.
+    fn set_entries(&mut self, entries: &Entries) {
+        if let Input::Terminal { editor, .. } = self {
+            if let Some(helper) = editor.helper_mut() {
+                helper.entries = entries.iter().cloned().collect();
+            }
+        }
+    }
+
+    /// Saves the history, if any.
+    fn save_history(&mut self) {
+        let Input::Terminal {
+            editor,
+            history: Some(history),
+        } = self
+        else {
+            return;
+        };
+        let saved = match history.parent() {
+            Some(directory) => std::fs::create_dir_all(directory).map_err(ReadlineError::Io),
+            None => Ok(()),
+        }
+        .and_then(|()| editor.save_history(history));
+        if let Err(e) = saved {
+            log::warn!("Unable to save the history {}: {:#}", history.display(), e);
+        }
+    }
+}
+
+/// Runs the interactive mode until "exit", Ctrl-D or Ctrl-C pressed twice.
+/// Ctrl-C pressed while a command runs terminates the process once the
+/// variable being written is written, see
+/// `sdboot::restore_immutability_on_signals`. The commands are executed like
+/// the ones given on the command line, with the same options. The entries are
+/// listed on the start and on "reload", with the titles shown in the
+/// completion where available. The entry aliases from the configuration file
+/// are completed and resolved as well.
+///
+/// When stdin is not a terminal the commands are read from it line by line,
+/// skipping the comments starting with "#", and the first failed one stops
+/// the run with an error, unless `keep_going` is set.
+pub fn run(
+    backend: &mut Backend,
+    config: &Config,
+    options: Options,
+    keep_going: bool,
+) -> Result<()> {
+    let mut entries = crate::titled_entries(backend.entries())?;
+    let mut input = Input::open(&entries, config)?;
+
+    let mut reboot = false;
+    let mut line_number = 0;
+    let mut failures = 0;
+    while let Some(line) = input.read_line()? {
+        line_number += 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut rebooting = false;
+        let result = match Line::parse(line) {
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::DisplayHelp | ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand
+                ) =>
+            {
+                print!("{}", e.render());
+                Ok(())
+            }
+            Err(e) => {
+                let message = e.render().to_string();
+                Err(anyhow::anyhow!(
+                    "{}",
+                    message.trim_end().trim_start_matches("error: ")
+                ))
+            }
+            Ok(Line::Loader(mut command)) => {
+                crate::resolve_aliases(&mut command, config);
+                rebooting = reboots(&command);
+                // The commands may reboot right away.
+                input.save_history();
+                match crate::execute(backend, command, options) {
+                    // Unset variables are reported, like on the command line,
+                    // but don't fail the scripts.
+                    Err(e) if e.is::<NotSet>() => {
+                        log::info!("{}", e);
+                        Ok(())
+                    }
+                    result => result,
+                }
+            }
+            Ok(Line::Reload) => crate::titled_entries(backend.entries()).map(|reloaded| {
+                log::info!("{} entries found", reloaded.len());
+                input.set_entries(&reloaded);
+                entries = reloaded;
+            }),
+            Ok(Line::Wizard) => {
+                wizard(backend, &entries, &mut input).map(|reboot_now| reboot = reboot_now)
+            }
+            Ok(Line::Exit) => break,
+        };
+        match result {
+            Ok(()) if rebooting => break,
+            Ok(()) => {}
+            Err(mut e) => {
+                if let Input::Batch(_) = input {
+                    e = e.context(format!("Line {}", line_number));
+                    if !keep_going {
+                        return Err(e);
+                    }
+                    failures += 1;
+                }
+                log::error!("{:#}", e);
+            }
+        }
+        if reboot {
+            break;
+        }
+    }
+
+    input.save_history();
+    if reboot {
+        sdboot::power::reboot()?;
+    }
+    anyhow::ensure!(failures == 0, "{} of the commands failed", failures);
+    Ok(())
+}
+
+/// Whether the command reboots the system once it succeeds.
+fn reboots(command: &LoaderCommand) -> bool {
+    matches!(
+        command,
+        LoaderCommand::Entry(EntryCommand::Reboot { .. })
+            | LoaderCommand::SystemdBoot(
+                SystemdBootCommand::Menu { reboot: true }
+                    | SystemdBootCommand::FirmwareSetup { reboot: true }
+            )
+    )
+}
+
+/// What the wizard does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WizardAction {
+    Oneshot,
+    Default,
+    Unset,
+}
+
+const WIZARD_ACTIONS: [(WizardAction, &str); 3] = [
+    (
+        WizardAction::Oneshot,
+        "Boot an entry once, on the next boot",
+    ),
+    (WizardAction::Default, "Change the entry booted by default"),
+    (WizardAction::Unset, "Cancel booting an entry once"),
+];
+
+/// Walks the user through the action, the entry, the confirmation and the
+/// reboot, for the ones who don't know the entry identifiers or the
+/// commands. Returns whether to reboot.
+fn wizard(backend: &mut Backend, entries: &Entries, input: &mut Input) -> Result<bool> {
+    let actions = WIZARD_ACTIONS.map(|(_, label)| label.to_string());
+    let Some(index) = input.choose("What would you like to do?", &actions) else {
+        log::info!("Cancelled");
+        return Ok(false);
+    };
+    let action = WIZARD_ACTIONS[index].0;
+    if action == WizardAction::Unset {
+        if input.agree("Cancel booting an entry once?") {
+            backend.remove_oneshot()?;
+            log::info!("Oneshot entry unset");
+        } else {
+            log::info!("Cancelled");
+        }
+        return Ok(false);
+    }
+
+    let entries = entries.iter().collect::<Vec<_>>();
+    anyhow::ensure!(!entries.is_empty(), "There are no entries to choose from");
+    let labels = entries
+        .iter()
+        .map(|entry| match description(entry) {
+            Some(description) => format!("{} [{}]", description, entry.id()),
+            None => entry.id().to_string(),
+        })
+        .collect::<Vec<_>>();
+    let Some(index) = input.choose("Which entry?", &labels) else {
+        log::info!("Cancelled");
+        return Ok(false);
+    };
+    let entry = entries[index].id();
+    let question = if action == WizardAction::Oneshot {
+        format!("Boot {} once, on the next boot?", labels[index])
+    } else {
+        format!("Boot {} by default?", labels[index])
+    };
+    if !input.agree(&question) {
+        log::info!("Cancelled");
+        return Ok(false);
+    }
+    if action == WizardAction::Oneshot {
+        backend.set_oneshot(entry)?;
+        log::info!(r#"Oneshot entry set to "{}""#, entry);
+        Ok(input.agree("Reboot now?"))
+    } else {
+        backend.set_default(entry)?;
+        log::info!(r#"Default entry set to "{}""#, entry);
+        Ok(false)
+    }
+}
+
+/// Renders the menu, numbering the items from one.
+fn menu(items: &[String]) -> String {
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| format!("{:>3}) {}\n", index + 1, item))
+        .collect()
+}
+
+/// Parses the number chosen from the menu of `count` items, returns the index
+/// of the item.
+fn choice(answer: &str, count: usize) -> Option<usize> {
+    answer
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .filter(|number| (1..=count).contains(number))
+        .map(|number| number - 1)
+}
 
-/// Like `str::split`, but keeps positional information of every chunk.
-struct SplitPosition<'a> {
-    last_position: usize,
-    line: &'a str,
+/// The file keeping the commands of the previous sessions:
+/// `$XDG_STATE_HOME/sdboot/history`, falling back to `~/.local/state`.
+fn history_file() -> Option<PathBuf> {
+    let state_home = std::env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .filter(|dir| !dir.is_empty())
+                .map(|home| PathBuf::from(home).join(".local").join("state"))
+        })?;
+    Some(state_home.join("sdboot").join("history"))
 }
 
-impl<'a> Iterator for SplitPosition<'a> {
-    type Item = (usize, &'a str);
+/// A [Helper] implementation.
+pub struct RustylineHelper {
+    grammar: clap::Command,
+    entries: Vec<Entry>,
+    /// The entry aliases and the entries they stand for.
+    aliases: Vec<(String, String)>,
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.line.is_empty() {
+impl RustylineHelper {
+    /// Creates a new helper from the given entries.
+    pub fn new(entries: impl IntoIterator<Item = Entry>) -> Self {
+        Self {
+            grammar: Line::grammar(),
+            entries: entries.into_iter().collect(),
+            aliases: Vec::new(),
+        }
+    }
+
+    /// Completes the given entry aliases as well.
+    pub fn with_aliases(mut self, aliases: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.aliases = aliases.into_iter().collect();
+        self
+    }
+}
+
+// Colors of the recognized commands, the unknown ones and the hints.
+const KNOWN: &str = "\x1b[32m";
+const UNKNOWN: &str = "\x1b[90m";
+const HINT: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+impl Hinter for RustylineHelper {
+    type Hint = String;
+
+    /// Hints the rest of the most likely completion, if it continues the
+    /// typed text.
+    fn hint(&self, line: &str, pos: usize, ctx: &rustyline::Context<'_>) -> Option<String> {
+        if pos < line.len() {
             return None;
         }
-        if let Some((whitespace_position, whitespace_char)) =
-            self.line.char_indices().find(|(_, c)| c.is_whitespace())
+        let (start, candidates) = self.complete(line, pos, ctx).ok()?;
+        let typed = line.get(start..pos).filter(|typed| !typed.is_empty())?;
+        candidates.into_iter().find_map(|pair| {
+            pair.replacement
+                .strip_prefix(typed)
+                .filter(|rest| !rest.is_empty())
+                .map(str::to_string)
+        })
+    }
+}
+
+impl Highlighter for RustylineHelper {
+    /// Colors the command if it is recognized, and greys it out if it can't
+    /// become one.
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        let Some(&(index, command)) = words(line).first() else {
+            return Cow::Borrowed(line);
+        };
+        let color = if self.grammar.find_subcommand(command).is_some() {
+            KNOWN
+        } else if self
+            .grammar
+            .get_subcommands()
+            .any(|subcommand| subcommand.get_name().starts_with(command))
         {
-            let current_position = self.last_position;
-            self.last_position += whitespace_position + whitespace_char.len_utf8();
-
-            let (result, remaining) = self.line.split_at(whitespace_position);
-            let mut remaining = remaining.chars();
-            remaining.next().expect("This is the whitespace");
-            self.line = remaining.as_str();
-            Some((current_position, result))
+            // Still being typed.
+            return Cow::Borrowed(line);
         } else {
-            let what_remains = std::mem::take(&mut self.line);
-            Some((self.last_position, what_remains))
-        }
+            UNKNOWN
+        };
+        let end = index + command.len();
+        Cow::Owned(format!(
+            "{}{}{}{}{}",
+            &line[..index],
+            color,
+            command,
+            RESET,
+            &line[end..]
+        ))
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("{}{}{}", HINT, hint, RESET))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        true
     }
 }
 
-fn split_position(line: &str) -> SplitPosition<'_> {
-    SplitPosition {
-        last_position: 0,
-        line,
+impl Validator for RustylineHelper {}
+
+impl Helper for RustylineHelper {}
+
+/// Splits the line into the words separated by whitespace, along with their
+/// byte offsets.
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    // The end of the line terminates the last word.
+    for (index, c) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(index),
+            (Some(begin), true) => {
+                words.push((begin, &line[begin..index]));
+                start = None;
+            }
+            _ => {}
+        }
     }
+    words
 }
 
 impl Completer for RustylineHelper {
     type Candidate = Pair;
 
+    /// Walks the grammar along the words before the cursor, and completes the
+    /// word under it: a subcommand, an option or the value of an argument,
+    /// e.g. an entry.
     fn complete(
         &self,
         line: &str,
         pos: usize,
-        ctx: &rustyline::Context<'_>,
+        _ctx: &rustyline::Context<'_>,
     ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
-        let mut items = split_position(line).filter(|(_, part)| !part.is_empty());
-        let cmd = match items.next() {
-            Some((_index, item)) => item,
-            None => {
-                // No input yet
-                return Ok((
-                    0,
-                    vec![
-                        Pair {
-                            display: "set-oneshot — set oneshot entry".into(),
-                            replacement: "set-oneshot".into(),
-                        },
-                        Pair {
-                            display: "set-default — set default entry".into(),
-                            replacement: "set-default".into(),
-                        },
-                        Pair {
-                            display: "exit — exit the application".into(),
-                            replacement: "exit".into(),
-                        },
-                        Pair {
-                            display: "unset — removes the oneshot entry".into(),
-                            replacement: "unset".into(),
-                        },
-                    ],
-                ));
-            }
-        };
-        if pos <= 4 && "set-".starts_with(line) {
-            return Ok((
-                0,
-                vec![
-                    Pair {
-                        display: "set-oneshot — set oneshot entry".into(),
-                        replacement: "set-oneshot".into(),
-                    },
-                    Pair {
-                        display: "set-default — set default entry".into(),
-                        replacement: "set-default".into(),
-                    },
-                ],
-            ));
-        } else if pos < 5 && "unset".starts_with(line) {
-            return Ok((
-                0,
-                vec![Pair {
-                    display: "unset — removes the oneshot entry".into(),
-                    replacement: "unset".into(),
-                }],
-            ));
-        } else if pos < 4 && "exit".starts_with(line) {
-            return Ok((
-                0,
-                vec![Pair {
-                    display: "exit — exit the application".into(),
-                    replacement: "exit".into(),
-                }],
-            ));
-        } else if pos < 11 && "set-oneshot".starts_with(line) {
-            return Ok((
-                0,
-                vec![Pair {
-                    display: "set-oneshot — set oneshot entry".into(),
-                    replacement: "set-oneshot".into(),
-                }],
-            ));
-        } else if pos < 11 && "set-default".starts_with(line) {
-            return Ok((
-                0,
-                vec![Pair {
-                    display: "set-default — set default entry".into(),
-                    replacement: "set-default".into(),
-                }],
-            ));
-        }
-        match cmd {
-            "set-oneshot" | "set-default" => { /* No op */ }
-            "unset" | "exit" => {
-                // No arguments expected
-                return Ok((0, vec![]));
+        let mut command = &self.grammar;
+        // The option whose value comes next.
+        let mut option = None;
+        let mut positional = 0;
+        for (index, word) in words(line) {
+            if pos <= index + word.len() {
+                let typed = line.get(index..pos).unwrap_or_default();
+                let candidates = self.candidates(command, option, positional, typed);
+                return Ok((index.min(pos), candidates));
             }
-            _ => {
-                // Unknown argument
-                return Ok((0, vec![]));
+            if let Some(long) = word.strip_prefix("--") {
+                option = command
+                    .get_arguments()
+                    .find(|arg| arg.get_long() == Some(long))
+                    .filter(|arg| arg.get_action().takes_values());
+            } else if option.take().is_some() {
+                // The value of the option.
+            } else {
+                match command.find_subcommand(word) {
+                    Some(subcommand) if positional == 0 => command = subcommand,
+                    _ => positional += 1,
+                }
             }
         }
+        Ok((pos, self.candidates(command, option, positional, "")))
+    }
+}
 
-        if let Some((index, partial_entry)) = items.next() {
-            // User has begun to input an entry (either oneshot or default).
-            if let Some(relative_pos) = pos.checked_sub(index) {
-                if relative_pos == 0 {
-                    // Show all entries
-                    return Ok((
-                        12,
-                        self.entries
-                            .iter()
-                            .map(|entry| Pair {
-                                display: entry.into(),
-                                replacement: entry.into(),
-                            })
-                            .collect(),
-                    ));
-                }
-                if relative_pos <= partial_entry.len() {
-                    return Ok((
-                        12,
-                        self.entries
-                            .iter()
-                            .filter(|entry| entry.starts_with(partial_entry))
-                            .map(|entry| Pair {
-                                display: entry.into(),
-                                replacement: entry.into(),
-                            })
-                            .collect(),
-                    ));
-                }
-            }
-        } else {
-            // No entry yet!
-            return Ok((
-                11,
-                self.entries
-                    .iter()
-                    .map(|entry| Pair {
-                        display: entry.into(),
-                        replacement: format!(" {}", entry),
+impl RustylineHelper {
+    /// The candidates for the word being typed: the value of the option if it
+    /// is expected, the options if the word starts with a dash, the
+    /// subcommands of the command if it has any, or the value of its
+    /// positional argument.
+    fn candidates(
+        &self,
+        command: &clap::Command,
+        option: Option<&clap::Arg>,
+        positional: usize,
+        typed: &str,
+    ) -> Vec<Pair> {
+        if let Some(option) = option {
+            return self.value_candidates(option, typed);
+        }
+        if typed.starts_with('-') {
+            return command
+                .get_arguments()
+                .filter(|arg| !arg.is_hide_set())
+                .filter_map(|arg| {
+                    let long = format!("--{}", arg.get_long()?);
+                    long.starts_with(typed).then(|| Pair {
+                        display: match arg.get_help() {
+                            Some(help) => format!("{} — {}", long, help),
+                            None => long.clone(),
+                        },
+                        replacement: long,
                     })
-                    .collect(),
-            ));
+                })
+                .collect();
+        }
+        if command.has_subcommands() {
+            if positional > 0 {
+                return vec![];
+            }
+            return command
+                .get_subcommands()
+                .filter(|subcommand| {
+                    !subcommand.is_hide_set() && subcommand.get_name().starts_with(typed)
+                })
+                .map(|subcommand| Pair {
+                    display: match subcommand.get_about() {
+                        Some(about) => format!("{} — {}", subcommand.get_name(), about),
+                        None => subcommand.get_name().to_string(),
+                    },
+                    replacement: subcommand.get_name().to_string(),
+                })
+                .collect();
         }
+        match command.get_positionals().nth(positional) {
+            Some(arg) => self.value_candidates(arg, typed),
+            None => vec![],
+        }
+    }
+
+    /// The candidates for the value of the argument: the entries if it is
+    /// one, its possible values otherwise.
+    fn value_candidates(&self, arg: &clap::Arg, typed: &str) -> Vec<Pair> {
+        let is_entry = arg.get_id() == "entry"
+            || arg
+                .get_value_names()
+                .is_some_and(|names| names.iter().any(|name| name.as_str() == "ENTRY"));
+        if is_entry {
+            return self.entry_candidates(typed);
+        }
+        arg.get_possible_values()
+            .iter()
+            .filter(|value| !value.is_hide_set() && value.get_name().starts_with(typed))
+            .map(|value| Pair {
+                display: value.get_name().to_string(),
+                replacement: value.get_name().to_string(),
+            })
+            .collect()
+    }
+
+    /// The entries matching the typed text: the ones starting with it
+    /// first, then the ones containing it, then the ones containing its
+    /// characters in the same order, ignoring the case, and finally the ones
+    /// whose title and version match that way. The aliases starting with or
+    /// containing the text come before the entries of the same rank.
+    fn entry_candidates(&self, typed: &str) -> Vec<Pair> {
+        let typed_lowercase = typed.to_lowercase();
+        let aliases = self.aliases.iter().filter_map(|(alias, target)| {
+            let alias_lowercase = alias.to_lowercase();
+            let rank = if alias_lowercase.starts_with(&typed_lowercase) {
+                0
+            } else if alias_lowercase.contains(&typed_lowercase) {
+                1
+            } else {
+                return None;
+            };
+            let pair = Pair {
+                display: format!("{} → {}", alias, target),
+                replacement: alias.clone(),
+            };
+            Some((rank, pair))
+        });
+        let rank = |entry: &Entry| {
+            let id_lowercase = entry.id().to_lowercase();
+            if id_lowercase.starts_with(&typed_lowercase) {
+                Some(0)
+            } else if id_lowercase.contains(&typed_lowercase) {
+                Some(1)
+            } else if fuzzy_match(typed, entry.id()) {
+                Some(2)
+            } else if fuzzy_match(typed, &description(entry)?) {
+                Some(3)
+            } else {
+                None
+            }
+        };
+        let entries = self.entries.iter().filter_map(|entry| {
+            let pair = Pair {
+                display: match description(entry) {
+                    Some(description) => format!("{} — {}", entry.id(), description),
+                    None => entry.id().to_string(),
+                },
+                replacement: entry.id().to_string(),
+            };
+            Some((rank(entry)?, pair))
+        });
+        let mut candidates = aliases.chain(entries).collect::<Vec<_>>();
+        // The sort is stable, keeping the aliases first.
+        candidates.sort_by_key(|(rank, _)| *rank);
+        candidates.into_iter().map(|(_, pair)| pair).collect()
+    }
+}
 
-        let _ = (line, pos, ctx);
-        Ok((0, Vec::with_capacity(0)))
+/// The title of the entry with the version, e.g. "Arch Linux (6.5.3)".
+fn description(entry: &Entry) -> Option<String> {
+    match (entry.title(), entry.version()) {
+        (Some(title), Some(version)) => Some(format!("{} ({})", title, version)),
+        (Some(title), None) => Some(title.to_string()),
+        (None, Some(version)) => Some(format!("({})", version)),
+        (None, None) => None,
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::{TimeoutAction, TimeoutCommand};
 
     #[test]
-    fn check_split() {
-        let input = "set kek  löl    something-something";
-        let mut iter = split_position(input);
-        assert_eq!(Some((0, "set")), iter.next());
-        assert_eq!(Some((4, "kek")), iter.next());
-        assert_eq!(Some((8, "")), iter.next());
-        assert_eq!(Some((9, "löl")), iter.next());
-        assert_eq!(Some((14, "")), iter.next()); // "ö" contains two bytes, hence 14, not 13.
-        assert_eq!(Some((15, "")), iter.next());
-        assert_eq!(Some((16, "")), iter.next());
-        assert_eq!(Some((17, "something-something")), iter.next());
-        assert_eq!(None, iter.next());
-
-        let input = "set ";
-        let mut iter = split_position(input);
-        assert_eq!(Some((0, "set")), iter.next());
-        assert_eq!(None, iter.next());
+    fn check_words() {
+        // "ö" takes two bytes, hence 14 rather than 13.
+        assert_eq!(
+            words("set kek  löl    something-something"),
+            [
+                (0, "set"),
+                (4, "kek"),
+                (9, "löl"),
+                (17, "something-something")
+            ]
+        );
+        assert_eq!(words("set "), [(0, "set")]);
+        assert_eq!(words("  "), []);
+    }
+
+    #[test]
+    fn check_parse() {
+        assert!(matches!(
+            Line::parse(" set-oneshot  arch.conf "),
+            Ok(Line::Loader(LoaderCommand::Entry(EntryCommand::SetOneshot {
+                entry: Some(entry),
+                force: false,
+                ..
+            }))) if entry == "arch.conf"
+        ));
+        assert!(matches!(
+            Line::parse("unset"),
+            Ok(Line::Loader(LoaderCommand::Entry(EntryCommand::Unset)))
+        ));
+        assert!(matches!(
+            Line::parse("unset-default"),
+            Ok(Line::Loader(LoaderCommand::Entry(
+                EntryCommand::UnsetDefault
+            )))
+        ));
+        assert!(Line::parse("set-default").is_err());
+        assert!(Line::parse("exit now").is_err());
+        assert!(matches!(Line::parse("reload"), Ok(Line::Reload)));
+        assert!(matches!(
+            Line::parse("reboot --into arch.conf"),
+            Ok(Line::Loader(LoaderCommand::Entry(EntryCommand::Reboot {
+                into: Some(entry),
+                ..
+            }))) if entry == "arch.conf"
+        ));
+        assert!(Line::parse("reboot now please").is_err());
+        let help = Line::parse("help set-default").err().unwrap();
+        assert_eq!(help.kind(), ErrorKind::DisplayHelp);
+        assert!(help
+            .render()
+            .to_string()
+            .contains("set-default [OPTIONS] <ENTRY>"));
+        assert_eq!(
+            Line::parse("shutdown").err().unwrap().kind(),
+            ErrorKind::InvalidSubcommand
+        );
+        assert!(matches!(
+            Line::parse("timeout get"),
+            Ok(Line::Loader(LoaderCommand::SystemdBoot(
+                SystemdBootCommand::Timeout {
+                    command: TimeoutCommand::Persistent(TimeoutAction::Get)
+                }
+            )))
+        ));
+        assert!(matches!(
+            Line::parse("timeout oneshot set menu-hidden"),
+            Ok(Line::Loader(LoaderCommand::SystemdBoot(
+                SystemdBootCommand::Timeout {
+                    command: TimeoutCommand::Oneshot {
+                        action: TimeoutAction::Set {
+                            timeout: sdboot::Timeout::MenuHidden
+                        }
+                    }
+                }
+            )))
+        ));
+        assert!(Line::parse("timeout set").is_err());
+        assert!(Line::parse("timeout set soon").is_err());
+        assert!(reboots(&LoaderCommand::Entry(EntryCommand::Reboot {
+            into: None,
+            firmware: false,
+            menu: false
+        })));
+        assert!(!reboots(&LoaderCommand::SystemdBoot(
+            SystemdBootCommand::Menu { reboot: false }
+        )));
+    }
+
+    #[test]
+    fn check_menu() {
+        let items = ["Arch Linux (6.5.3) [arch.conf]", "auto-windows"].map(str::to_string);
+        assert_eq!(
+            menu(&items),
+            "  1) Arch Linux (6.5.3) [arch.conf]\n  2) auto-windows\n"
+        );
+        assert_eq!(choice(" 2 ", 2), Some(1));
+        assert_eq!(choice("0", 2), None);
+        assert_eq!(choice("3", 2), None);
+        assert_eq!(choice("arch", 2), None);
+    }
+
+    #[test]
+    fn check_hint_and_highlight() {
+        let helper = RustylineHelper::new([Entry::new("arch.conf")]);
+        let history = DefaultHistory::new();
+        let context = rustyline::Context::new(&history);
+        assert_eq!(helper.hint("set-o", 5, &context), Some("neshot".into()));
+        assert_eq!(
+            helper.hint("set-oneshot ar", 14, &context),
+            Some("ch.conf".into())
+        );
+        assert_eq!(helper.hint("set-oneshot acf", 15, &context), None);
+        assert_eq!(helper.hint("", 0, &context), None);
+
+        assert_eq!(
+            helper.highlight(" unset", 0),
+            format!(" {}unset{}", KNOWN, RESET)
+        );
+        assert_eq!(helper.highlight("uns", 0), "uns");
+        assert_eq!(
+            helper.highlight("shutdown now", 0),
+            format!("{}shutdown{} now", UNKNOWN, RESET)
+        );
+    }
+
+    #[test]
+    fn check_complete() {
+        let helper = RustylineHelper::new([
+            Entry::new("arch.conf")
+                .with_title("Arch Linux")
+                .with_version("6.5.3"),
+            Entry::new("auto-windows"),
+        ])
+        .with_aliases([("win".to_string(), "auto-windows".to_string())]);
+        let history = DefaultHistory::new();
+        let context = rustyline::Context::new(&history);
+        let complete = |line: &str| {
+            let (start, candidates) = helper.complete(line, line.len(), &context).unwrap();
+            let replacements = candidates
+                .into_iter()
+                .map(|pair| pair.replacement)
+                .collect::<Vec<_>>();
+            (start, replacements)
+        };
+        assert_eq!(
+            complete("uns"),
+            (0, vec!["unset".into(), "unset-default".into()])
+        );
+        assert_eq!(complete("unset-"), (0, vec!["unset-default".into()]));
+        assert_eq!(
+            complete("set-default a"),
+            (12, vec!["arch.conf".into(), "auto-windows".into()])
+        );
+        assert_eq!(complete("unset-default "), (14, vec![]));
+        assert_eq!(complete("so arc"), (3, vec!["arch.conf".into()]));
+        assert_eq!(
+            complete("help ex"),
+            (5, vec!["export".into(), "exit".into()])
+        );
+        assert_eq!(complete("reboot --in"), (7, vec!["--into".into()]));
+        assert_eq!(
+            complete("reboot --into win"),
+            (14, vec!["win".into(), "auto-windows".into()])
+        );
+        assert_eq!(complete("reboot --menu "), (14, vec![]));
+        assert_eq!(
+            complete("set-oneshot --force arc"),
+            (20, vec!["arch.conf".into()])
+        );
+        assert_eq!(
+            complete("get "),
+            (
+                4,
+                vec![
+                    "oneshot".into(),
+                    "default".into(),
+                    "selected".into(),
+                    "entries".into()
+                ]
+            )
+        );
+        assert_eq!(complete("timeout s"), (8, vec!["set".into()]));
+        assert_eq!(
+            complete("timeout set menu-"),
+            (
+                12,
+                vec![
+                    "menu-force".into(),
+                    "menu-hidden".into(),
+                    "menu-disabled".into()
+                ]
+            )
+        );
+        assert_eq!(complete("timeout get "), (12, vec![]));
+        assert_eq!(
+            complete("set-oneshot WIN"),
+            (12, vec!["win".into(), "auto-windows".into()])
+        );
+        assert_eq!(complete("set-oneshot acf"), (12, vec!["arch.conf".into()]));
+        assert_eq!(
+            complete("set-oneshot linux"),
+            (12, vec!["arch.conf".into()])
+        );
+
+        let (_, candidates) = helper.complete("set-default ", 12, &context).unwrap();
+        let displays = candidates
+            .iter()
+            .map(|pair| pair.display.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            displays,
+            [
+                "win → auto-windows",
+                "arch.conf — Arch Linux (6.5.3)",
+                "auto-windows — Windows Boot Manager"
+            ]
+        );
     }
 }
@@ -0,0 +1,193 @@
+//! The entries table printed by the "list" subcommand.
+
+use std::{cmp::Reverse, path::Path};
+
+use sdboot::{esp::EspEntry, parse::compare_versions, Entries};
+use serde_json::{json, Value};
+
+/// The entries to mark in the table.
+#[derive(Debug, Default)]
+pub struct Markers<'a> {
+    /// The default entry.
+    pub default: Option<&'a str>,
+    /// The currently booted entry.
+    pub selected: Option<&'a str>,
+    /// The one shot entry.
+    pub oneshot: Option<&'a str>,
+}
+
+impl Markers<'_> {
+    /// Lists the markers of the entry, like "default, selected".
+    pub fn of(&self, id: &str) -> String {
+        [
+            (self.default, "default"),
+            (self.selected, "selected"),
+            (self.oneshot, "oneshot"),
+        ]
+        .into_iter()
+        .filter(|(marked, _)| *marked == Some(id))
+        .map(|(_, marker)| marker)
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+/// The order to list the entries in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Sort {
+    /// The boot menu order.
+    Menu,
+    /// By the identifier.
+    Alpha,
+    /// The newest version first.
+    Version,
+    /// The most recently modified entry file first.
+    Mtime,
+}
+
+/// A row of the table.
+struct Row<'a> {
+    /// Position in the menu.
+    index: usize,
+    id: &'a str,
+    title: Option<&'a str>,
+    version: Option<&'a str>,
+    /// The entry file on the ESP.
+    path: Option<&'a Path>,
+}
+
+/// Collects the rows in the given order, with the titles and the versions
+/// taken from the entry files on the ESP when available. The entries without
+/// a version or a file go last when sorting by them.
+fn rows<'a>(entries: &'a Entries, esp_entries: &'a [EspEntry], sort: Sort) -> Vec<Row<'a>> {
+    let mut rows = entries
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            let esp_entry = esp_entries
+                .iter()
+                .find(|esp_entry| esp_entry.id == entry.id());
+            Row {
+                index,
+                id: entry.id(),
+                title: esp_entry
+                    .and_then(|esp_entry| esp_entry.title.as_deref())
+                    .or(entry.title()),
+                version: esp_entry
+                    .and_then(|esp_entry| esp_entry.version.as_deref())
+                    .or(entry.version()),
+                path: esp_entry.map(|esp_entry| esp_entry.path.as_path()),
+            }
+        })
+        .collect::<Vec<_>>();
+    match sort {
+        Sort::Menu => {}
+        Sort::Alpha => rows.sort_by_key(|row| row.id.to_lowercase()),
+        Sort::Version => rows.sort_by(|a, b| match (a.version, b.version) {
+            (Some(a), Some(b)) => compare_versions(b, a),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        }),
+        Sort::Mtime => rows.sort_by_cached_key(|row| {
+            Reverse(row.path.and_then(|path| {
+                std::fs::metadata(path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok()
+            }))
+        }),
+    }
+    rows
+}
+
+/// Lists the identifiers in the given order.
+pub fn ids<'a>(entries: &'a Entries, esp_entries: &'a [EspEntry], sort: Sort) -> Vec<&'a str> {
+    rows(entries, esp_entries, sort)
+        .into_iter()
+        .map(|row| row.id)
+        .collect()
+}
+
+/// Renders the entries in the given order as a table, numbered by their
+/// positions in the menu.
+pub fn render(
+    entries: &Entries,
+    esp_entries: &[EspEntry],
+    markers: &Markers,
+    sort: Sort,
+) -> String {
+    let rows = rows(entries, esp_entries, sort).into_iter().map(|row| {
+        [
+            row.index.to_string(),
+            row.id.to_string(),
+            row.title.unwrap_or("-").to_string(),
+            row.version.unwrap_or("-").to_string(),
+            markers.of(row.id),
+        ]
+    });
+    crate::table::render(["#", "ID", "TITLE", "VERSION", ""], rows)
+}
+
+/// Lists the entries in the given order as a JSON array.
+pub fn to_json(
+    entries: &Entries,
+    esp_entries: &[EspEntry],
+    markers: &Markers,
+    sort: Sort,
+) -> Value {
+    rows(entries, esp_entries, sort)
+        .into_iter()
+        .map(|row| {
+            json!({
+                "index": row.index,
+                "id": row.id,
+                "title": row.title,
+                "version": row.version,
+                "default": markers.default == Some(row.id),
+                "selected": markers.selected == Some(row.id),
+                "oneshot": markers.oneshot == Some(row.id),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use sdboot::Entry;
+
+    use super::*;
+
+    #[test]
+    fn check_render() {
+        let entries: Entries = ["arch.conf", "auto-windows"]
+            .into_iter()
+            .map(Entry::new)
+            .collect();
+        let esp_entries = [EspEntry {
+            id: "arch.conf".into(),
+            path: "/efi/loader/entries/arch.conf".into(),
+            title: Some("Arch Linux".into()),
+            version: Some("6.5.3".into()),
+            machine_id: None,
+            sort_key: None,
+            boot_counter: None,
+        }];
+        let markers = Markers {
+            default: Some("arch.conf"),
+            selected: Some("arch.conf"),
+            oneshot: Some("auto-windows"),
+        };
+        assert_eq!(
+            render(&entries, &esp_entries, &markers, Sort::Menu),
+            "#  ID            TITLE                 VERSION\n\
+             0  arch.conf     Arch Linux            6.5.3    default, selected\n\
+             1  auto-windows  Windows Boot Manager  -        oneshot\n"
+        );
+        assert_eq!(
+            ids(&entries, &esp_entries, Sort::Version),
+            ["arch.conf", "auto-windows"]
+        );
+        assert_eq!(
+            ids(&entries, &esp_entries, Sort::Alpha),
+            ["arch.conf", "auto-windows"]
+        );
+    }
+}
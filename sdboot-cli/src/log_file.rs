@@ -0,0 +1,73 @@
+//! The log file of unattended runs.
+
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+
+/// Creates the log output appending to the file, with every record
+/// timestamped.
+pub fn dispatch(path: &Path) -> Result<fern::Dispatch> {
+    let file = fern::log_file(path)
+        .with_context(|| format!("Unable to open the log file {}", path.display()))?;
+    Ok(fern::Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{} [{}] {}",
+                timestamp(SystemTime::now()),
+                record.level(),
+                message
+            ))
+        })
+        .chain(file))
+}
+
+/// Formats the time as UTC RFC 3339, e.g. "2024-05-01T12:00:00Z".
+pub fn timestamp(time: SystemTime) -> String {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let (days, seconds) = (seconds / 86_400, seconds % 86_400);
+
+    // Howard Hinnant's days_from_civil inverse, for days since 1970-01-01.
+    let days = days as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_timestamp() {
+        let at = |seconds| timestamp(UNIX_EPOCH + std::time::Duration::from_secs(seconds));
+        assert_eq!(at(0), "1970-01-01T00:00:00Z");
+        assert_eq!(at(951_827_696), "2000-02-29T12:34:56Z");
+        assert_eq!(at(1_714_564_800), "2024-05-01T12:00:00Z");
+    }
+}
@@ -1,7 +1,48 @@
+use std::{io::IsTerminal, process::ExitCode};
+
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use config::{Config, RebootAfterOneshot};
 use fern::colors::{Color, ColoredLevelConfig};
-use sdboot::Manager;
+use sdboot::{
+    backup::Backup,
+    esp::{Esp, EspEntry},
+    service::Status,
+    Backend, Entries, EntriesSource, Entry, ErrorKind, ImmutableAfterWrite, ImmutableHandling,
+    Lookup, Manager, OneshotCheck, Timeout,
+};
+use serde_json::json;
+
+mod boot_time;
+mod bootnext;
+mod completions;
+mod config;
+mod dump;
+#[cfg(target_os = "linux")]
+mod elevate;
+mod features;
+mod fleet;
+mod history;
+#[cfg(feature = "http")]
+mod http;
+mod interactive;
+mod list;
+mod log_file;
+mod plugins;
+mod remote;
+#[cfg(target_os = "linux")]
+mod restore_default;
+mod schedule;
+#[cfg(feature = "tui")]
+mod select;
+#[cfg(target_os = "linux")]
+mod socket;
+mod status;
+mod table;
+#[cfg(feature = "tui")]
+mod tui;
+#[cfg(target_os = "linux")]
+mod varlink;
 
 #[derive(PartialEq, Debug, clap::ValueEnum, Clone, Copy)]
 pub enum ColorMode {
@@ -10,29 +51,341 @@ pub enum ColorMode {
     Off,
 }
 
+/// Where the logs go.
+#[derive(PartialEq, Debug, clap::ValueEnum, Clone, Copy)]
+pub enum LogTarget {
+    /// The terminal.
+    Console,
+    /// The systemd journal, for unattended runs.
+    #[cfg(target_os = "linux")]
+    Journald,
+}
+
+/// How to print the results of the subcommands.
+#[derive(PartialEq, Debug, clap::ValueEnum, Clone, Copy)]
+pub enum Format {
+    /// Human readable text.
+    Plain,
+    /// JSON, for scripts. The logs go to stderr then.
+    Json,
+}
+
+/// How to deal with the "immutable" attribute of the variable files.
+#[derive(PartialEq, Debug, clap::ValueEnum, Clone, Copy)]
+pub enum ImmutableMode {
+    /// Fail if the attribute can't be removed.
+    Strict,
+    /// Warn if the attribute can't be removed, and try to write anyway.
+    Warn,
+    /// Don't touch the attribute.
+    Skip,
+}
+
+impl From<ImmutableMode> for ImmutableHandling {
+    fn from(mode: ImmutableMode) -> Self {
+        match mode {
+            ImmutableMode::Strict => ImmutableHandling::Strict,
+            ImmutableMode::Warn => ImmutableHandling::Warn,
+            ImmutableMode::Skip => ImmutableHandling::Skip,
+        }
+    }
+}
+
+/// What to do with the "immutable" attribute after writing the variables.
+#[derive(PartialEq, Debug, clap::ValueEnum, Clone, Copy)]
+pub enum AfterWriteMode {
+    /// Restore the attribute as it was.
+    Restore,
+    /// Leave the variable files mutable.
+    LeaveMutable,
+    /// Make the variable files immutable.
+    SetImmutable,
+}
+
+impl From<AfterWriteMode> for ImmutableAfterWrite {
+    fn from(mode: AfterWriteMode) -> Self {
+        match mode {
+            AfterWriteMode::Restore => ImmutableAfterWrite::Restore,
+            AfterWriteMode::LeaveMutable => ImmutableAfterWrite::LeaveMutable,
+            AfterWriteMode::SetImmutable => ImmutableAfterWrite::SetImmutable,
+        }
+    }
+}
+
+/// A variable to print with the "get" subcommand.
+#[derive(PartialEq, Debug, clap::ValueEnum, Clone, Copy)]
+pub enum Variable {
+    /// The one shot entry.
+    Oneshot,
+    /// The default entry.
+    Default,
+    /// The currently booted entry.
+    Selected,
+    /// The entries known to the boot loader, one per line.
+    Entries,
+}
+
+impl Variable {
+    /// How the variable is called in the messages.
+    fn description(self) -> &'static str {
+        match self {
+            Variable::Oneshot => "The one shot entry",
+            Variable::Default => "The default entry",
+            Variable::Selected => "The currently booted entry",
+            Variable::Entries => "The list of the entries",
+        }
+    }
+}
+
+/// The value printed for scripts is not set. It is reported by the
+/// [ErrorKind::VariableNotSet] exit code alone, nothing is printed.
+#[derive(Debug)]
+struct NotSet(&'static str);
+
+impl std::fmt::Display for NotSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} is not set", self.0)
+    }
+}
+
+impl std::error::Error for NotSet {}
+
+/// The exit codes, see [ErrorKind::code].
+const EXIT_CODES: &str = "\
+Exit codes:
+  0  Success
+  1  Other failures, including invalid arguments
+  2  The entry was not found
+  3  Permission denied
+  4  Not a UEFI system booted with systemd-boot
+  5  The variable is not set (\"get\")
+  6  The boot loader doesn't support one shot entries
+  7  The EFI variables are read-only
+  8  No space left in the NVRAM";
+
 /// A simple utility to manage systemd-boot oneshot entry.
 #[derive(Parser)]
-#[clap(version)]
+#[clap(version, after_help = EXIT_CODES, allow_external_subcommands = true)]
 struct Args {
     /// Be verbose.
     #[clap(long, short)]
     verbose: bool,
 
+    /// Refuse to set the one shot entry if the boot loader doesn't support it.
+    #[clap(long)]
+    check_features: bool,
+
     /// Set the color mode.
     #[clap(value_enum, long = "color", default_value_t = ColorMode::Auto)]
     color_mode: ColorMode,
 
+    /// Don't read and print the current state before executing the command.
+    #[clap(long, short, global = true, visible_alias = "no-status")]
+    quiet: bool,
+
+    /// Don't ask for confirmation before changing the default entry,
+    /// repairing the variables or rebooting.
+    #[clap(long, short, global = true, visible_alias = "no-confirm")]
+    yes: bool,
+
+    /// Don't offer to re-run the command with pkexec or sudo when it lacks
+    /// the privileges.
+    #[cfg(target_os = "linux")]
+    #[clap(long, global = true)]
+    no_elevate: bool,
+
+    /// Where to send the logs.
+    #[clap(value_enum, long, global = true, default_value_t = LogTarget::Console)]
+    log_target: LogTarget,
+
+    /// Additionally append the logs to the file.
+    #[clap(long, global = true, value_name = "PATH")]
+    log_file: Option<std::path::PathBuf>,
+
+    /// Set the output format.
+    #[clap(value_enum, long, global = true, default_value_t = Format::Plain)]
+    format: Format,
+
+    /// Read the entries from the ESP instead of the LoaderEntries variable.
+    /// They are read from there anyway if the variable is not set, e.g. when
+    /// the system hasn't been rebooted since systemd-boot was installed.
+    #[clap(long, global = true)]
+    entries_from_esp: bool,
+
+    /// How to deal with the "immutable" attribute of the variable files, e.g.
+    /// "skip" in containers where the attribute can't be changed.
+    #[clap(value_enum, long = "immutable", default_value_t = ImmutableMode::Strict)]
+    immutable_mode: ImmutableMode,
+
+    /// What to do with the "immutable" attribute of the variable files after
+    /// writing them.
+    #[clap(value_enum, long = "after-write", default_value_t = AfterWriteMode::Restore)]
+    after_write_mode: AfterWriteMode,
+
+    /// Send the requests to a privileged service (see "serve socket") through
+    /// the control socket instead of accessing the EFI variables directly.
+    #[cfg(target_os = "linux")]
+    #[clap(
+        long,
+        value_name = "SOCKET",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = socket::DEFAULT_SOCKET
+    )]
+    connect: Option<std::path::PathBuf>,
+
+    /// Run the command on the remote machine over SSH, e.g. "root@lab-1",
+    /// with the copy of the CLI installed there.
+    #[clap(long, global = true, value_name = "[USER@]HOST")]
+    host: Option<String>,
+
+    /// The CLI to run on the remote machine.
+    #[clap(long, global = true, value_name = "PATH", default_value = "sdboot-cli")]
+    remote_cli: String,
+
     #[command(subcommand)]
     command: Option<Command>,
 }
 
 #[derive(Subcommand)]
 enum Command {
+    #[command(flatten)]
+    Loader(LoaderCommand),
+
+    /// Restores the default entry recorded by "set-default
+    /// --until-next-boot", run by the unit it installs.
+    #[cfg(target_os = "linux")]
+    #[clap(name = restore_default::COMMAND, hide = true)]
+    RestoreDefault,
+
+    /// Checks the EFI variables can be written, i.e. the privileges, the
+    /// handling of the "immutable" attribute and the firmware, by writing,
+    /// reading back and removing a scratch variable of a private vendor. The
+    /// boot loader variables are not touched.
+    SelfTest,
+
+    /// Picks the one shot (or the default) entry in a full-screen list with
+    /// a fuzzy filter.
+    #[cfg(feature = "tui")]
+    Select {
+        /// Set the default entry instead.
+        #[clap(long)]
+        default: bool,
+    },
+
+    /// Runs the command on the hosts listed in the file, one per line, over
+    /// SSH in parallel and prints whether it succeeded on each of them, e.g.
+    /// "sdboot-cli fleet --hosts hosts.txt set-oneshot arch-test.conf". The
+    /// commands don't ask for confirmations.
+    Fleet {
+        /// The file listing the hosts.
+        #[clap(long, value_name = "FILE")]
+        hosts: std::path::PathBuf,
+
+        /// How many hosts to run the command on at once.
+        #[clap(long, short = 'j', default_value_t = 16)]
+        jobs: usize,
+
+        /// The command and its arguments.
+        #[clap(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+
+    /// Records the boots and shows the recorded ones.
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+    },
+
+    /// Runs a terminal dashboard showing the status and the entries, with
+    /// keys to set the one shot and the default entries, the timeout and to
+    /// reboot.
+    #[cfg(feature = "tui")]
+    Tui,
+
+    /// Runs an interactive shell. Short alias is "i". Reads the commands
+    /// line by line, without prompts, when stdin is not a terminal, stopping
+    /// at the first failed one.
+    #[clap(alias = "i")]
+    Interactive {
+        /// Keeps running the piped commands after a failure, still exiting
+        /// with an error in the end.
+        #[clap(short = 'k', long)]
+        keep_going: bool,
+    },
+
+    /// Prints the completion script for the shell, e.g.
+    /// "sdboot-cli completions bash > /etc/bash_completion.d/sdboot-cli".
+    Completions {
+        #[clap(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Prints the roff man page, or writes the man pages of the command and
+    /// its subcommands to the directory, for packagers.
+    #[clap(hide = true)]
+    Man {
+        /// The directory to write the man pages to.
+        #[clap(long, value_name = "DIR")]
+        out_dir: Option<std::path::PathBuf>,
+    },
+
+    /// Prints the entry identifiers and aliases for the completion scripts.
+    #[clap(name = completions::ENTRIES_COMMAND, hide = true)]
+    CompleteEntries,
+
+    /// Runs the "sdboot-<name>" executable in PATH, passing it the status as
+    /// JSON on stdin.
+    #[command(external_subcommand)]
+    External(Vec<String>),
+
+    /// Runs a service handling requests from other processes.
+    #[cfg(any(target_os = "linux", feature = "http"))]
+    Serve {
+        #[command(subcommand)]
+        protocol: ServeProtocol,
+    },
+}
+
+/// A command working on the boot loader state: the EFI variables of this
+/// machine, the ones behind the control socket or the GRUB environment block.
+#[derive(Subcommand)]
+enum LoaderCommand {
+    #[command(flatten)]
+    Entry(EntryCommand),
+
+    #[command(flatten)]
+    SystemdBoot(SystemdBootCommand),
+}
+
+/// A command on the one shot and the default entries, which works whatever
+/// the boot loader is.
+#[derive(Subcommand)]
+enum EntryCommand {
     /// Set one shot entry. Short alias is "so".
     #[clap(name = "set-oneshot", alias = "so")]
     SetOneshot {
-        /// New one shot entry name.
-        entry: String,
+        /// New one shot entry name. If omitted on a terminal, the entry is
+        /// chosen from a list.
+        entry: Option<String>,
+
+        /// Reboot after the given time, e.g. "30m" or "1h30m".
+        #[clap(
+            long = "in",
+            value_name = "DURATION",
+            value_parser = schedule::parse_span,
+            conflicts_with = "at"
+        )]
+        reboot_in: Option<schedule::When>,
+
+        /// Reboot at the given local time, e.g. "03:00".
+        #[clap(long, value_name = "HH:MM", value_parser = schedule::parse_time)]
+        at: Option<schedule::When>,
+
+        /// Write the entry name as given even if no entry matches it, e.g. a
+        /// glob pattern or an entry which is not installed yet.
+        #[clap(long)]
+        force: bool,
     },
 
     /// Set default entry. Short alias is "sd".
@@ -40,25 +393,542 @@ enum Command {
     SetDefault {
         /// New default entry name.
         entry: String,
+
+        /// Restore the current default entry after the next successful boot,
+        /// e.g. to run an image once as the default one. Installs a systemd
+        /// unit, which removes itself after the restore.
+        #[clap(long)]
+        until_next_boot: bool,
+
+        /// Write the entry name as given even if no entry matches it, e.g. a
+        /// glob pattern or an entry which is not installed yet.
+        #[clap(long)]
+        force: bool,
     },
 
     /// Removes the one shot entry.
     Unset,
+
+    /// Removes the default entry, so the one from the boot loader
+    /// configuration applies.
+    UnsetDefault,
+
+    /// Shows the firmware and boot loader information, the entries and the
+    /// boot timings. This is the default when no subcommand is given.
+    Status,
+
+    /// Reboots the system, optionally into the given entry, the boot menu or
+    /// the firmware setup.
+    Reboot {
+        /// Boot the entry once.
+        #[clap(long, value_name = "ENTRY")]
+        into: Option<String>,
+
+        /// Boot into the firmware setup.
+        #[clap(long)]
+        firmware: bool,
+
+        /// Show the boot menu.
+        #[clap(long)]
+        menu: bool,
+    },
+
+    /// Lists the entries in the menu order, marking the default, the
+    /// currently booted and the one shot entries.
+    List {
+        /// Print only the identifiers, each terminated by NUL, for
+        /// "xargs -0" and the like.
+        #[clap(long, short = '0')]
+        print0: bool,
+
+        /// The order to list the entries in.
+        #[clap(value_enum, long, default_value_t = list::Sort::Menu)]
+        sort: list::Sort,
+    },
+
+    /// Prints the raw value of the variable, for use in scripts. Prints
+    /// nothing and exits with code 5 if the variable is not set.
+    Get {
+        #[clap(value_enum)]
+        variable: Variable,
+
+        /// Terminate the values by NUL instead of a newline.
+        #[clap(long, short = '0')]
+        print0: bool,
+    },
+}
+
+/// A command which only works with systemd-boot on this machine.
+#[derive(Subcommand)]
+enum SystemdBootCommand {
+    /// Schedules setting the one shot entry and rebooting into it, e.g.
+    /// during a maintenance window, with a transient systemd timer.
+    #[cfg(target_os = "linux")]
+    Schedule {
+        /// The entry to reboot into.
+        entry: String,
+
+        /// When to reboot, as a systemd calendar time, e.g. "Sat 02:00" or
+        /// "2024-05-01 03:00".
+        #[clap(long, value_name = "TIME")]
+        at: String,
+    },
+
+    /// Prints the entry the system boots next: the one shot entry, else the
+    /// default one, the default of loader.conf or the first entry. Exits with
+    /// code 2 if there are no entries.
+    NextBoot,
+
+    /// Converges the system to the given state, writing only the variables
+    /// which differ, and reports whether each of them has changed. Meant for
+    /// configuration management.
+    #[clap(group = clap::ArgGroup::new("state").required(true).multiple(true))]
+    Ensure {
+        /// The default entry.
+        #[clap(long, value_name = "ENTRY", group = "state")]
+        default: Option<String>,
+
+        /// The boot menu timeout: seconds, "menu-force", "menu-hidden" or
+        /// "menu-disabled".
+        #[clap(long, value_parser = TimeoutParser, hide_possible_values = true, group = "state")]
+        timeout: Option<Timeout>,
+
+        /// Write the default entry as given even if no entry matches it, e.g.
+        /// a glob pattern.
+        #[clap(long, requires = "default")]
+        force: bool,
+    },
+
+    /// Sets the one shot entry to the one after the default (or the
+    /// currently booted) entry in the menu order.
+    Next,
+
+    /// Sets the one shot entry to the one before the default (or the
+    /// currently booted) entry in the menu order.
+    Prev,
+
+    /// Marks the currently booted entry as good, so the boot loader stops
+    /// counting its boot attempts.
+    Bless,
+
+    /// Lists the features the boot loader (and the stub of the booted unified
+    /// kernel image) supports, e.g. one shot entries or boot counting.
+    Features,
+
+    /// Reports how long the firmware and the boot loader took, and when the
+    /// kernel was started.
+    BootTime,
+
+    /// Lists the firmware boot manager options, or boots one of them once on
+    /// the next boot (BootNext), e.g. a Windows Boot Manager which isn't
+    /// managed by systemd-boot.
+    Bootnext {
+        /// The option to boot: its description, "Boot####" or number.
+        option: Option<String>,
+
+        /// Remove BootNext instead.
+        #[clap(long, conflicts_with = "option")]
+        unset: bool,
+    },
+
+    /// Shows the boot menu on the next boot, even if it is normally hidden.
+    Menu {
+        /// Reboot right away.
+        #[clap(long)]
+        reboot: bool,
+    },
+
+    /// Boots into the firmware setup UI on the next boot.
+    FirmwareSetup {
+        /// Reboot right away.
+        #[clap(long)]
+        reboot: bool,
+    },
+
+    /// Manages the boot menu timeout.
+    Timeout {
+        #[command(subcommand)]
+        command: TimeoutCommand,
+    },
+
+    /// Prints all the boot loader interface variables with their attributes
+    /// and values, for bug reports.
+    Dump,
+
+    /// Checks the boot loader interface variables for the correct
+    /// attributes, NUL termination, valid UTF-16 and valid identifiers.
+    /// Fails if any check fails.
+    Verify,
+
+    /// Rewrites the variables with wrong attributes or without the NUL
+    /// terminator, which the boot loader ignores.
+    Repair,
+
+    /// Saves the default and one shot entries and the menu timeouts as JSON,
+    /// to roll them back with "restore".
+    Backup {
+        /// The file to write, stdout if omitted.
+        file: Option<std::path::PathBuf>,
+    },
+
+    /// Restores the configuration saved with "backup".
+    Restore {
+        /// The file to read, stdin if omitted.
+        file: Option<std::path::PathBuf>,
+    },
+
+    /// Exports the boot loader interface variables to the file, e.g. to
+    /// exchange them with the UEFI shell's dmpstore tool.
+    Export {
+        /// The file to write.
+        file: std::path::PathBuf,
+
+        /// The format of the file.
+        #[clap(long = "as", value_enum, default_value_t = DumpFormat::Dmpstore)]
+        dump_format: DumpFormat,
+    },
+
+    /// Imports the variables the OS sets, i.e. the default and one shot
+    /// entries and the menu timeouts, from the file written by "export" or
+    /// "dmpstore -s". The variables missing from the file are removed.
+    Import {
+        /// The file to read.
+        file: std::path::PathBuf,
+
+        /// The format of the file.
+        #[clap(long = "as", value_enum, default_value_t = DumpFormat::Dmpstore)]
+        dump_format: DumpFormat,
+    },
+
+    /// Checks the boot loader state for problems, like a stale one shot
+    /// entry or entries missing on the ESP.
+    Check {
+        /// Remove the one shot entry if it is stale.
+        #[clap(long)]
+        clear: bool,
+    },
+}
+
+/// Formats of the variable dumps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum DumpFormat {
+    /// The binary format of the UEFI shell's "dmpstore -s".
+    Dmpstore,
+    /// A JSON array of the variables.
+    Json,
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// Records the current boot: the booted and the default entries and the
+    /// boot loader timings. Meant to run from a systemd unit at boot, every
+    /// boot is recorded once.
+    Record,
+    /// Shows the most recent boots, whether a non-default entry was booted,
+    /// e.g. a one shot one, and how long the boot loader took.
+    Show {
+        /// Number of the boots to show.
+        #[clap(long, short = 'n', default_value_t = 10)]
+        count: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum TimeoutCommand {
+    #[command(flatten)]
+    Persistent(TimeoutAction),
+
+    /// Manages the boot menu timeout for the next boot only.
+    Oneshot {
+        #[command(subcommand)]
+        action: TimeoutAction,
+    },
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+enum TimeoutAction {
+    /// Prints the timeout. Prints nothing and exits with code 5 if it is not
+    /// set.
+    Get,
+
+    /// Sets the timeout.
+    Set {
+        /// Seconds, "menu-force", "menu-hidden" or "menu-disabled".
+        #[clap(value_parser = TimeoutParser, hide_possible_values = true)]
+        timeout: Timeout,
+    },
+
+    /// Removes the timeout, so the one from the boot loader configuration
+    /// applies.
+    Unset,
 }
 
-fn main() -> Result<()> {
+#[cfg(any(target_os = "linux", feature = "http"))]
+#[derive(Subcommand)]
+enum ServeProtocol {
+    /// Serves the io.github.sdboot varlink interface.
+    #[cfg(target_os = "linux")]
+    Varlink {
+        /// Path of the listening socket.
+        #[clap(long, default_value = varlink::DEFAULT_SOCKET)]
+        socket: std::path::PathBuf,
+
+        /// Permissions of the socket, in octal. Whoever can connect may
+        /// change the variables.
+        #[clap(long, default_value = "600", value_parser = parse_mode)]
+        mode: u32,
+
+        /// Group owning the socket.
+        #[clap(long)]
+        group: Option<String>,
+    },
+
+    /// Serves the JSON-lines control protocol for "--connect" clients.
+    #[cfg(target_os = "linux")]
+    Socket {
+        /// Path of the listening socket.
+        #[clap(long, default_value = socket::DEFAULT_SOCKET)]
+        socket: std::path::PathBuf,
+
+        /// Permissions of the socket, in octal. Whoever can connect may
+        /// change the variables.
+        #[clap(long, default_value = "660", value_parser = parse_mode)]
+        mode: u32,
+
+        /// Group owning the socket.
+        #[clap(long)]
+        group: Option<String>,
+    },
+
+    /// Serves a REST API over HTTP, authenticated with a bearer token.
+    #[cfg(feature = "http")]
+    Http {
+        /// Address to listen on.
+        #[clap(long, default_value = http::DEFAULT_ADDRESS)]
+        listen: String,
+
+        /// The token clients must present.
+        #[clap(long, env = "SDBOOT_HTTP_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+
+        /// Read the token from the file instead.
+        #[clap(long, conflicts_with = "token")]
+        token_file: Option<std::path::PathBuf>,
+    },
+}
+
+fn parse_timeout(timeout: &str) -> Result<Timeout, String> {
+    timeout.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// Parses the timeouts, offering the named ones to the completion.
+#[derive(Clone)]
+struct TimeoutParser;
+
+impl clap::builder::TypedValueParser for TimeoutParser {
+    type Value = Timeout;
+
+    fn parse_ref(
+        &self,
+        command: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Timeout, clap::Error> {
+        parse_timeout.parse_ref(command, arg, value)
+    }
+
+    fn possible_values(
+        &self,
+    ) -> Option<Box<dyn Iterator<Item = clap::builder::PossibleValue> + '_>> {
+        let named = [
+            Timeout::MenuForce,
+            Timeout::MenuHidden,
+            Timeout::MenuDisabled,
+        ];
+        Some(Box::new(named.into_iter().map(|timeout| {
+            clap::builder::PossibleValue::new(timeout.to_string())
+        })))
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn parse_mode(mode: &str) -> Result<u32, std::num::ParseIntError> {
+    u32::from_str_radix(mode, 8)
+}
+
+fn main() -> ExitCode {
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    // Clap exits with 2 on invalid arguments, which is reserved for
+    // ErrorKind::EntryNotFound.
+    let args = match with_defaults(Args::command(), &config)
+        .try_get_matches()
+        .and_then(|matches| Args::from_arg_matches(&matches))
+    {
+        Ok(args) => args,
+        Err(e) if e.use_stderr() => {
+            let _ = e.print();
+            return ExitCode::FAILURE;
+        }
+        Err(e) => e.exit(),
+    };
+    if let Some(host) = &args.host {
+        return remote::rerun(host, &args.remote_cli).unwrap_or_else(|e| {
+            eprintln!("Error: {:?}", e);
+            ExitCode::FAILURE
+        });
+    }
+    // The service behind the control socket has its own privileges.
+    #[cfg(target_os = "linux")]
+    let (elevate, yes) = (!args.no_elevate && args.connect.is_none(), args.yes);
+    match run(args, &config) {
+        Ok(code) => code,
+        #[cfg(target_os = "linux")]
+        Err(e) if elevate && ErrorKind::of(&e) == Some(ErrorKind::PermissionDenied) => {
+            eprintln!("Error: {:#}", e);
+            match elevate::tool() {
+                Some(tool) if yes || ask(&format!("Retry with {}?", tool)) => elevate::rerun(tool)
+                    .unwrap_or_else(|e| {
+                        eprintln!("Error: {:?}", e);
+                        ExitCode::FAILURE
+                    }),
+                _ => exit_code(&e),
+            }
+        }
+        Err(e) if e.is::<NotSet>() => exit_code(&e),
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            exit_code(&e)
+        }
+    }
+}
+
+/// Whether the command draws on the whole terminal, so the logs must not be
+/// printed there.
+fn takes_over_terminal(command: &Option<Command>) -> bool {
+    match command {
+        #[cfg(feature = "tui")]
+        Some(Command::Tui) => true,
+        _ => false,
+    }
+}
+
+/// The exit code for the error, see [EXIT_CODES].
+fn exit_code(e: &anyhow::Error) -> ExitCode {
+    ErrorKind::of(e)
+        .or_else(|| e.is::<NotSet>().then_some(ErrorKind::VariableNotSet))
+        .and_then(|kind| u8::try_from(kind.code()).ok())
+        .map_or(ExitCode::FAILURE, ExitCode::from)
+}
+
+/// Replaces the defaults of the options with the values from the
+/// configuration file, so the command line still overrides them.
+fn with_defaults(mut command: clap::Command, config: &Config) -> clap::Command {
+    let defaults = [
+        ("color_mode", config.color.clone()),
+        ("format", config.format.clone()),
+        ("yes", config.yes.map(|yes| yes.to_string())),
+        ("quiet", config.quiet.map(|quiet| quiet.to_string())),
+    ];
+    for (id, value) in defaults {
+        if let Some(value) = value {
+            command = command.mut_arg(id, |arg| arg.default_value(value));
+        }
+    }
+    command
+}
+
+/// Replaces the entry aliases from the configuration file with the entry
+/// names.
+fn resolve_aliases(command: &mut LoaderCommand, config: &Config) {
+    let entry = match command {
+        LoaderCommand::Entry(
+            EntryCommand::SetOneshot {
+                entry: Some(entry), ..
+            }
+            | EntryCommand::SetDefault { entry, .. }
+            | EntryCommand::Reboot {
+                into: Some(entry), ..
+            },
+        )
+        | LoaderCommand::SystemdBoot(SystemdBootCommand::Ensure {
+            default: Some(entry),
+            ..
+        }) => entry,
+        #[cfg(target_os = "linux")]
+        LoaderCommand::SystemdBoot(SystemdBootCommand::Schedule { entry, .. }) => entry,
+        _ => return,
+    };
+    let resolved = config.resolve_alias(entry);
+    if resolved != entry {
+        log::debug!(r#"Alias "{}" stands for "{}""#, entry, resolved);
+        *entry = resolved.to_string();
+    }
+}
+
+/// Schedules the reboot requested after setting the one shot entry, or
+/// reboots right away if configured to.
+fn reboot_after_oneshot(
+    when: Option<schedule::When>,
+    reboot: RebootAfterOneshot,
+    yes: bool,
+) -> Result<()> {
+    if let Some(when) = when {
+        return schedule::reboot(when);
+    }
+    match reboot {
+        RebootAfterOneshot::Never => return Ok(()),
+        RebootAfterOneshot::Ask if !ask("Reboot now?") => return Ok(()),
+        RebootAfterOneshot::Ask => {}
+        RebootAfterOneshot::Always => ensure_confirmed(yes, "Reboot now?")?,
+    }
+    sdboot::power::reboot()
+}
+
+/// Asks a yes/no question if there is a terminal to ask on, otherwise it's a
+/// no.
+fn ask(question: &str) -> bool {
+    std::io::stdin().is_terminal() && confirm(question).unwrap_or(false)
+}
+
+fn run(args: Args, config: &Config) -> Result<ExitCode> {
     let Args {
         verbose,
+        quiet,
+        yes,
+        check_features,
         command,
         color_mode,
-    } = Args::parse();
+        log_target,
+        log_file,
+        format,
+        entries_from_esp,
+        immutable_mode,
+        after_write_mode,
+        #[cfg(target_os = "linux")]
+        connect,
+        #[cfg(target_os = "linux")]
+            no_elevate: _,
+        // Handled in main.
+        host: _,
+        remote_cli,
+    } = args;
 
     let colorful_logs = match color_mode {
         ColorMode::Auto => {
             #[cfg(target_os = "linux")]
             {
                 use std::os::unix::io::AsRawFd;
-                nix::unistd::isatty(std::io::stdout().as_raw_fd()).unwrap_or(false)
+                let fd = match format {
+                    Format::Plain => std::io::stdout().as_raw_fd(),
+                    Format::Json => std::io::stderr().as_raw_fd(),
+                };
+                nix::unistd::isatty(fd).unwrap_or(false)
             }
             #[cfg(not(target_os = "linux"))]
             {
@@ -89,69 +959,1307 @@ fn main() -> Result<()> {
         })
     };
 
-    fern::Dispatch::new()
-        .format(formatter)
-        .level(if verbose {
-            log::LevelFilter::Debug
-        } else {
-            log::LevelFilter::Info
-        })
-        .chain(std::io::stdout())
-        .apply()
-        .context("Unable to initialize logging")?;
+    // Keep stdout clean for the JSON and NUL-separated output.
+    let print0 = matches!(
+        command,
+        Some(Command::Loader(LoaderCommand::Entry(
+            EntryCommand::List { print0: true, .. } | EntryCommand::Get { print0: true, .. }
+        )))
+    );
+    let output: fern::Output = match format {
+        Format::Plain if !print0 => std::io::stdout().into(),
+        _ => std::io::stderr().into(),
+    };
+    let level = if verbose {
+        log::LevelFilter::Debug
+    } else {
+        log::LevelFilter::Info
+    };
+    let dispatch = match log_target {
+        LogTarget::Console => fern::Dispatch::new().chain(
+            fern::Dispatch::new()
+                .format(formatter)
+                .level(if takes_over_terminal(&command) {
+                    log::LevelFilter::Off
+                } else {
+                    level
+                })
+                .chain(output),
+        ),
+        // With the structured fields intact (hence no formatting).
+        #[cfg(target_os = "linux")]
+        LogTarget::Journald => fern::Dispatch::new().chain(
+            fern::Dispatch::new()
+                .level(level)
+                .chain(sdboot::journald::logger()?),
+        ),
+    };
 
-    let mut manager = Manager::new();
+    // Variable changes are additionally recorded in the journal.
+    #[cfg(target_os = "linux")]
+    let dispatch = match (log_target, sdboot::journald::logger()) {
+        (LogTarget::Journald, _) => dispatch,
+        (LogTarget::Console, Ok(journal)) => dispatch.chain(
+            fern::Dispatch::new()
+                .filter(|metadata| metadata.target() == sdboot::change::LOG_TARGET)
+                .chain(journal),
+        ),
+        (LogTarget::Console, Err(e)) => {
+            eprintln!("Changes won't be logged to the journal: {:#}", e);
+            dispatch
+        }
+    };
 
-    if let Some(name) = manager.get_default_entry()? {
-        log::info!(r#"Default entry: "{name}""#);
-    } else {
-        log::info!("Default entry: not set");
+    let dispatch = match log_file {
+        Some(path) => dispatch.chain(log_file::dispatch(&path)?.level(level)),
+        None => dispatch,
+    };
+
+    dispatch.apply().context("Unable to initialize logging")?;
+
+    let options = Options {
+        format,
+        quiet,
+        yes,
+        reboot: config.reboot.unwrap_or_default(),
+    };
+    let open_manager = || {
+        // Don't leave the variables mutable if interrupted in the middle of
+        // an update.
+        #[cfg(target_os = "linux")]
+        if let Err(e) = sdboot::restore_immutability_on_signals() {
+            log::warn!("{:#}", e);
+        }
+        Manager::new()
+            .with_feature_check(check_features)
+            .with_entries_source(if entries_from_esp {
+                EntriesSource::Esp
+            } else {
+                EntriesSource::LoaderOrEsp
+            })
+            .with_immutable_handling(immutable_mode.into())
+            .with_immutable_after_write(after_write_mode.into())
+    };
+    let open_backend = || -> Result<Backend> {
+        let backend = Backend::detect(open_manager())?;
+        match &backend {
+            Backend::SystemdBoot(_) => ensure_uefi()?,
+            Backend::Grub(grub) => {
+                log::debug!("Using GRUB environment block {}", grub.path().display())
+            }
+        }
+        Ok(backend)
+    };
+    // For the commands which only work on this machine.
+    let local_backend = |what: &str| -> Result<Backend> {
+        #[cfg(target_os = "linux")]
+        anyhow::ensure!(
+            connect.is_none(),
+            "{} is not supported through the control socket",
+            what
+        );
+        open_backend()
+    };
+    // For the commands which only work on the EFI variables of this machine.
+    let local_manager = |what: &str| -> Result<Manager> {
+        match local_backend(what)? {
+            Backend::SystemdBoot(manager) => Ok(manager),
+            Backend::Grub(_) => anyhow::bail!("{} is not supported with GRUB", what),
+        }
+    };
+
+    let mut command = match command {
+        Some(Command::Loader(command)) => command,
+        None => LoaderCommand::Entry(EntryCommand::Status),
+        Some(Command::Completions { shell }) => {
+            print!("{}", completions::generate(shell, &mut Args::command()));
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Man { out_dir }) => {
+            let command = Args::command();
+            match out_dir {
+                Some(dir) => clap_mangen::generate_to(command, &dir).with_context(|| {
+                    format!("Unable to write the man pages to {}", dir.display())
+                })?,
+                None => clap_mangen::Man::new(command)
+                    .render(&mut std::io::stdout())
+                    .context("Unable to print the man page")?,
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::CompleteEntries) => {
+            // The completion scripts only need the identifiers, whatever
+            // goes wrong.
+            let manager = Manager::new().with_entries_source(EntriesSource::LoaderOrEsp);
+            if let Ok(entries) = manager.entries() {
+                entries.ids().for_each(|id| println!("{}", id));
+            }
+            config
+                .aliases
+                .keys()
+                .for_each(|alias| println!("{}", alias));
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Fleet {
+            hosts,
+            jobs,
+            command,
+        }) => {
+            let hosts = fleet::read_hosts(&hosts)?;
+            let outcomes = fleet::run(&hosts, &remote_cli, &command, jobs);
+            match format {
+                Format::Plain => print!("{}", fleet::render(&outcomes)),
+                Format::Json => println!("{:#}", fleet::to_json(&outcomes)),
+            }
+            let failed = outcomes
+                .iter()
+                .filter(|outcome| !outcome.succeeded())
+                .count();
+            anyhow::ensure!(failed == 0, "Failed on {} of {} hosts", failed, hosts.len());
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::External(args)) => {
+            // The plugins get the status even if it can't be fully collected.
+            let manager = Manager::new().with_entries_source(if entries_from_esp {
+                EntriesSource::Esp
+            } else {
+                EntriesSource::LoaderOrEsp
+            });
+            let (status, error) = match status::Report::collect(&manager) {
+                Ok(report) => (report.to_json(), None),
+                Err(e) => (serde_json::Value::Null, Some(format!("{:#}", e))),
+            };
+            let context = json!({
+                "cli": {
+                    "path": std::env::current_exe().ok(),
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+                "format": clap::ValueEnum::to_possible_value(&format)
+                    .map(|value| value.get_name().to_string()),
+                "status": status,
+                "error": error,
+            });
+            // Pass the exit code of the plugin through.
+            let status = plugins::run(&args, &context)?;
+            return Ok(status
+                .code()
+                .and_then(|code| u8::try_from(code).ok())
+                .map_or(ExitCode::FAILURE, ExitCode::from));
+        }
+        Some(Command::History {
+            command: HistoryCommand::Show { count },
+        }) => {
+            let records = sdboot::history::History::open_readable()?.last(count)?;
+            match format {
+                Format::Plain => print!("{}", history::render(&records)),
+                Format::Json => println!("{:#}", history::to_json(&records)),
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::History {
+            command: HistoryCommand::Record,
+        }) => {
+            let manager = local_manager("Recording the boots")?;
+            let history = sdboot::history::History::open_default()?;
+            match history.record(&manager)? {
+                Some(record) => log::info!(
+                    "Boot of {} recorded to {}",
+                    record.selected.as_deref().unwrap_or("an unknown entry"),
+                    history.path().display()
+                ),
+                None => log::info!("The boot has already been recorded"),
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::SelfTest) => {
+            #[cfg(target_os = "linux")]
+            anyhow::ensure!(
+                connect.is_none(),
+                "The self-test is not supported through the control socket"
+            );
+            // The scratch variable works whatever the boot loader is.
+            open_manager().self_test().context("Self-test failed")?;
+            print_done(format, "Self-test passed, the EFI variables can be written");
+            return Ok(ExitCode::SUCCESS);
+        }
+        #[cfg(target_os = "linux")]
+        Some(Command::RestoreDefault) => {
+            restore_default::restore(&mut local_manager("Restoring the default entry")?)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        #[cfg(feature = "tui")]
+        Some(Command::Select {
+            default: as_default,
+        }) => {
+            let mut backend = local_backend("The full-screen interface")?;
+            select(&mut backend, as_default, options)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        #[cfg(feature = "tui")]
+        Some(Command::Tui) => {
+            let mut manager = local_manager("The full-screen interface")?;
+            anyhow::ensure!(
+                std::io::stdout().is_terminal(),
+                "The dashboard needs a terminal"
+            );
+            tui::run(&mut manager, |manager| titled_entries(manager.entries()))?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Some(Command::Interactive { keep_going }) => {
+            let mut backend = local_backend("The interactive mode")?;
+            interactive::run(&mut backend, config, options, keep_going)?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        #[cfg(any(target_os = "linux", feature = "http"))]
+        Some(Command::Serve { protocol }) => {
+            let mut backend = local_backend("Serving requests")?;
+            match protocol {
+                #[cfg(target_os = "linux")]
+                ServeProtocol::Varlink {
+                    socket,
+                    mode,
+                    group,
+                } => varlink::serve(&mut backend, &socket, mode, group.as_deref())?,
+                #[cfg(target_os = "linux")]
+                ServeProtocol::Socket {
+                    socket,
+                    mode,
+                    group,
+                } => socket::serve(&mut backend, &socket, mode, group.as_deref())?,
+                #[cfg(feature = "http")]
+                ServeProtocol::Http {
+                    listen,
+                    token,
+                    token_file,
+                } => {
+                    let token = match (token, token_file) {
+                        (Some(token), _) => token,
+                        (None, Some(path)) => std::fs::read_to_string(&path)
+                            .with_context(|| format!("Unable to read {}", path.display()))?
+                            .trim()
+                            .to_string(),
+                        (None, None) => {
+                            anyhow::bail!(
+                                "Either --token, --token-file or SDBOOT_HTTP_TOKEN is required"
+                            )
+                        }
+                    };
+                    http::serve(&mut backend, &listen, &token)?
+                }
+            }
+            return Ok(ExitCode::SUCCESS);
+        }
+    };
+    resolve_aliases(&mut command, config);
+
+    #[cfg(target_os = "linux")]
+    if let Some(socket) = connect {
+        return run_client(&socket, command, options).map(|()| ExitCode::SUCCESS);
+    }
+
+    execute(&mut open_backend()?, command, options)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// The global options the boot loader commands run with.
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    format: Format,
+    quiet: bool,
+    yes: bool,
+    /// Whether to reboot after setting the one shot entry.
+    reboot: RebootAfterOneshot,
+}
+
+/// Fails with [ErrorKind::NotSystemdBoot] if the system is not booted in UEFI
+/// mode.
+fn ensure_uefi() -> Result<()> {
+    #[cfg(target_os = "linux")]
+    if !std::path::Path::new("/sys/firmware/efi").exists() {
+        return Err(anyhow::Error::new(ErrorKind::NotSystemdBoot)
+            .context("The system is not booted in UEFI mode"));
+    }
+    Ok(())
+}
+
+/// Executes the command on this machine, whichever the boot loader is.
+fn execute(backend: &mut Backend, command: LoaderCommand, options: Options) -> Result<()> {
+    match command {
+        LoaderCommand::Entry(command) => execute_entry(backend, command, options),
+        LoaderCommand::SystemdBoot(command) => match backend {
+            Backend::SystemdBoot(manager) => execute_systemd_boot(manager, command, options),
+            Backend::Grub(_) => Err(anyhow::Error::new(ErrorKind::NotSystemdBoot)
+                .context("Only the one shot and the default entries are supported with GRUB")),
+        },
+    }
+}
+
+/// Executes the command on the one shot and the default entries of this
+/// machine, whichever the boot loader is.
+fn execute_entry(backend: &mut Backend, command: EntryCommand, options: Options) -> Result<()> {
+    let Options {
+        format,
+        quiet,
+        yes,
+        reboot,
+    } = options;
+    match command {
+        EntryCommand::Status => match backend {
+            Backend::SystemdBoot(manager) => {
+                let report = status::Report::collect(manager)?;
+                match format {
+                    Format::Plain => print!("{}", report),
+                    Format::Json => println!("{:#}", report.to_json()),
+                }
+            }
+            Backend::Grub(_) => print_status(format, &Status::of(backend)?),
+        },
+        EntryCommand::Get { variable, print0 } => {
+            let value = match variable {
+                Variable::Oneshot => backend.get_oneshot()?,
+                Variable::Default => backend.get_default_entry()?,
+                Variable::Selected => backend.get_selected_entry()?,
+                Variable::Entries => {
+                    let entries = backend.entries().context("Unable to fetch entries")?;
+                    print_ids(format, entries.ids(), print0);
+                    return Ok(());
+                }
+            };
+            print_value(format, variable.description(), value, print0)?;
+        }
+        EntryCommand::SetOneshot {
+            entry,
+            reboot_in,
+            at,
+            force,
+        } => {
+            if !quiet {
+                log_current("One shot", backend.get_oneshot()?);
+            }
+            let entry = match entry {
+                Some(entry) => resolve_entry(backend.entries(), &entry, force)?,
+                None => pick_entry(backend.entries())?,
+            };
+            let changed = backend.set_oneshot_if_changed(entry.id())?;
+            if format == Format::Json {
+                println!("{:#}", json!({ "entry": entry.id(), "changed": changed }));
+            } else if changed {
+                log::info!("Oneshot entry set to {}", label(&entry));
+            } else {
+                log::info!("Oneshot entry is already set to {}", label(&entry));
+            }
+            reboot_after_oneshot(reboot_in.or(at), reboot, yes)?;
+        }
+        EntryCommand::SetDefault {
+            entry,
+            until_next_boot,
+            force,
+        } => {
+            let previous = backend.get_default_entry()?;
+            if !quiet {
+                log_current("Default entry", previous.clone());
+            }
+            let entry = resolve_entry(backend.entries(), &entry, force)?;
+            ensure_confirmed(yes, &format!("Set the default entry to {}?", label(&entry)))?;
+            if until_next_boot {
+                backend.manager("Restoring the default entry")?;
+                #[cfg(target_os = "linux")]
+                restore_default::install(previous.as_deref())?;
+                #[cfg(not(target_os = "linux"))]
+                anyhow::bail!("Restoring the default entry is only supported on linux");
+            }
+            let changed = backend.set_default_if_changed(entry.id())?;
+            if format == Format::Json {
+                println!("{:#}", json!({ "entry": entry.id(), "changed": changed }));
+            } else if changed {
+                log::info!("Default entry set to {}", label(&entry));
+            } else {
+                log::info!("Default entry is already set to {}", label(&entry));
+            }
+        }
+        EntryCommand::Unset => {
+            if !quiet {
+                log_current("One shot", backend.get_oneshot()?);
+            }
+            backend.remove_oneshot()?;
+            print_done(format, "Oneshot entry unset");
+        }
+        EntryCommand::UnsetDefault => {
+            if !quiet {
+                log_current("Default entry", backend.get_default_entry()?);
+            }
+            ensure_confirmed(yes, "Unset the default entry?")?;
+            backend.remove_default()?;
+            print_done(format, "Default entry unset");
+        }
+        EntryCommand::List { print0: true, sort } => {
+            let entries = backend.entries().context("Unable to fetch entries")?;
+            let esp_entries = match sort {
+                list::Sort::Menu => Vec::new(),
+                _ => esp_entries(),
+            };
+            print_ids(
+                format,
+                list::ids(&entries, &esp_entries, sort).into_iter(),
+                true,
+            );
+        }
+        EntryCommand::List {
+            print0: false,
+            sort,
+        } => {
+            let entries = backend.entries().context("Unable to fetch entries")?;
+            let default = backend.get_default_entry()?;
+            let selected = backend.get_selected_entry()?;
+            let oneshot = backend.get_oneshot()?;
+            let markers = list::Markers {
+                default: default.as_deref(),
+                selected: selected.as_deref(),
+                oneshot: oneshot.as_deref(),
+            };
+            print_list(format, &entries, &markers, sort);
+        }
+        EntryCommand::Reboot {
+            into,
+            firmware,
+            menu,
+        } => {
+            ensure_confirmed(yes, "Reboot now?")?;
+            if let Some(entry) = &into {
+                if !backend.entries()?.contains(entry) {
+                    return Err(anyhow::Error::new(ErrorKind::EntryNotFound)
+                        .context(format!(r#"There is no entry "{}""#, entry)));
+                }
+            }
+            if menu {
+                backend
+                    .manager_mut("Showing the boot menu")?
+                    .show_menu_once()?;
+            }
+            if firmware {
+                backend
+                    .manager_mut("Booting into the firmware setup")?
+                    .show_firmware_setup_once()?;
+            }
+            if let Some(entry) = &into {
+                backend.set_oneshot(entry)?;
+            }
+            sdboot::power::reboot()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Executes the command on the EFI variables of this machine.
+fn execute_systemd_boot(
+    manager: &mut Manager,
+    command: SystemdBootCommand,
+    options: Options,
+) -> Result<()> {
+    let Options { format, yes, .. } = options;
+    // Every variable read takes time on slow firmwares, hence the commands
+    // only read what they report or need.
+    match command {
+        #[cfg(target_os = "linux")]
+        SystemdBootCommand::Schedule { entry, at } => {
+            // "reboot --into" refuses the unknown entries anyway.
+            let entry = resolve_entry(manager.entries(), &entry, false)?;
+            schedule::oneshot(entry.id(), &at)?;
+        }
+        SystemdBootCommand::NextBoot => {
+            let Some(next) = manager.next_boot(Esp::discover().as_ref())? else {
+                return Err(
+                    anyhow::Error::new(ErrorKind::EntryNotFound).context("There are no entries")
+                );
+            };
+            match format {
+                Format::Plain => {
+                    log::debug!("{} is booted as the {}", next.entry.id(), next.reason);
+                    println!("{}", next.entry.id());
+                }
+                Format::Json => println!(
+                    "{:#}",
+                    json!({ "entry": next.entry.id(), "reason": next.reason })
+                ),
+            }
+        }
+        SystemdBootCommand::Ensure {
+            default,
+            timeout,
+            force,
+        } => {
+            // (item, value, whether it has been written)
+            let mut items = Vec::new();
+            if let Some(entry) = default {
+                let entry = resolve_entry(manager.entries(), &entry, force)?;
+                let changed = manager.set_default_if_changed(entry.id())?;
+                items.push(("default", entry.id().to_string(), changed));
+            }
+            if let Some(timeout) = timeout {
+                let changed = manager.set_timeout_if_changed(timeout)?;
+                items.push(("timeout", timeout.to_string(), changed));
+            }
+            if format == Format::Json {
+                let report = items
+                    .iter()
+                    .map(|(item, value, changed)| {
+                        (
+                            item.to_string(),
+                            json!({ "value": value, "changed": changed }),
+                        )
+                    })
+                    .collect::<serde_json::Map<_, _>>();
+                println!("{:#}", serde_json::Value::Object(report));
+            } else {
+                for (item, value, changed) in items {
+                    let state = if changed { "changed" } else { "unchanged" };
+                    log::info!("{}: {} ({})", item, state, value);
+                }
+            }
+        }
+        command @ (SystemdBootCommand::Next | SystemdBootCommand::Prev) => {
+            let entries = titled_entries(manager.entries())?;
+            let base = match manager.get_default_entry()? {
+                // The default entry may be a glob pattern.
+                Some(default) => entries
+                    .ids()
+                    .find(|id| sdboot::parse::glob_match(&default, id))
+                    .map(str::to_string),
+                None => None,
+            };
+            let base = match base {
+                Some(base) => base,
+                None => manager
+                    .get_selected_entry()?
+                    .context("Neither the default nor the currently booted entry is known")?,
+            };
+            let offset = if matches!(command, SystemdBootCommand::Next) {
+                1
+            } else {
+                -1
+            };
+            let entry = entries
+                .relative_to(&base, offset)
+                .with_context(|| format!(r#"Entry "{}" is not in the menu"#, base))?;
+            let changed = manager.set_oneshot_if_changed(entry.id())?;
+            if format == Format::Json {
+                println!("{:#}", json!({ "entry": entry.id(), "changed": changed }));
+            } else if changed {
+                log::info!("Oneshot entry set to {}", label(entry));
+            } else {
+                log::info!("Oneshot entry is already set to {}", label(entry));
+            }
+        }
+        SystemdBootCommand::Bless => {
+            let selected = manager.get_selected_entry()?.ok_or_else(|| {
+                anyhow::Error::new(ErrorKind::VariableNotSet)
+                    .context("The boot loader doesn't report the booted entry")
+            })?;
+            let esp = Esp::discover().context("Unable to find the ESP")?;
+            let entry = esp
+                .entries()?
+                .into_iter()
+                .find(|entry| entry.id == selected)
+                .ok_or_else(|| {
+                    anyhow::Error::new(ErrorKind::EntryNotFound).context(format!(
+                        r#"The booted entry "{}" is not found on the ESP"#,
+                        selected
+                    ))
+                })?;
+            match entry.bless()? {
+                Some(path) => print_done(
+                    format,
+                    &format!(
+                        r#"Entry "{}" marked as good ({})"#,
+                        selected,
+                        path.display()
+                    ),
+                ),
+                None => print_done(
+                    format,
+                    &format!(r#"Entry "{}" doesn't use boot counting"#, selected),
+                ),
+            }
+        }
+        SystemdBootCommand::Features => {
+            let loader = manager.get_loader_features()?;
+            let stub = manager.get_stub_features()?;
+            match format {
+                Format::Plain => print!("{}", features::render(loader, stub)),
+                Format::Json => println!("{:#}", features::to_json(loader, stub)),
+            }
+        }
+        SystemdBootCommand::BootTime => {
+            let timings = manager.get_boot_timings()?;
+            if timings.init_usec.is_none() && timings.exec_usec.is_none() {
+                return Err(anyhow::Error::new(ErrorKind::VariableNotSet)
+                    .context("The boot loader doesn't report the boot timings"));
+            }
+            match format {
+                Format::Plain => print!("{}", boot_time::render(&timings)),
+                Format::Json => println!("{:#}", boot_time::to_json(&timings)),
+            }
+        }
+        SystemdBootCommand::Bootnext { option, unset } => {
+            if unset {
+                manager.remove_boot_next()?;
+                print_done(format, "BootNext unset");
+                return Ok(());
+            }
+            let options = manager.boot_options()?;
+            match option {
+                Some(query) => {
+                    let option = bootnext::find(&options, &query)?;
+                    manager.set_boot_next(option.number)?;
+                    print_done(
+                        format,
+                        &format!(
+                            r#"The firmware will boot {} "{}" next"#,
+                            option.name(),
+                            option.description
+                        ),
+                    );
+                }
+                None => {
+                    let next = manager.get_boot_next()?;
+                    match format {
+                        Format::Plain => print!("{}", bootnext::render(&options, next)),
+                        Format::Json => println!("{:#}", bootnext::to_json(&options, next)),
+                    }
+                }
+            }
+        }
+        SystemdBootCommand::Menu { reboot } => {
+            manager.show_menu_once()?;
+            print_done(format, "The boot menu will be shown on the next boot");
+            if reboot {
+                sdboot::power::reboot()?;
+            }
+        }
+        SystemdBootCommand::FirmwareSetup { reboot } => {
+            manager.show_firmware_setup_once()?;
+            print_done(format, "The firmware setup will be shown on the next boot");
+            if reboot {
+                sdboot::power::reboot()?;
+            }
+        }
+        SystemdBootCommand::Timeout { command } => {
+            let (oneshot, action) = match command {
+                TimeoutCommand::Persistent(action) => (false, action),
+                TimeoutCommand::Oneshot { action } => (true, action),
+            };
+            match (action, oneshot) {
+                (TimeoutAction::Get, false) => {
+                    print_value(format, "The timeout", manager.get_timeout()?, false)?
+                }
+                (TimeoutAction::Get, true) => print_value(
+                    format,
+                    "The one shot timeout",
+                    manager.get_timeout_oneshot()?,
+                    false,
+                )?,
+                (TimeoutAction::Set { timeout }, false) => {
+                    manager.set_timeout(timeout)?;
+                    print_done(format, &format!("Timeout set to {}", timeout));
+                }
+                (TimeoutAction::Set { timeout }, true) => {
+                    manager.set_timeout_oneshot(timeout)?;
+                    print_done(format, &format!("Oneshot timeout set to {}", timeout));
+                }
+                (TimeoutAction::Unset, false) => {
+                    manager.remove_timeout()?;
+                    print_done(format, "Timeout unset");
+                }
+                (TimeoutAction::Unset, true) => {
+                    manager.remove_timeout_oneshot()?;
+                    print_done(format, "Oneshot timeout unset");
+                }
+            }
+        }
+        SystemdBootCommand::Dump => {
+            let variables = manager.dump()?;
+            match format {
+                Format::Plain => print!("{}", dump::render(&variables)),
+                Format::Json => println!("{:#}", dump::to_json(&variables)),
+            }
+        }
+        SystemdBootCommand::Verify => {
+            let checks = sdboot::verify::verify(&manager.dump()?);
+            match format {
+                Format::Plain => {
+                    for check in &checks {
+                        println!("{}", check);
+                    }
+                }
+                Format::Json => {
+                    let checks = checks
+                        .iter()
+                        .map(|check| {
+                            json!({
+                                "variable": check.variable,
+                                "check": check.check,
+                                "passed": check.passed(),
+                                "failure": check.failure,
+                            })
+                        })
+                        .collect::<Vec<_>>();
+                    println!("{:#}", json!(checks));
+                }
+            }
+            let failed = checks.iter().filter(|check| !check.passed()).count();
+            anyhow::ensure!(failed == 0, "{} of {} checks failed", failed, checks.len());
+        }
+        SystemdBootCommand::Backup { file } => {
+            let backup = serde_json::to_string_pretty(&Backup::take(manager)?)
+                .context("Unable to serialize the backup")?;
+            match file {
+                Some(file) => {
+                    std::fs::write(&file, backup + "\n")
+                        .with_context(|| format!("Unable to write {}", file.display()))?;
+                    print_done(format, &format!("Backup saved to {}", file.display()));
+                }
+                None => println!("{}", backup),
+            }
+        }
+        SystemdBootCommand::Restore { file } => {
+            let backup = match &file {
+                Some(file) => std::fs::read_to_string(file)
+                    .with_context(|| format!("Unable to read {}", file.display()))?,
+                None => std::io::read_to_string(std::io::stdin())
+                    .context("Unable to read the backup from stdin")?,
+            };
+            let backup: Backup =
+                serde_json::from_str(&backup).context("Unable to parse the backup")?;
+            // The backup may come from stdin, with no terminal left to confirm
+            // on.
+            if file.is_some() {
+                ensure_confirmed(yes, "Restore the boot configuration?")?;
+            }
+            let changed = backup.restore(manager)?;
+            match format {
+                Format::Json => println!("{:#}", json!({ "changed": changed })),
+                Format::Plain if changed.is_empty() => log::info!("Nothing to restore"),
+                Format::Plain => log::info!("Restored {}", changed.join(", ")),
+            }
+        }
+        SystemdBootCommand::Export { file, dump_format } => {
+            let variables = manager.dump()?;
+            let contents = match dump_format {
+                DumpFormat::Dmpstore => sdboot::dump::to_dmpstore(&variables),
+                DumpFormat::Json => serde_json::to_vec_pretty(&variables)
+                    .context("Unable to serialize the variables")?,
+            };
+            std::fs::write(&file, contents)
+                .with_context(|| format!("Unable to write {}", file.display()))?;
+            print_done(
+                format,
+                &format!(
+                    "{} variables exported to {}",
+                    variables.len(),
+                    file.display()
+                ),
+            );
+        }
+        SystemdBootCommand::Import { file, dump_format } => {
+            let contents = std::fs::read(&file)
+                .with_context(|| format!("Unable to read {}", file.display()))?;
+            let variables: Vec<sdboot::dump::Variable> = match dump_format {
+                DumpFormat::Dmpstore => sdboot::dump::from_dmpstore(&contents)?,
+                DumpFormat::Json => {
+                    serde_json::from_slice(&contents).context("Unable to parse the variables")?
+                }
+            };
+            let backup = Backup::from_variables(&variables);
+            ensure_confirmed(yes, "Import the boot configuration?")?;
+            let changed = backup.restore(manager)?;
+            match format {
+                Format::Json => println!("{:#}", json!({ "changed": changed })),
+                Format::Plain if changed.is_empty() => log::info!("Nothing to import"),
+                Format::Plain => log::info!("Imported {}", changed.join(", ")),
+            }
+        }
+        SystemdBootCommand::Repair => {
+            let repairs = sdboot::verify::repairs(&manager.dump()?);
+            for repair in &repairs {
+                log::info!("{}", repair);
+            }
+            if repairs.is_empty() {
+                log::info!("Nothing to repair");
+            } else {
+                ensure_confirmed(yes, &format!("Rewrite {} variables?", repairs.len()))?;
+                for repair in &repairs {
+                    manager.repair(repair)?;
+                    log::info!("{} repaired", repair.variable);
+                }
+            }
+            if format == Format::Json {
+                let repaired = repairs
+                    .iter()
+                    .map(|repair| {
+                        json!({
+                            "variable": repair.variable,
+                            "value": repair.value,
+                            "problems": repair.problems,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                println!("{:#}", json!({ "repaired": repaired }));
+            }
+        }
+        SystemdBootCommand::Check { clear } => {
+            let esp = Esp::discover();
+            if esp.is_none() {
+                log::info!("ESP not found, skipping checks of the entries on the disk");
+            }
+            let findings = manager.diagnose(esp.as_ref())?;
+            let cleared = match manager.check_oneshot(clear)? {
+                OneshotCheck::Stale {
+                    entry,
+                    cleared: true,
+                } => Some(entry),
+                _ => None,
+            };
+            if format == Format::Json {
+                let findings = findings.iter().map(ToString::to_string).collect::<Vec<_>>();
+                println!(
+                    "{:#}",
+                    json!({ "findings": findings, "cleared_oneshot": cleared })
+                );
+            } else {
+                for finding in &findings {
+                    log::warn!("{}", finding);
+                }
+                if findings.is_empty() {
+                    log::info!("No problems found");
+                }
+                if let Some(entry) = cleared {
+                    log::info!(r#"Stale oneshot entry "{}" has been removed"#, entry);
+                }
+            }
+        }
     }
 
-    if let Some(name) = manager.get_selected_entry()? {
-        log::info!(r#"Currently booted: "{name}""#);
+    Ok(())
+}
+
+/// Picks the one shot (or the default) entry in the full-screen list.
+#[cfg(feature = "tui")]
+fn select(backend: &mut Backend, as_default: bool, options: Options) -> Result<()> {
+    anyhow::ensure!(
+        std::io::stdout().is_terminal(),
+        "The entry selector needs a terminal"
+    );
+    let entries = titled_entries(backend.entries())?;
+    let default = backend.get_default_entry()?;
+    let selected = backend.get_selected_entry()?;
+    let oneshot = backend.get_oneshot()?;
+    let markers = list::Markers {
+        default: default.as_deref(),
+        selected: selected.as_deref(),
+        oneshot: oneshot.as_deref(),
+    };
+    let title = if as_default {
+        "Set the default entry"
+    } else {
+        "Set the one shot entry"
+    };
+    let Some(entry) = select::run(entries, &markers, title)? else {
+        log::info!("Cancelled");
+        return Ok(());
+    };
+    let (changed, what) = if as_default {
+        (backend.set_default_if_changed(entry.id())?, "Default entry")
+    } else {
+        (backend.set_oneshot_if_changed(entry.id())?, "Oneshot entry")
+    };
+    if options.format == Format::Json {
+        println!("{:#}", json!({ "entry": entry.id(), "changed": changed }));
+    } else if changed {
+        log::info!("{} set to {}", what, label(&entry));
     } else {
-        log::info!(r#"Currently booted: not booted with systemd-boot"#);
+        log::info!("{} is already set to {}", what, label(&entry));
     }
+    if !as_default {
+        reboot_after_oneshot(None, options.reboot, options.yes)?;
+    }
+    Ok(())
+}
 
-    if let Some(current_oneshot_entry) = manager.get_oneshot()? {
-        log::info!(
-            r#"One shot is currently set to "{}""#,
-            current_oneshot_entry
-        );
+/// Logs the value of the variable about to be changed.
+fn log_current(name: &str, value: Option<String>) {
+    match value {
+        Some(value) => log::info!(r#"{} is currently set to "{}""#, name, value),
+        None => log::info!("{} is currently not set", name),
+    }
+}
+
+/// Adds the titles and versions from the ESP, where available, to the
+/// entries known to the boot loader.
+fn titled_entries(entries: Result<Entries>) -> Result<Entries> {
+    let entries = entries.context("Unable to fetch entries")?;
+    log::debug!(
+        "Discovered {} entries: {:#?}",
+        entries.len(),
+        entries.ids().collect::<Vec<_>>()
+    );
+    Ok(sdboot::esp::describe(entries, &esp_entries()))
+}
+
+/// Describes the entry for the user, e.g. `"arch.conf" (Arch Linux 6.8.9)`.
+fn label(entry: &Entry) -> String {
+    let details = [entry.title(), entry.version()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+    if details.is_empty() {
+        format!(r#""{}""#, entry.id())
     } else {
-        log::info!(r#"One shot is currently not set"#);
+        format!(r#""{}" ({})"#, entry.id(), details.join(" "))
     }
+}
 
-    let entries = manager.entries().context("Unable to fetch entries")?;
-    log::info!("Discovered {} entries: {:#?}", entries.len(), entries);
+/// Resolves a possibly abbreviated entry name to the entry it matches,
+/// asking the user to choose if there are several. Names that match nothing,
+/// as well as glob patterns, are refused unless `force` is given, in which
+/// case they are kept as is.
+fn resolve_entry(entries: Result<Entries>, entry: &str, force: bool) -> Result<Entry> {
+    let not_found = |message: String| {
+        if force {
+            log::warn!("{}, setting it anyway", message);
+            Ok(Entry::new(entry))
+        } else {
+            Err(anyhow::Error::new(ErrorKind::EntryNotFound)
+                .context(format!("{}, use --force to set it anyway", message)))
+        }
+    };
+    if entry.contains(['*', '?', '[']) {
+        return not_found(format!(r#""{}" is a glob pattern"#, entry));
+    }
+    let entries = match titled_entries(entries) {
+        Ok(entries) => entries,
+        Err(e) => return not_found(format!(r#"{:#}, unable to check "{}""#, e, entry)),
+    };
+    let matches = match entries.find(entry) {
+        Lookup::Matches(matches) => matches,
+        Lookup::Suggestions(suggestions) if suggestions.is_empty() => {
+            return not_found(format!(r#"There is no entry "{}""#, entry))
+        }
+        Lookup::Suggestions(suggestions) => {
+            return not_found(format!(
+                r#"There is no entry "{}", did you mean {}?"#,
+                entry,
+                labels(&suggestions)
+            ))
+        }
+    };
+    let resolved = match matches[..] {
+        [resolved] => resolved,
+        _ if std::io::stdin().is_terminal() => {
+            choose(&format!(r#""{}" matches several entries"#, entry), &matches)?
+        }
+        _ => {
+            return Err(
+                anyhow::Error::new(ErrorKind::EntryNotFound).context(format!(
+                    r#""{}" is ambiguous, it matches {}"#,
+                    entry,
+                    labels(&matches)
+                )),
+            )
+        }
+    };
+    if resolved.id() != entry {
+        log::info!(r#""{}" resolved to {}"#, entry, label(resolved));
+    }
+    Ok(resolved.clone())
+}
 
+/// Describes the entries for the user, separated by commas.
+fn labels(entries: &[&Entry]) -> String {
+    entries
+        .iter()
+        .map(|entry| label(entry))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lets the user choose the entry from the menu, when running on a terminal.
+fn pick_entry(entries: Result<Entries>) -> Result<Entry> {
+    anyhow::ensure!(
+        std::io::stdin().is_terminal(),
+        "No entry given, and the standard input is not a terminal to choose one"
+    );
+    let entries = titled_entries(entries)?;
+    anyhow::ensure!(!entries.is_empty(), "The boot loader reported no entries");
+    let entry = choose("Boot entries", &entries.iter().collect::<Vec<_>>())?;
+    Ok(entry.clone())
+}
+
+/// Asks the user to pick one of the entries by its number.
+fn choose<'a>(question: &str, entries: &[&'a Entry]) -> Result<&'a Entry> {
+    use std::io::Write;
+
+    eprintln!("{}:", question);
+    for (index, entry) in entries.iter().enumerate() {
+        eprintln!("{:>3}) {}", index + 1, label(entry));
+    }
+    loop {
+        eprint!("Choose [1-{}]: ", entries.len());
+        std::io::stderr()
+            .flush()
+            .context("Unable to print the question")?;
+        let mut answer = String::new();
+        let read = std::io::stdin()
+            .read_line(&mut answer)
+            .context("Unable to read the answer")?;
+        anyhow::ensure!(read != 0, "Cancelled");
+        match answer.trim().parse::<usize>() {
+            Ok(number) if (1..=entries.len()).contains(&number) => return Ok(entries[number - 1]),
+            _ => eprintln!("Please enter a number from 1 to {}", entries.len()),
+        }
+    }
+}
+
+/// Asks the user a yes/no question on the terminal. Anything but "y" or
+/// "yes" is a no.
+fn confirm(question: &str) -> Result<bool> {
+    use std::io::Write;
+
+    eprint!("{} [y/N] ", question);
+    std::io::stderr()
+        .flush()
+        .context("Unable to print the question")?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .read_line(&mut answer)
+        .context("Unable to read the answer")?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Asks for the confirmation of an impactful change, unless it is given
+/// upfront with "--yes" or there is no terminal to ask on.
+fn ensure_confirmed(yes: bool, question: &str) -> Result<()> {
+    if yes || !std::io::stdin().is_terminal() {
+        return Ok(());
+    }
+    anyhow::ensure!(confirm(question)?, "Cancelled");
+    Ok(())
+}
+
+/// Lists the entries on the ESP, if it can be found and read.
+fn esp_entries() -> Vec<EspEntry> {
+    let Some(esp) = Esp::discover() else {
+        log::debug!("ESP not found, the entry titles are unknown");
+        return Vec::new();
+    };
+    esp.entries().unwrap_or_else(|e| {
+        log::warn!("Unable to read the entries on the ESP: {:#}", e);
+        Vec::new()
+    })
+}
+
+/// Prints the value of a variable alone on stdout (or as `{"value": ...}` in
+/// JSON), and fails with [NotSet] if it is not set.
+fn print_value(
+    format: Format,
+    description: &'static str,
+    value: Option<String>,
+    print0: bool,
+) -> Result<()> {
+    if format == Format::Json && !print0 {
+        println!("{:#}", json!({ "value": value }));
+    }
+    match value {
+        Some(value) => {
+            if print0 {
+                print!("{}\0", value);
+            } else if format == Format::Plain {
+                println!("{}", value);
+            }
+            Ok(())
+        }
+        None => Err(NotSet(description).into()),
+    }
+}
+
+/// Prints the entry identifiers one per line, NUL-terminated with `print0`
+/// (regardless of the format), or as a JSON array.
+fn print_ids<'a>(format: Format, ids: impl Iterator<Item = &'a str>, print0: bool) {
+    match format {
+        _ if print0 => ids.for_each(|id| print!("{}\0", id)),
+        Format::Plain => ids.for_each(|id| println!("{}", id)),
+        Format::Json => println!("{:#}", json!({ "value": ids.collect::<Vec<_>>() })),
+    }
+}
+
+/// Reports a successful change, as an empty object in JSON.
+fn print_done(format: Format, message: &str) {
+    match format {
+        Format::Plain => log::info!("{}", message),
+        Format::Json => println!("{}", json!({})),
+    }
+}
+
+/// Prints the entries table.
+fn print_list(format: Format, entries: &Entries, markers: &list::Markers, sort: list::Sort) {
+    let esp_entries = esp_entries();
+    match format {
+        Format::Plain => print!("{}", list::render(entries, &esp_entries, markers, sort)),
+        Format::Json => println!("{:#}", list::to_json(entries, &esp_entries, markers, sort)),
+    }
+}
+
+/// Prints the boot loader state reported by the service, or GRUB's one.
+fn print_status(format: Format, status: &Status) {
+    match format {
+        Format::Plain => {
+            log::info!(
+                "Default entry: {}",
+                status.default.as_deref().unwrap_or("not set")
+            );
+            log::info!(
+                "Currently booted: {}",
+                status.selected.as_deref().unwrap_or("unknown")
+            );
+            log::info!(
+                "One shot: {}",
+                status.oneshot.as_deref().unwrap_or("not set")
+            );
+            log::info!(
+                "Discovered {} entries: {:#?}",
+                status.entries.len(),
+                status.entries
+            );
+        }
+        Format::Json => println!("{:#}", json!(status)),
+    }
+}
+
+/// Executes the command through the control socket.
+#[cfg(target_os = "linux")]
+fn run_client(socket: &std::path::Path, command: LoaderCommand, options: Options) -> Result<()> {
+    use sdboot::service::Request;
+
+    let Options {
+        format,
+        quiet,
+        yes,
+        reboot,
+    } = options;
+    let LoaderCommand::Entry(command) = command else {
+        anyhow::bail!(
+            "Only the one shot and the default entries are supported through the control socket"
+        );
+    };
+    let mut client = socket::Client::connect(socket)?;
     match command {
-        Some(Command::SetOneshot { entry }) => {
-            manager.set_oneshot(&entry)?;
-            log::info!(r#"Oneshot entry set to "{}""#, entry);
-            if !entries.contains(&entry) {
-                log::warn!(
-                    r#"Please note that there is no entry detected with the name "{}"!"#,
-                    entry
-                )
-            }
-        }
-        Some(Command::SetDefault { entry }) => {
-            manager.set_default(&entry)?;
-            log::info!(r#"Default entry set to "{}""#, entry);
-            if !entries.contains(&entry) {
-                log::warn!(
-                    r#"Please note that there is no entry detected with the name "{}"!"#,
-                    entry
-                )
-            }
-        }
-        Some(Command::Unset) => {
-            manager.remove_oneshot()?;
-            log::info!("Oneshot entry unset");
-        }
-        None => { /* No op */ }
+        EntryCommand::SetOneshot {
+            entry,
+            reboot_in,
+            at,
+            ..
+        } => {
+            let entry = entry.context("The entry is required with --connect")?;
+            if !quiet {
+                let status: Status = client.call(&Request::GetStatus {})?;
+                log_current("One shot", status.oneshot);
+            }
+            client.call::<serde_json::Value>(&Request::SetOneshot {
+                entry: entry.clone(),
+            })?;
+            print_done(format, &format!(r#"Oneshot entry set to "{}""#, entry));
+            reboot_after_oneshot(reboot_in.or(at), reboot, yes)?;
+        }
+        EntryCommand::SetDefault {
+            entry,
+            until_next_boot,
+            ..
+        } => {
+            anyhow::ensure!(
+                !until_next_boot,
+                "Restoring the default entry is not supported through the control socket"
+            );
+            if !quiet {
+                let status: Status = client.call(&Request::GetStatus {})?;
+                log_current("Default entry", status.default);
+            }
+            ensure_confirmed(yes, &format!(r#"Set the default entry to "{}"?"#, entry))?;
+            client.call::<serde_json::Value>(&Request::SetDefault {
+                entry: entry.clone(),
+            })?;
+            print_done(format, &format!(r#"Default entry set to "{}""#, entry));
+        }
+        EntryCommand::Unset => {
+            if !quiet {
+                let status: Status = client.call(&Request::GetStatus {})?;
+                log_current("One shot", status.oneshot);
+            }
+            client.call::<serde_json::Value>(&Request::RemoveOneshot {})?;
+            print_done(format, "Oneshot entry unset");
+        }
+        EntryCommand::UnsetDefault => {
+            if !quiet {
+                let status: Status = client.call(&Request::GetStatus {})?;
+                log_current("Default entry", status.default);
+            }
+            ensure_confirmed(yes, "Unset the default entry?")?;
+            client.call::<serde_json::Value>(&Request::RemoveDefault {})?;
+            print_done(format, "Default entry unset");
+        }
+        EntryCommand::Status => {
+            let status: Status = client.call(&Request::GetStatus {})?;
+            print_status(format, &status);
+        }
+        EntryCommand::Get { variable, print0 } => {
+            let status: Status = client.call(&Request::GetStatus {})?;
+            let value = match variable {
+                Variable::Oneshot => status.oneshot,
+                Variable::Default => status.default,
+                Variable::Selected => status.selected,
+                Variable::Entries => {
+                    print_ids(format, status.entries.iter().map(String::as_str), print0);
+                    return Ok(());
+                }
+            };
+            print_value(format, variable.description(), value, print0)?;
+        }
+        EntryCommand::List { print0, sort } => {
+            let status: Status = client.call(&Request::GetStatus {})?;
+            let entries: Entries = status.entries.iter().map(Entry::new).collect();
+            if print0 {
+                let esp_entries = match sort {
+                    list::Sort::Menu => Vec::new(),
+                    _ => esp_entries(),
+                };
+                print_ids(
+                    format,
+                    list::ids(&entries, &esp_entries, sort).into_iter(),
+                    true,
+                );
+                return Ok(());
+            }
+            let markers = list::Markers {
+                default: status.default.as_deref(),
+                selected: status.selected.as_deref(),
+                oneshot: status.oneshot.as_deref(),
+            };
+            print_list(format, &entries, &markers, sort);
+        }
+        EntryCommand::Reboot {
+            into,
+            firmware,
+            menu,
+        } => {
+            anyhow::ensure!(
+                !firmware && !menu,
+                "Only --into is supported through the control socket when rebooting"
+            );
+            ensure_confirmed(yes, "Reboot now?")?;
+            if let Some(entry) = into {
+                client.call::<serde_json::Value>(&Request::SetOneshot { entry })?;
+            }
+            sdboot::power::reboot()?;
+        }
     }
 
     Ok(())
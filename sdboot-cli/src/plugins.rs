@@ -0,0 +1,62 @@
+//! External subcommands: "sdboot-cli foo ..." runs the "sdboot-foo"
+//! executable in `PATH`, git-style, for the site-specific commands.
+//!
+//! The plugin gets the arguments following the subcommand name and, on
+//! stdin, the context as a JSON object:
+//!
+//! ```json
+//! {
+//!   "cli": { "path": "/usr/bin/sdboot-cli", "version": "0.1.0" },
+//!   "format": "plain",
+//!   "status": { "firmware": { ... }, "loader": { ... }, "timings": { ... } },
+//!   "error": null
+//! }
+//! ```
+//!
+//! `status` is the output of "sdboot-cli --format json status", or null with
+//! the reason in `error` if it couldn't be collected.
+
+use std::{
+    io::Write,
+    process::{Command, ExitStatus, Stdio},
+};
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+
+/// Prefix of the plugin executables.
+const PREFIX: &str = "sdboot-";
+
+/// Runs the plugin with the arguments, the first of which is the subcommand
+/// name, feeding it the context.
+pub fn run(args: &[String], context: &Value) -> Result<ExitStatus> {
+    let Some((name, args)) = args.split_first() else {
+        anyhow::bail!("No subcommand");
+    };
+    let program = format!("{}{}", PREFIX, name);
+    let mut child = match Command::new(&program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => anyhow::bail!(
+            r#"Unknown subcommand "{}", and there is no {} in PATH"#,
+            name,
+            program
+        ),
+        Err(e) => return Err(e).with_context(|| format!("Unable to run {}", program)),
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // The plugin doesn't have to read the context.
+        match stdin.write_all(format!("{:#}\n", context).as_bytes()) {
+            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => {
+                log::warn!("Unable to pass the context to {}: {}", program, e)
+            }
+            _ => {}
+        }
+    }
+    child
+        .wait()
+        .with_context(|| format!("Unable to wait for {}", program))
+}
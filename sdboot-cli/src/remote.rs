@@ -0,0 +1,110 @@
+//! Running the command on a remote machine over SSH, with the copy of the CLI
+//! installed there, e.g. to flip the next boot entry of lab machines.
+
+use std::{
+    ffi::OsString,
+    io::IsTerminal,
+    process::{Command, ExitCode},
+};
+
+use anyhow::{Context, Result};
+
+/// The options only the local side handles, all taking a value.
+const LOCAL_OPTIONS: [&str; 2] = ["--host", "--remote-cli"];
+
+/// Runs the current command line on the host, without the remote options,
+/// and returns the remote exit code.
+pub fn rerun(host: &str, remote_cli: &str) -> Result<ExitCode> {
+    let args = std::env::args_os()
+        .skip(1)
+        .map(OsString::into_string)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|arg| anyhow::anyhow!("Non UTF-8 argument {:?}", arg))?;
+    let interactive = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+    let status = command(host, remote_cli, &strip_local_options(&args), interactive)
+        .status()
+        .context("Unable to run ssh")?;
+    Ok(status
+        .code()
+        .and_then(|code| u8::try_from(code).ok())
+        .map_or(ExitCode::FAILURE, ExitCode::from))
+}
+
+/// Prepares the ssh command running the CLI with the arguments on the host.
+/// The interactive commands get a terminal to ask for confirmations in.
+pub fn command(host: &str, remote_cli: &str, args: &[String], interactive: bool) -> Command {
+    let mut command = Command::new("ssh");
+    if interactive {
+        command.arg("-t");
+    }
+    command.args([host, "--", &shell_command(remote_cli, args)]);
+    command
+}
+
+/// The command line for the remote shell.
+pub fn shell_command(remote_cli: &str, args: &[String]) -> String {
+    std::iter::once(remote_cli)
+        .chain(args.iter().map(String::as_str))
+        .map(quote)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Removes the remote options, both "--host HOST" and "--host=HOST".
+pub fn strip_local_options(args: &[String]) -> Vec<String> {
+    let mut stripped = Vec::with_capacity(args.len());
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if LOCAL_OPTIONS.contains(&arg.as_str()) {
+            args.next();
+        } else if !LOCAL_OPTIONS
+            .iter()
+            .any(|option| arg.starts_with(&format!("{}=", option)))
+        {
+            stripped.push(arg.clone());
+        }
+    }
+    stripped
+}
+
+/// Quotes the argument for the remote shell.
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@,+".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_command() {
+        let args = [
+            "--host",
+            "lab-1",
+            "set-oneshot",
+            "--host=lab-2",
+            "Arch's kernel",
+        ]
+        .map(str::to_string);
+        let args = strip_local_options(&args);
+        assert_eq!(args, ["set-oneshot", "Arch's kernel"]);
+
+        let command = command("root@lab-1", "sdboot-cli", &args, false);
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            [
+                "root@lab-1",
+                "--",
+                r"sdboot-cli set-oneshot 'Arch'\''s kernel'"
+            ]
+        );
+    }
+}
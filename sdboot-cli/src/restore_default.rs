@@ -0,0 +1,164 @@
+//! Temporary default entries: the previous default is recorded and a systemd
+//! unit restores it after the next successful boot.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{Context, Result};
+use sdboot::Manager;
+use serde::{Deserialize, Serialize};
+
+/// The hidden subcommand the unit runs.
+pub const COMMAND: &str = "restore-default";
+
+/// Where the default entry to restore is kept.
+const STATE_FILE: &str = "/var/lib/sdboot/restore-default.json";
+
+const UNIT_NAME: &str = "sdboot-restore-default.service";
+
+const UNIT_DIRECTORY: &str = "/etc/systemd/system";
+
+/// The default entry to restore.
+#[derive(Debug, Serialize, Deserialize)]
+struct State {
+    /// [None] if there was no default entry.
+    default: Option<String>,
+}
+
+/// Records the default entry to restore, unless a restore is already pending,
+/// and enables the unit restoring it on the next boot.
+pub fn install(previous: Option<&str>) -> Result<()> {
+    let state_file = Path::new(STATE_FILE);
+    if state_file.exists() {
+        log::debug!("A restore of the default entry is already pending");
+    } else {
+        if let Some(directory) = state_file.parent() {
+            fs::create_dir_all(directory)
+                .with_context(|| format!("Unable to create {}", directory.display()))?;
+        }
+        let state = State {
+            default: previous.map(str::to_string),
+        };
+        fs::write(state_file, serde_json::to_vec(&state)?)
+            .with_context(|| format!("Unable to write {}", STATE_FILE))?;
+    }
+
+    let executable = std::env::current_exe().context("Unable to locate the executable")?;
+    let executable = executable
+        .to_str()
+        .with_context(|| format!("{} is not valid UTF-8", executable.display()))?;
+    fs::write(unit_path(), unit(executable))
+        .with_context(|| format!("Unable to write {}", unit_path().display()))?;
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", UNIT_NAME])?;
+    match previous {
+        Some(previous) => log::info!(
+            "The default entry will be restored to {} after the next boot",
+            previous
+        ),
+        None => log::info!("The default entry will be unset after the next boot"),
+    }
+    Ok(())
+}
+
+/// Restores the recorded default entry and removes the unit.
+pub fn restore(manager: &mut Manager) -> Result<()> {
+    let state = fs::read(STATE_FILE).with_context(|| format!("Unable to read {}", STATE_FILE))?;
+    let state: State = serde_json::from_slice(&state)
+        .with_context(|| format!("Unable to parse {}", STATE_FILE))?;
+    match &state.default {
+        Some(default) => {
+            manager.set_default(default)?;
+            log::info!("Default entry restored to {}", default);
+        }
+        None => {
+            manager.remove_default()?;
+            log::info!("Default entry unset");
+        }
+    }
+    fs::remove_file(STATE_FILE).with_context(|| format!("Unable to remove {}", STATE_FILE))?;
+
+    systemctl(&["disable", UNIT_NAME])?;
+    fs::remove_file(unit_path())
+        .with_context(|| format!("Unable to remove {}", unit_path().display()))?;
+    systemctl(&["daemon-reload"])
+}
+
+fn unit_path() -> PathBuf {
+    Path::new(UNIT_DIRECTORY).join(UNIT_NAME)
+}
+
+/// The unit running the restore once the boot has been found successful.
+fn unit(executable: &str) -> String {
+    format!(
+        "[Unit]\n\
+         Description=Restore the default boot entry changed by sdboot-cli\n\
+         Requires=boot-complete.target\n\
+         After=boot-complete.target\n\
+         ConditionPathExists={state}\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         ExecStart={executable} {command}\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n",
+        state = STATE_FILE,
+        executable = quote(executable),
+        command = COMMAND,
+    )
+}
+
+/// Quotes the argument of a unit's command line, so the spaces, the quotes,
+/// the backslashes, the specifiers ("%") and the variables ("$") in it are
+/// taken literally, see systemd.service(5).
+fn quote(argument: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in argument.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '%' => quoted.push_str("%%"),
+            '$' => quoted.push_str("$$"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let status = Command::new("systemctl")
+        .args(args)
+        .status()
+        .context("Unable to run systemctl")?;
+    anyhow::ensure!(
+        status.success(),
+        "systemctl {} failed ({})",
+        args.join(" "),
+        status
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_unit() {
+        let unit = unit("/usr/bin/sdboot-cli");
+        assert!(unit.contains("\nExecStart=\"/usr/bin/sdboot-cli\" restore-default\n"));
+        assert!(unit.contains("\nConditionPathExists=/var/lib/sdboot/restore-default.json\n"));
+        assert_eq!(
+            quote(r#"/opt/my tools/100%/$HOME/"sd"\cli"#),
+            r#""/opt/my tools/100%%/$$HOME/\"sd\"\\cli""#
+        );
+    }
+}
@@ -0,0 +1,194 @@
+//! Delayed reboots, e.g. into a rescue entry during a maintenance window.
+
+use std::{process::Command, time::Duration};
+
+use anyhow::{Context, Result};
+
+/// When to reboot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum When {
+    /// After the given time.
+    In(Duration),
+    /// At the given local time.
+    At {
+        /// Hours, 0..=23.
+        hour: u8,
+        /// Minutes, 0..=59.
+        minute: u8,
+    },
+}
+
+/// Parses a time span like "90s", "30m", "2h" or "1h30m".
+pub fn parse_span(span: &str) -> Result<When, String> {
+    let invalid = || {
+        format!(
+            "Invalid duration {:?}, expected e.g. \"30m\" or \"1h30m\"",
+            span
+        )
+    };
+    let mut seconds = 0u64;
+    let mut rest = span.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (number, tail) = rest.split_at(digits);
+        let number: u64 = number.parse().map_err(|_| invalid())?;
+        let unit_length = tail
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_length);
+        let multiplier = match unit.trim() {
+            "s" | "sec" => 1,
+            "m" | "min" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        seconds = number
+            .checked_mul(multiplier)
+            .and_then(|part| seconds.checked_add(part))
+            .ok_or_else(invalid)?;
+        rest = tail.trim_start();
+    }
+    Ok(When::In(Duration::from_secs(seconds)))
+}
+
+/// Parses a local time like "03:00".
+pub fn parse_time(time: &str) -> Result<When, String> {
+    let invalid = || format!("Invalid time {:?}, expected HH:MM", time);
+    let (hour, minute) = time.trim().split_once(':').ok_or_else(invalid)?;
+    let hour: u8 = hour.parse().map_err(|_| invalid())?;
+    let minute: u8 = minute.parse().map_err(|_| invalid())?;
+    if hour > 23 || minute > 59 {
+        return Err(invalid());
+    }
+    Ok(When::At { hour, minute })
+}
+
+/// Schedules the reboot with a transient systemd timer, falling back to
+/// `shutdown -r`.
+#[cfg(target_os = "linux")]
+pub fn reboot(when: When) -> Result<()> {
+    let trigger = match when {
+        When::In(delay) => format!("--on-active={}s", delay.as_secs()),
+        When::At { hour, minute } => format!("--on-calendar=*-*-* {:02}:{:02}:00", hour, minute),
+    };
+    let systemd_run = Command::new("systemd-run")
+        .args([
+            "--unit=sdboot-scheduled-reboot",
+            "--description=Reboot scheduled by sdboot-cli",
+            &trigger,
+            "systemctl",
+            "reboot",
+        ])
+        .output();
+    match systemd_run {
+        Ok(output) if output.status.success() => {
+            log::info!("Reboot scheduled with the sdboot-scheduled-reboot timer");
+            return Ok(());
+        }
+        Ok(output) => log::debug!(
+            "systemd-run failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ),
+        Err(e) => log::debug!("Unable to run systemd-run: {:#}", e),
+    }
+
+    let time = match when {
+        // shutdown counts in minutes.
+        When::In(delay) => format!("+{}", delay.as_secs().div_ceil(60)),
+        When::At { hour, minute } => format!("{:02}:{:02}", hour, minute),
+    };
+    let status = Command::new("shutdown")
+        .args(["-r", &time])
+        .status()
+        .context("Unable to run shutdown")?;
+    anyhow::ensure!(status.success(), "shutdown failed ({})", status);
+    log::info!("Reboot scheduled with shutdown at {}", time);
+    Ok(())
+}
+
+/// The transient unit setting the one shot entry and rebooting.
+#[cfg(target_os = "linux")]
+const ONESHOT_UNIT: &str = "sdboot-scheduled-oneshot";
+
+/// Schedules setting the one shot entry and rebooting at the systemd calendar
+/// time, e.g. "Sat 02:00", with a transient systemd timer. The timer is
+/// stopped once it has fired, and goes away with the reboot.
+#[cfg(target_os = "linux")]
+pub fn oneshot(entry: &str, calendar: &str) -> Result<()> {
+    let executable = std::env::current_exe().context("Unable to locate the executable")?;
+    let output = Command::new("systemd-run")
+        .arg(format!("--unit={}", ONESHOT_UNIT))
+        .arg(format!(
+            "--description=Reboot into {} scheduled by sdboot-cli",
+            entry
+        ))
+        .arg(format!("--on-calendar={}", calendar))
+        .arg("--timer-property=AccuracySec=1s")
+        .arg(format!(
+            "--property=ExecStopPost=systemctl stop {}.timer",
+            ONESHOT_UNIT
+        ))
+        .arg("--collect")
+        .arg(executable)
+        .args(["--yes", "reboot", "--into", entry])
+        .output()
+        .context("Unable to run systemd-run")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "systemd-run failed ({}): {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    log::info!(
+        "Reboot into {} scheduled with the {}.timer timer, \
+         cancel with \"systemctl stop {}.timer\"",
+        entry,
+        ONESHOT_UNIT,
+        ONESHOT_UNIT
+    );
+    Ok(())
+}
+
+/// Schedules the reboot with `shutdown /r`.
+#[cfg(target_os = "windows")]
+pub fn reboot(when: When) -> Result<()> {
+    let When::In(delay) = when else {
+        anyhow::bail!("Rebooting at a given time is not supported on Windows, use --in");
+    };
+    let status = Command::new("shutdown")
+        .args(["/r", "/t", &delay.as_secs().to_string()])
+        .status()
+        .context("Unable to run shutdown")?;
+    anyhow::ensure!(status.success(), "shutdown failed ({})", status);
+    log::info!("Reboot scheduled in {} seconds", delay.as_secs());
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_parse() {
+        assert_eq!(parse_span("30m"), Ok(When::In(Duration::from_secs(1800))));
+        assert_eq!(
+            parse_span("1h 30min"),
+            Ok(When::In(Duration::from_secs(5400)))
+        );
+        assert_eq!(parse_span("90s"), Ok(When::In(Duration::from_secs(90))));
+        assert!(parse_span("").is_err());
+        assert!(parse_span("30").is_err());
+        assert!(parse_span("m").is_err());
+
+        assert_eq!(parse_time("03:00"), Ok(When::At { hour: 3, minute: 0 }));
+        assert!(parse_time("24:00").is_err());
+        assert!(parse_time("3").is_err());
+    }
+}
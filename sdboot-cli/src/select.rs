@@ -0,0 +1,239 @@
+//! The full-screen entry picker of the "select" subcommand.
+
+use anyhow::{Context, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+use sdboot::{parse::fuzzy_match, Entries, Entry};
+
+use crate::list::Markers;
+
+/// The entries list narrowed down by a fuzzy filter.
+pub struct EntryList {
+    entries: Entries,
+    filter: String,
+    /// Indices of the entries matching the filter.
+    visible: Vec<usize>,
+    state: ListState,
+}
+
+impl EntryList {
+    /// Creates the list with the given entry highlighted, or the first one.
+    pub fn new(entries: Entries, highlighted: Option<&str>) -> Self {
+        let highlighted = highlighted
+            .and_then(|id| entries.position(id))
+            .or((!entries.is_empty()).then_some(0));
+        let mut list = Self {
+            visible: (0..entries.len()).collect(),
+            entries,
+            filter: String::new(),
+            state: ListState::default(),
+        };
+        list.state.select(highlighted);
+        list
+    }
+
+    /// The highlighted entry.
+    pub fn highlighted(&self) -> Option<&Entry> {
+        let index = *self.visible.get(self.state.selected()?)?;
+        self.entries.get_by_index(index)
+    }
+
+    /// Handles the navigation keys, returns whether the key has been
+    /// handled.
+    pub fn navigate(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Up => self.move_by(-1),
+            KeyCode::Down => self.move_by(1),
+            KeyCode::PageUp => self.move_by(-10),
+            KeyCode::PageDown => self.move_by(10),
+            KeyCode::Home => self.move_by(isize::MIN),
+            KeyCode::End => self.move_by(isize::MAX),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handles the navigation and filter keys, returns whether the key has
+    /// been handled.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.navigate(key) {
+            return true;
+        }
+        match key.code {
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.refilter();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.filter.push(c);
+                self.refilter();
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn move_by(&mut self, offset: isize) {
+        let Some(last) = self.visible.len().checked_sub(1) else {
+            return;
+        };
+        let current = self.state.selected().unwrap_or_default();
+        let target = if offset < 0 {
+            current.saturating_sub(offset.unsigned_abs())
+        } else {
+            current.saturating_add(offset.unsigned_abs()).min(last)
+        };
+        self.state.select(Some(target));
+    }
+
+    fn refilter(&mut self) {
+        let highlighted = self.highlighted().map(|entry| entry.id().to_string());
+        self.visible = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| matches(&self.filter, entry))
+            .map(|(index, _)| index)
+            .collect();
+        // Keep the highlighted entry if it still matches.
+        let position = highlighted
+            .and_then(|id| {
+                self.visible
+                    .iter()
+                    .position(|&index| self.entries.get_by_index(index).map(Entry::id) == Some(&id))
+            })
+            .or((!self.visible.is_empty()).then_some(0));
+        self.state.select(position);
+    }
+
+    /// Draws the filter line above the entries, marking the default, the
+    /// currently booted and the one shot entries.
+    pub fn draw(&mut self, frame: &mut Frame, area: Rect, title: &str, markers: &Markers) {
+        let [filter_area, list_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(area);
+        frame.render_widget(
+            Paragraph::new(format!("Filter: {}", self.filter)),
+            filter_area,
+        );
+        let items = self
+            .visible
+            .iter()
+            .filter_map(|&index| self.entries.get_by_index(index))
+            .map(|entry| {
+                let mut line = entry.id().to_string();
+                if let Some(title) = entry.title() {
+                    line += &format!("  {}", title);
+                }
+                if let Some(version) = entry.version() {
+                    line += &format!(" {}", version);
+                }
+                let markers = markers.of(entry.id());
+                if !markers.is_empty() {
+                    line += &format!("  [{}]", markers);
+                }
+                ListItem::new(line)
+            });
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+    }
+}
+
+/// Whether the characters of the filter appear in the order in the
+/// identifier, or the title and the version, ignoring the case.
+fn matches(filter: &str, entry: &Entry) -> bool {
+    let title = format!(
+        "{} {}",
+        entry.title().unwrap_or_default(),
+        entry.version().unwrap_or_default()
+    );
+    fuzzy_match(filter, entry.id()) || fuzzy_match(filter, &title)
+}
+
+/// Lets the user pick one of the entries in a full-screen list. [None] if
+/// the user cancelled with Esc or Ctrl-C.
+pub fn run(entries: Entries, markers: &Markers, title: &str) -> Result<Option<Entry>> {
+    let mut terminal = ratatui::try_init().context("Unable to initialize the terminal")?;
+    let mut list = EntryList::new(entries, markers.oneshot.or(markers.default));
+    let result = loop {
+        let drawn = terminal.draw(|frame| {
+            let [list_area, help_area] =
+                Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+            list.draw(frame, list_area, title, markers);
+            frame.render_widget(
+                Line::from("Up/Down: move  Type: filter  Enter: choose  Esc: cancel"),
+                help_area,
+            );
+        });
+        if let Err(e) = drawn {
+            break Err(e).context("Unable to draw the entries");
+        }
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(e) => break Err(e).context("Unable to read the input"),
+        };
+        match key.code {
+            KeyCode::Esc => break Ok(None),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break Ok(None),
+            KeyCode::Enter if list.highlighted().is_some() => {
+                break Ok(list.highlighted().cloned())
+            }
+            _ => {
+                list.handle_key(key);
+            }
+        }
+    };
+    ratatui::try_restore().context("Unable to restore the terminal")?;
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_filter() {
+        let entries: Entries = [
+            Entry::new("arch.conf")
+                .with_title("Arch Linux")
+                .with_version("6.5.3"),
+            Entry::new("arch-lts.conf").with_title("Arch Linux (LTS)"),
+            Entry::new("auto-windows"),
+        ]
+        .into_iter()
+        .collect();
+        let mut list = EntryList::new(entries, Some("auto-windows"));
+        assert_eq!(list.highlighted().map(Entry::id), Some("auto-windows"));
+
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        for c in "lts".chars() {
+            assert!(list.handle_key(key(KeyCode::Char(c))));
+        }
+        assert_eq!(list.visible, [1]);
+        assert_eq!(list.highlighted().map(Entry::id), Some("arch-lts.conf"));
+
+        list.handle_key(key(KeyCode::Backspace));
+        list.handle_key(key(KeyCode::Backspace));
+        list.handle_key(key(KeyCode::Backspace));
+        assert_eq!(list.visible, [0, 1, 2]);
+        assert_eq!(list.highlighted().map(Entry::id), Some("arch-lts.conf"));
+
+        // "Windows Boot Manager".
+        for c in "wbm".chars() {
+            list.handle_key(key(KeyCode::Char(c)));
+        }
+        assert_eq!(list.visible, [2]);
+        list.handle_key(key(KeyCode::Down));
+        assert_eq!(list.highlighted().map(Entry::id), Some("auto-windows"));
+        assert!(!list.handle_key(key(KeyCode::Enter)));
+    }
+}
@@ -0,0 +1,237 @@
+//! Local control socket.
+//!
+//! A privileged `sdboot-cli serve socket` process performs the EFI variables
+//! writes, while unprivileged clients (`sdboot-cli --connect ...`) only send
+//! the requests. Every request and response is a single line of JSON: a
+//! [Request], answered with a [Response].
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::{
+        fs::PermissionsExt,
+        net::{UnixListener, UnixStream},
+    },
+    path::Path,
+    sync::mpsc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use nix::sys::{
+    socket::{getsockopt, sockopt::PeerCredentials},
+    stat::{umask, Mode},
+};
+use sdboot::{
+    service::{self, Request, Response},
+    Backend,
+};
+
+/// Default path of the control socket.
+pub const DEFAULT_SOCKET: &str = "/run/sdboot/control.sock";
+
+/// The longest request accepted, in bytes.
+const MAX_REQUEST: usize = 64 * 1024;
+
+/// How long a connection may stay silent before it is closed.
+const READ_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Binds the socket with the `mode` and the `group`, replacing a stale one
+/// left by a previous run.
+///
+/// The socket is bound under a temporary name with a restrictive umask and
+/// moved into place only once its permissions are set, so nobody else can
+/// connect to it in between.
+pub fn bind(socket: &Path, mode: u32, group: Option<&str>) -> Result<UnixListener> {
+    let gid = group
+        .map(|group| {
+            nix::unistd::Group::from_name(group)
+                .with_context(|| format!("Unable to look up group {}", group))?
+                .with_context(|| format!("There is no group {}", group))
+                .map(|group| group.gid)
+        })
+        .transpose()?;
+    if let Some(directory) = socket.parent() {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Unable to create {}", directory.display()))?;
+    }
+    let name = socket
+        .file_name()
+        .with_context(|| format!("{} is not a file path", socket.display()))?;
+    let staging = socket.with_file_name(format!(".{}.new", name.to_string_lossy()));
+    match std::fs::remove_file(&staging) {
+        Ok(()) => log::debug!("Removed a stale socket {}", staging.display()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => { /* Nothing to remove */ }
+        Err(e) => return Err(e).with_context(|| format!("Unable to remove {}", staging.display())),
+    }
+
+    let previous = umask(Mode::from_bits_truncate(0o077));
+    let listener = UnixListener::bind(&staging);
+    umask(previous);
+    let listener = listener.with_context(|| format!("Unable to bind {}", staging.display()))?;
+
+    let placed = std::fs::set_permissions(&staging, std::fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Unable to change permissions of {}", staging.display()))
+        .and_then(|()| match gid {
+            Some(gid) => nix::unistd::chown(&staging, None, Some(gid))
+                .with_context(|| format!("Unable to change the group of {}", staging.display())),
+            None => Ok(()),
+        })
+        // Replaces the stale socket, if any, at once.
+        .and_then(|()| {
+            std::fs::rename(&staging, socket).with_context(|| {
+                format!(
+                    "Unable to move {} to {}",
+                    staging.display(),
+                    socket.display()
+                )
+            })
+        });
+    if let Err(e) = placed {
+        let _ = std::fs::remove_file(&staging);
+        return Err(e);
+    }
+    Ok(listener)
+}
+
+/// A parsed request, with where to send its response.
+type Call = (Request, mpsc::Sender<Response>);
+
+/// Serves the requests on the control socket. Every connection is read in its
+/// own thread, while the requests are handled one at a time by this one.
+///
+/// Whoever can connect to the socket may change the variables, so access is
+/// controlled with the socket `mode` and `group`.
+pub fn serve(backend: &mut Backend, socket: &Path, mode: u32, group: Option<&str>) -> Result<()> {
+    let listener = bind(socket, mode, group)?;
+    log::info!("Serving requests at {}", socket.display());
+
+    let (calls, received) = mpsc::channel::<Call>();
+    std::thread::scope(|scope| {
+        scope.spawn(move || {
+            for stream in listener.incoming() {
+                let calls = calls.clone();
+                let spawned = stream
+                    .context("Unable to accept a connection")
+                    .and_then(|stream| {
+                        std::thread::Builder::new()
+                            .spawn_scoped(scope, move || {
+                                if let Err(e) = handle_connection(&calls, stream) {
+                                    log::warn!("Control connection failed: {:#}", e);
+                                }
+                            })
+                            .context("Unable to start a connection thread")
+                    });
+                if let Err(e) = spawned {
+                    log::warn!("Control connection failed: {:#}", e);
+                }
+            }
+        });
+        for (request, respond) in received {
+            // The client may have gone away meanwhile.
+            let _ = respond.send(Response::from_result(service::handle(backend, request)));
+        }
+    });
+    Ok(())
+}
+
+fn handle_connection(calls: &mpsc::Sender<Call>, stream: UnixStream) -> Result<()> {
+    let peer = getsockopt(&stream, PeerCredentials)
+        .map(|credentials| credentials.uid().to_string())
+        .unwrap_or_else(|_| "unknown".to_string());
+    stream
+        .set_read_timeout(Some(READ_TIMEOUT))
+        .context("Unable to set the read timeout")?;
+    let mut reader = BufReader::new(
+        stream
+            .try_clone()
+            .context("Unable to clone the connection")?,
+    );
+    let mut writer = stream;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = (&mut reader)
+            .take(MAX_REQUEST as u64)
+            .read_line(&mut line)
+            .context("Unable to read a request")?;
+        if read == 0 {
+            // The client has disconnected.
+            return Ok(());
+        }
+        // The rest of an overlong line can't be told from the next request.
+        let overlong = read == MAX_REQUEST && !line.ends_with('\n');
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = if overlong {
+            Response::Error {
+                code: None,
+                message: format!("The request is longer than {} bytes", MAX_REQUEST),
+            }
+        } else {
+            match serde_json::from_str::<Request>(&line) {
+                Ok(request) => {
+                    if request.is_mutation() {
+                        log::info!("Request from uid {}: {:?}", peer, request);
+                    } else {
+                        log::debug!("Request from uid {}: {:?}", peer, request);
+                    }
+                    let (respond, response) = mpsc::channel();
+                    calls
+                        .send((request, respond))
+                        .context("The service has stopped")?;
+                    response.recv().context("The service has stopped")?
+                }
+                Err(e) => Response::Error {
+                    code: None,
+                    message: format!("Malformed request: {}", e),
+                },
+            }
+        };
+        let mut response =
+            serde_json::to_string(&response).context("Unable to serialize the response")?;
+        response.push('\n');
+        writer
+            .write_all(response.as_bytes())
+            .context("Unable to send the response")?;
+        anyhow::ensure!(!overlong, "Request from uid {} is too long", peer);
+    }
+}
+
+/// A client of the control socket.
+pub struct Client {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    /// Connects to the control socket.
+    pub fn connect(socket: &Path) -> Result<Self> {
+        let writer = UnixStream::connect(socket)
+            .with_context(|| format!("Unable to connect to {}", socket.display()))?;
+        let reader = BufReader::new(
+            writer
+                .try_clone()
+                .context("Unable to clone the connection")?,
+        );
+        Ok(Self { reader, writer })
+    }
+
+    /// Sends the request and decodes the reply.
+    pub fn call<T: serde::de::DeserializeOwned>(&mut self, request: &Request) -> Result<T> {
+        let mut line = serde_json::to_string(request).context("Unable to serialize the request")?;
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .context("Unable to send the request")?;
+
+        line.clear();
+        self.reader
+            .read_line(&mut line)
+            .context("Unable to read the response")?;
+        anyhow::ensure!(!line.is_empty(), "The service closed the connection");
+        let response: Response = serde_json::from_str(&line).context("Malformed response")?;
+        let reply = response.into_result()?;
+        serde_json::from_value(reply).context("Unexpected reply")
+    }
+}
@@ -0,0 +1,200 @@
+//! The boot loader status printed by the "status" subcommand, modeled after
+//! `bootctl status`.
+
+use std::fmt::{self, Write as _};
+
+use anyhow::Result;
+use sdboot::{BootTimings, ErrorKind, Manager, SecureBoot};
+use serde_json::{json, Value};
+
+/// Everything the firmware and the boot loader report about the current boot.
+#[derive(Debug, Default)]
+pub struct Report {
+    /// Firmware type and version, e.g. "UEFI 2.70".
+    pub firmware_type: Option<String>,
+    /// Firmware vendor and version, e.g. "EDK II 1.00".
+    pub firmware_info: Option<String>,
+    /// [None] if the firmware doesn't support Secure Boot.
+    pub secure_boot: Option<SecureBoot>,
+    /// Boot loader name and version, e.g. "systemd-boot 254".
+    pub loader_info: Option<String>,
+    /// Names of the features the boot loader supports.
+    pub features: Option<Vec<&'static str>>,
+    /// The default entry.
+    pub default: Option<String>,
+    /// The currently booted entry.
+    pub selected: Option<String>,
+    /// The one shot entry.
+    pub oneshot: Option<String>,
+    /// The boot menu timeout.
+    pub timeout: Option<String>,
+    /// The boot menu timeout for the next boot only.
+    pub timeout_oneshot: Option<String>,
+    /// Number of the discovered entries, [None] if the boot loader doesn't
+    /// report them.
+    pub entries: Option<usize>,
+    /// The boot timings.
+    pub timings: BootTimings,
+}
+
+impl Report {
+    /// Reads the report out of the EFI variables.
+    pub fn collect(manager: &Manager) -> Result<Self> {
+        Ok(Self {
+            firmware_type: manager.get_firmware_type()?,
+            firmware_info: manager.get_firmware_info()?,
+            secure_boot: manager.get_secure_boot()?,
+            loader_info: manager.get_loader_info()?,
+            features: manager
+                .get_loader_features()?
+                .map(|features| features.names().collect()),
+            default: manager.get_default_entry()?,
+            selected: manager.get_selected_entry()?,
+            oneshot: manager.get_oneshot()?,
+            timeout: manager.get_timeout()?,
+            timeout_oneshot: manager.get_timeout_oneshot()?,
+            entries: match manager.entries() {
+                Ok(entries) => Some(entries.len()),
+                Err(e) if ErrorKind::of(&e) == Some(ErrorKind::NotSystemdBoot) => None,
+                Err(e) => return Err(e.context("Unable to fetch entries")),
+            },
+            timings: manager.get_boot_timings()?,
+        })
+    }
+
+    /// Represents the report as JSON, with the unknown values as nulls.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "firmware": {
+                "type": self.firmware_type,
+                "info": self.firmware_info,
+                "secure_boot": self.secure_boot,
+            },
+            "loader": {
+                "info": self.loader_info,
+                "features": self.features,
+                "default": self.default,
+                "selected": self.selected,
+                "oneshot": self.oneshot,
+                "timeout": self.timeout,
+                "timeout_oneshot": self.timeout_oneshot,
+                "entries": self.entries,
+            },
+            "timings": self.timings,
+        })
+    }
+}
+
+/// Formats microseconds as seconds, e.g. "1.234s".
+pub fn seconds(usec: Option<u64>) -> String {
+    match usec {
+        Some(usec) => format!("{}.{:03}s", usec / 1_000_000, usec % 1_000_000 / 1000),
+        None => "unknown".into(),
+    }
+}
+
+/// Writes a "label: value" line with the labels aligned to the right.
+fn field(f: &mut fmt::Formatter<'_>, label: &str, value: impl fmt::Display) -> fmt::Result {
+    writeln!(f, "{:>14}: {}", label, value)
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or = |value: &Option<String>, fallback: &str| {
+            value.clone().unwrap_or_else(|| fallback.to_string())
+        };
+
+        writeln!(f, "System:")?;
+        let mut firmware = or(&self.firmware_type, "unknown");
+        if let Some(info) = &self.firmware_info {
+            let _ = write!(firmware, " ({})", info);
+        }
+        field(f, "Firmware", firmware)?;
+        field(
+            f,
+            "Secure Boot",
+            self.secure_boot
+                .map_or_else(|| "unsupported".to_string(), |state| state.to_string()),
+        )?;
+
+        writeln!(f, "\nBoot loader:")?;
+        field(
+            f,
+            "Product",
+            or(&self.loader_info, "not booted with systemd-boot"),
+        )?;
+        field(
+            f,
+            "Features",
+            match &self.features {
+                Some(features) if !features.is_empty() => features.join(", "),
+                Some(_) => "none".into(),
+                None => "unknown".into(),
+            },
+        )?;
+        field(f, "Default", or(&self.default, "not set"))?;
+        field(f, "Selected", or(&self.selected, "unknown"))?;
+        field(f, "One shot", or(&self.oneshot, "not set"))?;
+        field(f, "Timeout", or(&self.timeout, "not set"))?;
+        field(f, "One shot menu", or(&self.timeout_oneshot, "not set"))?;
+        field(
+            f,
+            "Entries",
+            self.entries
+                .map_or_else(|| "unknown".to_string(), |count| count.to_string()),
+        )?;
+
+        writeln!(f, "\nBoot timings:")?;
+        field(f, "Loader start", seconds(self.timings.init_usec))?;
+        field(f, "Entry start", seconds(self.timings.exec_usec))?;
+        field(f, "In the menu", seconds(self.timings.menu_usec))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_render() {
+        let report = Report {
+            firmware_type: Some("UEFI 2.70".into()),
+            firmware_info: Some("EDK II 1.00".into()),
+            secure_boot: Some(SecureBoot::SetupMode),
+            loader_info: Some("systemd-boot 254".into()),
+            features: Some(vec!["entry-oneshot", "boot-counting"]),
+            default: Some("arch.conf".into()),
+            selected: Some("arch.conf".into()),
+            oneshot: None,
+            timeout: Some("5".into()),
+            timeout_oneshot: None,
+            entries: Some(2),
+            timings: BootTimings {
+                init_usec: Some(1_234_567),
+                exec_usec: Some(3_000_100),
+                menu_usec: None,
+            },
+        };
+        assert_eq!(
+            report.to_string(),
+            "System:\n\
+             \x20     Firmware: UEFI 2.70 (EDK II 1.00)\n\
+             \x20  Secure Boot: disabled (setup mode)\n\
+             \n\
+             Boot loader:\n\
+             \x20      Product: systemd-boot 254\n\
+             \x20     Features: entry-oneshot, boot-counting\n\
+             \x20      Default: arch.conf\n\
+             \x20     Selected: arch.conf\n\
+             \x20     One shot: not set\n\
+             \x20      Timeout: 5\n\
+             \x20One shot menu: not set\n\
+             \x20      Entries: 2\n\
+             \n\
+             Boot timings:\n\
+             \x20 Loader start: 1.234s\n\
+             \x20  Entry start: 3.000s\n\
+             \x20  In the menu: unknown\n"
+        );
+    }
+}
@@ -0,0 +1,31 @@
+//! Plain text tables of the "list" and "history show" subcommands.
+
+/// Renders the header and the rows as columns aligned with spaces, without
+/// the trailing ones.
+pub fn render<const N: usize>(
+    header: [&str; N],
+    rows: impl IntoIterator<Item = [String; N]>,
+) -> String {
+    let table = std::iter::once(header.map(str::to_string))
+        .chain(rows)
+        .collect::<Vec<_>>();
+
+    let mut widths = [0; N];
+    for row in &table {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let mut output = String::new();
+    for row in &table {
+        let line = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        output.push_str(line.trim_end());
+        output.push('\n');
+    }
+    output
+}
@@ -0,0 +1,264 @@
+//! The terminal dashboard of the "tui" subcommand, mirroring the GUI for
+//! headless servers.
+
+use anyhow::{Context, Result};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    layout::{Constraint, Layout},
+    text::Line,
+    widgets::{Block, Paragraph},
+    Frame,
+};
+use sdboot::{Entries, Manager, Timeout};
+
+use crate::{list::Markers, select::EntryList, status::Report};
+
+const HELP: &str = "o: set oneshot  d: set default  u: unset  t: timeout  r: reboot  \
+                    /: filter  F5: refresh  q: quit";
+
+/// A change requested by the user.
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    SetOneshot(String),
+    SetDefault(String),
+    Unset,
+    /// [None] removes the timeout.
+    SetTimeout(Option<Timeout>),
+    Reboot,
+}
+
+/// What the keys do.
+enum Mode {
+    /// Navigating the entries and running the actions.
+    Normal,
+    /// Typing the entries filter.
+    Filter,
+    /// Typing the new boot menu timeout.
+    Timeout(String),
+    /// Waiting for the confirmation of the action.
+    Confirm(Action),
+}
+
+struct App<'a> {
+    manager: &'a mut Manager,
+    entries: fn(&Manager) -> Result<Entries>,
+    report: Report,
+    list: EntryList,
+    mode: Mode,
+    message: String,
+}
+
+impl App<'_> {
+    /// Re-reads the status and the entries, keeping the highlighted entry.
+    fn refresh(&mut self) -> Result<()> {
+        self.report = Report::collect(self.manager)?;
+        let highlighted = self.list.highlighted().map(|entry| entry.id().to_string());
+        self.list = EntryList::new((self.entries)(self.manager)?, highlighted.as_deref());
+        Ok(())
+    }
+
+    /// Handles the key, returns the action to quit with, if any.
+    fn handle_key(&mut self, key: KeyEvent) -> Option<Quit> {
+        match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Normal => return self.handle_normal_key(key),
+            Mode::Filter => match key.code {
+                KeyCode::Esc | KeyCode::Enter => {}
+                _ => {
+                    self.list.handle_key(key);
+                    self.mode = Mode::Filter;
+                }
+            },
+            Mode::Timeout(mut input) => match key.code {
+                KeyCode::Esc => {}
+                KeyCode::Enter => match timeout_action(&input) {
+                    Ok(action) => return self.perform(action),
+                    Err(message) => self.message = message,
+                },
+                KeyCode::Backspace => {
+                    input.pop();
+                    self.mode = Mode::Timeout(input);
+                }
+                KeyCode::Char(c) => {
+                    input.push(c);
+                    self.mode = Mode::Timeout(input);
+                }
+                _ => self.mode = Mode::Timeout(input),
+            },
+            Mode::Confirm(action) => match key.code {
+                KeyCode::Char('y' | 'Y') => return self.perform(action),
+                _ => self.message = "Cancelled".into(),
+            },
+        }
+        None
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> Option<Quit> {
+        let highlighted = self.list.highlighted().map(|entry| entry.id().to_string());
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Some(Quit::Exit),
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                return Some(Quit::Exit)
+            }
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('o') => return self.perform(Action::SetOneshot(highlighted?)),
+            KeyCode::Char('d') => self.mode = Mode::Confirm(Action::SetDefault(highlighted?)),
+            KeyCode::Char('u') => return self.perform(Action::Unset),
+            KeyCode::Char('t') => {
+                self.mode = Mode::Timeout(self.report.timeout.clone().unwrap_or_default())
+            }
+            KeyCode::Char('r') => self.mode = Mode::Confirm(Action::Reboot),
+            KeyCode::F(5) => match self.refresh() {
+                Ok(()) => self.message = "Refreshed".into(),
+                Err(e) => self.message = format!("Unable to refresh: {:#}", e),
+            },
+            _ => {
+                self.list.navigate(key);
+            }
+        }
+        None
+    }
+
+    /// Runs the action, reporting the outcome in the message line.
+    fn perform(&mut self, action: Action) -> Option<Quit> {
+        let result = match &action {
+            Action::SetOneshot(entry) => self
+                .manager
+                .set_oneshot(entry)
+                .map(|()| format!(r#"Oneshot entry set to "{}""#, entry)),
+            Action::SetDefault(entry) => self
+                .manager
+                .set_default(entry)
+                .map(|()| format!(r#"Default entry set to "{}""#, entry)),
+            Action::Unset => self
+                .manager
+                .remove_oneshot()
+                .map(|()| "Oneshot entry unset".to_string()),
+            Action::SetTimeout(Some(timeout)) => self
+                .manager
+                .set_timeout(*timeout)
+                .map(|()| format!("Timeout set to {}", timeout)),
+            Action::SetTimeout(None) => self
+                .manager
+                .remove_timeout()
+                .map(|()| "Timeout unset".to_string()),
+            Action::Reboot => return Some(Quit::Reboot),
+        };
+        self.message = match result {
+            Ok(message) => match self.refresh() {
+                Ok(()) => message,
+                Err(e) => format!("{}, but unable to refresh: {:#}", message, e),
+            },
+            Err(e) => format!("Error: {:#}", e),
+        };
+        None
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main_area, message_area, help_area] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [status_area, entries_area] =
+            Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)])
+                .areas(main_area);
+
+        frame.render_widget(
+            Paragraph::new(self.report.to_string()).block(Block::bordered().title("Status")),
+            status_area,
+        );
+        let markers = Markers {
+            default: self.report.default.as_deref(),
+            selected: self.report.selected.as_deref(),
+            oneshot: self.report.oneshot.as_deref(),
+        };
+        self.list.draw(frame, entries_area, "Entries", &markers);
+
+        let prompt = match &self.mode {
+            Mode::Normal => self.message.clone(),
+            Mode::Filter => "Type to filter, Enter or Esc when done".into(),
+            Mode::Timeout(input) => format!(
+                "Timeout (seconds, menu-force, menu-hidden, menu-disabled, empty to unset): {}",
+                input
+            ),
+            Mode::Confirm(Action::SetDefault(entry)) => {
+                format!(r#"Set the default entry to "{}"? [y/N]"#, entry)
+            }
+            Mode::Confirm(_) => "Reboot now? [y/N]".into(),
+        };
+        frame.render_widget(Line::from(prompt), message_area);
+        frame.render_widget(Line::from(HELP), help_area);
+    }
+}
+
+/// How the dashboard has been closed.
+enum Quit {
+    Exit,
+    Reboot,
+}
+
+/// Parses the timeout typed by the user.
+fn timeout_action(input: &str) -> Result<Action, String> {
+    match input.trim() {
+        "" => Ok(Action::SetTimeout(None)),
+        timeout => timeout
+            .parse()
+            .map(|timeout| Action::SetTimeout(Some(timeout)))
+            .map_err(|e: anyhow::Error| e.to_string()),
+    }
+}
+
+/// Runs the dashboard until the user quits, rebooting if requested.
+pub fn run(manager: &mut Manager, entries: fn(&Manager) -> Result<Entries>) -> Result<()> {
+    let report = Report::collect(manager)?;
+    let list = EntryList::new(entries(manager)?, report.oneshot.as_deref());
+    let mut app = App {
+        manager,
+        entries,
+        report,
+        list,
+        mode: Mode::Normal,
+        message: String::new(),
+    };
+
+    let mut terminal = ratatui::try_init().context("Unable to initialize the terminal")?;
+    let result = loop {
+        if let Err(e) = terminal.draw(|frame| app.draw(frame)) {
+            break Err(e).context("Unable to draw the dashboard");
+        }
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if let Some(quit) = app.handle_key(key) {
+                    break Ok(quit);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => break Err(e).context("Unable to read the input"),
+        }
+    };
+    ratatui::try_restore().context("Unable to restore the terminal")?;
+    match result? {
+        Quit::Exit => Ok(()),
+        Quit::Reboot => sdboot::power::reboot(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_timeout_action() {
+        assert_eq!(timeout_action(" "), Ok(Action::SetTimeout(None)));
+        assert_eq!(
+            timeout_action("5"),
+            Ok(Action::SetTimeout(Some(Timeout::Seconds(5))))
+        );
+        assert_eq!(
+            timeout_action("menu-force"),
+            Ok(Action::SetTimeout(Some(Timeout::MenuForce)))
+        );
+        assert!(timeout_action("soon").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+//! Varlink service, see https://varlink.org/
+//!
+//! Messages are JSON objects terminated by a null byte. Besides the
+//! `io.github.sdboot` interface, the mandatory `org.varlink.service` methods
+//! are implemented for introspection (e.g. `varlinkctl introspect`).
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::Path,
+};
+
+use anyhow::{Context, Result};
+use sdboot::{
+    service::{self, Request},
+    Backend, ErrorKind,
+};
+use serde_json::{json, Value};
+
+/// Name of the provided interface.
+const INTERFACE: &str = "io.github.sdboot";
+
+/// The interface description in the varlink IDL.
+const DESCRIPTION: &str = include_str!("io.github.sdboot.varlink");
+
+/// Default path of the listening socket.
+pub const DEFAULT_SOCKET: &str = "/run/sdboot/io.github.sdboot";
+
+/// Serves the requests on the socket at the given path, one connection at a
+/// time. Never returns unless the socket can't be set up.
+///
+/// Like with the control socket, access is controlled with the socket `mode`
+/// and `group`, see [crate::socket::bind].
+pub fn serve(backend: &mut Backend, socket: &Path, mode: u32, group: Option<&str>) -> Result<()> {
+    let listener = crate::socket::bind(socket, mode, group)?;
+    log::info!(
+        "Serving varlink interface {} at {}",
+        INTERFACE,
+        socket.display()
+    );
+    for stream in listener.incoming() {
+        let result = stream
+            .context("Unable to accept a connection")
+            .and_then(|stream| handle_connection(backend, stream));
+        if let Err(e) = result {
+            log::warn!("Varlink connection failed: {:#}", e);
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(backend: &mut Backend, stream: UnixStream) -> Result<()> {
+    let mut reader = BufReader::new(
+        stream
+            .try_clone()
+            .context("Unable to clone the connection")?,
+    );
+    let mut writer = stream;
+    let mut message = Vec::new();
+    loop {
+        message.clear();
+        if reader
+            .read_until(0, &mut message)
+            .context("Unable to read a message")?
+            == 0
+        {
+            // The client has disconnected.
+            return Ok(());
+        }
+        if message.last() == Some(&0) {
+            message.pop();
+        }
+        let call: Value = serde_json::from_slice(&message).context("Malformed message")?;
+        let reply = handle_call(backend, &call);
+        if call.get("oneway").and_then(Value::as_bool) == Some(true) {
+            continue;
+        }
+        let mut reply = serde_json::to_vec(&reply).context("Unable to serialize the reply")?;
+        reply.push(0);
+        writer
+            .write_all(&reply)
+            .context("Unable to send the reply")?;
+    }
+}
+
+fn handle_call(backend: &mut Backend, call: &Value) -> Value {
+    let method = call
+        .get("method")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let parameters = call.get("parameters").cloned().unwrap_or_else(|| json!({}));
+    match method {
+        "org.varlink.service.GetInfo" => reply(json!({
+            "vendor": "sdboot",
+            "product": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "url": "https://github.com/mexus/sdboot-oneshot",
+            "interfaces": ["org.varlink.service", INTERFACE],
+        })),
+        "org.varlink.service.GetInterfaceDescription" => {
+            match parameters.get("interface").and_then(Value::as_str) {
+                Some(INTERFACE) => reply(json!({ "description": DESCRIPTION })),
+                interface => error(
+                    "org.varlink.service.InterfaceNotFound",
+                    json!({ "interface": interface }),
+                ),
+            }
+        }
+        _ => {
+            let Some(name) = method
+                .strip_prefix(INTERFACE)
+                .and_then(|method| method.strip_prefix('.'))
+                .filter(|name| Request::METHODS.contains(name))
+            else {
+                return error(
+                    "org.varlink.service.MethodNotFound",
+                    json!({ "method": method }),
+                );
+            };
+            let request = match serde_json::from_value::<Request>(
+                json!({ "method": name, "parameters": parameters }),
+            ) {
+                Ok(request) => request,
+                Err(e) => {
+                    return error(
+                        "org.varlink.service.InvalidParameter",
+                        json!({ "parameter": e.to_string() }),
+                    )
+                }
+            };
+            log::debug!("Varlink call {:?}", request);
+            match service::handle(backend, request) {
+                Ok(parameters) => reply(parameters),
+                Err(e) => {
+                    log::warn!("{} failed: {:#}", method, e);
+                    error(
+                        &format!("{}.{}", INTERFACE, error_name(ErrorKind::of(&e))),
+                        json!({ "message": format!("{:#}", e) }),
+                    )
+                }
+            }
+        }
+    }
+}
+
+fn reply(parameters: Value) -> Value {
+    json!({ "parameters": parameters })
+}
+
+fn error(name: &str, parameters: Value) -> Value {
+    json!({ "error": name, "parameters": parameters })
+}
+
+/// Name of the interface error for the error kind.
+fn error_name(kind: Option<ErrorKind>) -> &'static str {
+    match kind {
+        Some(ErrorKind::EntryNotFound) => "EntryNotFound",
+        Some(ErrorKind::PermissionDenied) => "PermissionDenied",
+        Some(ErrorKind::NotSystemdBoot) => "NotSystemdBoot",
+        Some(ErrorKind::VariableNotSet) => "VariableNotSet",
+        Some(ErrorKind::OneshotNotSupported) => "OneshotNotSupported",
+        Some(ErrorKind::ReadOnly) => "ReadOnly",
+        Some(ErrorKind::NoSpace) => "NoSpace",
+        _ => "Failed",
+    }
+}
@@ -1,94 +1,741 @@
-use std::sync::Arc;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
 
-use egui::TextStyle;
+use egui::{Color32, RichText, TextStyle};
 
-use crate::Manager;
+use anyhow::{Context, Result};
+use sdboot::{esp::Esp, Entry, ErrorKind, Timeout};
+
+use crate::{Backend, Manager};
 
 /// GUI application.
 pub struct GuiApplication {
-    manager: Manager,
-    entries: Arc<[Arc<str>]>,
+    backend: Backend,
+    entries: Vec<Entry>,
+    /// Only the entries whose identifier or title contains it are listed.
+    filter: String,
     selected: Option<Arc<str>>,
+    mode: Mode,
+    state: State,
     message: String,
+    /// Set when the variables have been changed by another process.
+    changed: Arc<AtomicBool>,
+    /// Whether the firmware can be asked to show its setup UI.
+    firmware_setup: bool,
+    /// The confirmation being shown, if any.
+    dialog: Option<Dialog>,
+    /// The boot menu timeout being edited.
+    timeout: TimeoutEditor,
+}
+
+/// A modal confirmation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialog {
+    /// Rebooting into the selected oneshot entry.
+    Reboot,
+    /// Booting into the firmware setup UI.
+    FirmwareSetup,
+}
+
+/// The variable the entry list sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// The one shot entry, `LoaderEntryOneShot`.
+    Oneshot,
+    /// The default entry, `LoaderEntryDefault`.
+    Default,
+}
+
+impl Mode {
+    /// The variable name for the messages.
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Oneshot => "oneshot entry",
+            Mode::Default => "default entry",
+        }
+    }
+}
+
+/// The current values of the boot loader variables.
+struct State {
+    /// The entry booted when there's no one shot entry.
+    default: Option<String>,
+    /// The entry the system is running.
+    booted: Option<String>,
+    /// The entry to boot next time only.
+    oneshot: Option<String>,
+    /// Whether the boot menu is shown on the next boot.
+    menu_once: bool,
+    /// The persistent boot menu timeout, [None] if the one from the boot
+    /// loader configuration applies.
+    timeout: Option<Timeout>,
+}
+
+impl State {
+    /// Reads the variables. The unreadable ones are logged and treated as not
+    /// set.
+    fn read(backend: &Backend) -> Self {
+        let read = |what: &str, value: anyhow::Result<Option<String>>| {
+            value.unwrap_or_else(|e| {
+                log::error!("Unable to read the {} entry: {:#}", what, e);
+                None
+            })
+        };
+        // GRUB has no boot menu timeouts to manage.
+        let (menu_once, timeout) = match backend {
+            Backend::SystemdBoot(manager) => (
+                read("oneshot timeout", manager.get_timeout_oneshot()).as_deref()
+                    == Some("menu-force"),
+                read("timeout", manager.get_timeout()).and_then(|timeout| {
+                    timeout
+                        .parse()
+                        .map_err(|e| log::error!("Unable to parse the timeout: {:#}", e))
+                        .ok()
+                }),
+            ),
+            Backend::Grub(_) => (false, None),
+        };
+        Self {
+            default: read("default", backend.get_default_entry()),
+            booted: read("booted", backend.get_selected_entry()),
+            oneshot: read("oneshot", backend.get_oneshot()),
+            menu_once,
+            timeout,
+        }
+    }
+
+    /// The current value of the variable the mode sets.
+    fn value(&self, mode: Mode) -> Option<&str> {
+        match mode {
+            Mode::Oneshot => self.oneshot.as_deref(),
+            Mode::Default => self.default.as_deref(),
+        }
+    }
+
+    fn set_value(&mut self, mode: Mode, value: Option<String>) {
+        match mode {
+            Mode::Oneshot => self.oneshot = value,
+            Mode::Default => self.default = value,
+        }
+    }
+
+    /// The badges of the entry.
+    fn badges(&self, entry: &str) -> Vec<(&'static str, Color32)> {
+        [
+            (
+                &self.default,
+                "default",
+                Color32::from_rgb(0x2e, 0x7d, 0x32),
+            ),
+            (&self.booted, "booted", Color32::from_rgb(0x15, 0x65, 0xc0)),
+            (
+                &self.oneshot,
+                "oneshot",
+                Color32::from_rgb(0xef, 0x6c, 0x00),
+            ),
+        ]
+        .into_iter()
+        .filter(|(value, _, _)| value.as_deref() == Some(entry))
+        .map(|(_, badge, color)| (badge, color))
+        .collect()
+    }
 }
 
-impl Default for GuiApplication {
-    fn default() -> Self {
-        let manager = Manager::new();
-        let entries: Vec<Arc<str>> = manager
-            .entries()
-            .expect("Unable to load entries")
-            .into_iter()
-            .map(Arc::from)
-            .collect();
-        let selected = manager
+/// The boot menu timeout being edited.
+struct TimeoutEditor {
+    /// [None] for the one from the boot loader configuration.
+    value: Option<Timeout>,
+    /// The number of seconds, kept while another kind of timeout is chosen.
+    seconds: u32,
+}
+
+impl TimeoutEditor {
+    fn new(value: Option<Timeout>) -> Self {
+        let seconds = match value {
+            Some(Timeout::Seconds(seconds)) => seconds,
+            _ => 5,
+        };
+        Self { value, seconds }
+    }
+
+    /// Shows the choices.
+    fn show(&mut self, ui: &mut egui::Ui) {
+        ui.radio_value(&mut self.value, None, "As configured")
+            .on_hover_text("Use the timeout from the boot loader configuration");
+        ui.horizontal(|ui| {
+            if ui
+                .radio(matches!(self.value, Some(Timeout::Seconds(_))), "Seconds:")
+                .clicked()
+            {
+                self.value = Some(Timeout::Seconds(self.seconds));
+            }
+            let slider = ui.add(egui::Slider::new(&mut self.seconds, 0..=30).suffix(" s"));
+            if slider.changed() {
+                self.value = Some(Timeout::Seconds(self.seconds));
+            }
+        });
+        ui.radio_value(&mut self.value, Some(Timeout::MenuForce), "Always show")
+            .on_hover_text("Wait in the boot menu for the user");
+        ui.radio_value(&mut self.value, Some(Timeout::MenuHidden), "Hidden")
+            .on_hover_text("Show the boot menu only if a key is pressed");
+    }
+}
+
+impl GuiApplication {
+    /// Creates the application, refreshing it on the changes made by the
+    /// other processes where they can be watched.
+    pub fn new(ctx: &egui::Context) -> Result<Self> {
+        let backend = Backend::detect(Manager::new())?;
+        let entries = load_entries(&backend)?;
+        let selected = backend
             .get_oneshot()
-            .expect("Unable to load current entry")
+            .context("Unable to load current entry")?
             .map(Arc::from);
-        Self {
-            manager,
-            entries: Arc::from(entries),
+        let state = State::read(&backend);
+        let timeout = TimeoutEditor::new(state.timeout);
+        let firmware_setup = match &backend {
+            Backend::SystemdBoot(manager) => {
+                manager.firmware_setup_supported().unwrap_or_else(|e| {
+                    log::warn!("Unable to check for the firmware setup support: {:#}", e);
+                    false
+                })
+            }
+            Backend::Grub(_) => false,
+        };
+        Ok(Self {
+            backend,
+            entries,
+            filter: String::new(),
             selected,
+            mode: Mode::Oneshot,
+            state,
             message: String::new(),
+            changed: Arc::new(AtomicBool::new(false)),
+            firmware_setup,
+            dialog: None,
+            timeout,
+        }
+        .watch(ctx))
+    }
+
+    #[cfg(target_os = "linux")]
+    fn watch(self, ctx: &egui::Context) -> Self {
+        let changed = Arc::clone(&self.changed);
+        let ctx = ctx.clone();
+        let watched = sdboot::watch::watch(move || {
+            changed.store(true, Ordering::Relaxed);
+            ctx.request_repaint();
+            true
+        });
+        if let Err(e) = watched {
+            log::warn!("The changes won't be shown automatically: {:#}", e);
+        }
+        self
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn watch(self, _ctx: &egui::Context) -> Self {
+        self
+    }
+
+    /// Re-reads the entries and the variables, keeping the selection if the
+    /// entry is still there. Returns whether the entries were loaded.
+    fn refresh(&mut self) -> bool {
+        let loaded = match load_entries(&self.backend) {
+            Ok(entries) => {
+                self.entries = entries;
+                if let Some(selected) = &self.selected {
+                    if !self.entries.iter().any(|entry| entry.id() == &**selected) {
+                        self.selected = None;
+                    }
+                }
+                true
+            }
+            Err(e) => {
+                log::error!("{:#}", e);
+                self.message = format!("{:#}", e);
+                false
+            }
+        };
+        let old_timeout = self.state.timeout;
+        self.state = State::read(&self.backend);
+        // Unsaved changes of the timeout are kept.
+        if self.timeout.value == old_timeout {
+            self.timeout = TimeoutEditor::new(self.state.timeout);
+        }
+        loaded
+    }
+
+    /// Switches the variable the entry list sets, selecting its current
+    /// value.
+    fn switch_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.selected = self.state.value(mode).map(Arc::from);
+    }
+
+    /// Sets the variable of the mode to the selected entry, returns whether it
+    /// succeeded.
+    fn apply(&mut self) -> bool {
+        let Some(selected) = self.selected.clone() else {
+            self.message = "No entry selected!".to_string();
+            return false;
+        };
+        let name = self.mode.name();
+        log::info!("Setting {} to {}", name, selected);
+        let result = match self.mode {
+            Mode::Oneshot => self.backend.set_oneshot(&selected),
+            Mode::Default => self.backend.set_default(&selected),
+        };
+        if let Err(e) = result {
+            log::error!("Unable to set {} to {}: {:#}", name, selected, e);
+            self.message = format!("Unable to set {} to {}: {:#}", name, selected, e);
+            false
+        } else {
+            self.message = format!("{} set to {}", capitalize(name), selected);
+            self.state.set_value(self.mode, Some(selected.to_string()));
+            true
+        }
+    }
+
+    /// Sets the oneshot entry and reboots into it.
+    fn apply_and_reboot(&mut self) {
+        if self.apply() {
+            self.reboot();
         }
     }
+
+    /// Arranges for the firmware setup UI to be shown on the next boot,
+    /// returns whether it succeeded.
+    fn request_firmware_setup(&mut self) -> bool {
+        log::info!("Requesting the firmware setup on the next boot");
+        let result = self
+            .backend
+            .manager_mut("Booting into the firmware setup")
+            .and_then(Manager::show_firmware_setup_once);
+        if let Err(e) = result {
+            log::error!("Unable to request the firmware setup: {:#}", e);
+            self.message = format!("Unable to request the firmware setup: {:#}", e);
+            false
+        } else {
+            self.message = "The firmware setup will be shown on the next boot".to_string();
+            true
+        }
+    }
+
+    /// Shows the boot menu on the next boot, or stops doing it.
+    fn set_menu_once(&mut self, show: bool) {
+        log::info!(
+            "{} the boot menu on the next boot",
+            if show { "Showing" } else { "Not showing" }
+        );
+        let result = self
+            .backend
+            .manager_mut("Showing the boot menu")
+            .and_then(|manager| {
+                if show {
+                    manager.show_menu_once()
+                } else {
+                    manager.remove_timeout_oneshot()
+                }
+            });
+        match result {
+            Ok(()) => {
+                self.message = if show {
+                    "The boot menu will be shown on the next boot".to_string()
+                } else {
+                    "The boot menu won't be forced on the next boot".to_string()
+                };
+                self.state.menu_once = show;
+            }
+            Err(e) => {
+                log::error!("Unable to change the oneshot timeout: {:#}", e);
+                self.message = format!("Unable to change the oneshot timeout: {:#}", e);
+            }
+        }
+    }
+
+    /// Saves the edited boot menu timeout.
+    fn save_timeout(&mut self) {
+        let timeout = self.timeout.value;
+        log::info!("Setting the timeout to {:?}", timeout);
+        let result = self
+            .backend
+            .manager_mut("Changing the timeout")
+            .and_then(|manager| match timeout {
+                Some(timeout) => manager.set_timeout(timeout),
+                None => manager.remove_timeout(),
+            });
+        match result {
+            Ok(()) => {
+                self.message = match timeout {
+                    Some(timeout) => format!("Timeout set to {}", timeout),
+                    None => "Timeout unset".to_string(),
+                };
+                self.state.timeout = timeout;
+            }
+            Err(e) => {
+                log::error!("Unable to set the timeout: {:#}", e);
+                self.message = format!("Unable to set the timeout: {:#}", e);
+            }
+        }
+    }
+
+    fn reboot(&mut self) {
+        log::info!("Rebooting");
+        if let Err(e) = sdboot::power::reboot() {
+            log::error!("Unable to reboot: {:#}", e);
+            self.message = format!("Unable to reboot: {:#}", e);
+        }
+    }
+
+    /// Removes the variable of the mode.
+    fn unset(&mut self) {
+        let name = self.mode.name();
+        log::info!("Removing {}", name);
+        let result = match self.mode {
+            Mode::Oneshot => self.backend.remove_oneshot(),
+            Mode::Default => self.backend.remove_default(),
+        };
+        if let Err(e) = result {
+            log::error!("Unable to remove {}: {:#}", name, e);
+            self.message = format!("Unable to remove {}: {:#}", name, e);
+        } else {
+            self.message = format!("{} unset", capitalize(name));
+            self.selected = None;
+            self.state.set_value(self.mode, None);
+        }
+    }
+
+    /// Shows the confirmation and acts on the answer.
+    fn show_dialog(&mut self, ctx: &egui::Context, dialog: Dialog) {
+        let (title, text, actions): (_, _, &[&str]) = match dialog {
+            Dialog::Reboot => (
+                "Reboot now?",
+                format!(
+                    "The system will reboot into {}. Unsaved work in the other \
+                     applications will be lost.",
+                    self.selected.as_deref().unwrap_or_default()
+                ),
+                &["Reboot"],
+            ),
+            Dialog::FirmwareSetup => (
+                "Boot into the firmware setup?",
+                "The firmware setup UI will be shown on the next boot instead of the \
+                 boot menu."
+                    .to_string(),
+                &["Reboot now", "Next boot"],
+            ),
+        };
+        let mut clicked = None;
+        let mut cancel = false;
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+            .show(ctx, |ui| {
+                ui.label(text);
+                ui.horizontal(|ui| {
+                    for (index, action) in actions.iter().enumerate() {
+                        if ui.button(*action).clicked() {
+                            clicked = Some(index);
+                        }
+                    }
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if clicked.is_some() || cancel {
+            self.dialog = None;
+        }
+        match (dialog, clicked) {
+            (Dialog::Reboot, Some(_)) => self.apply_and_reboot(),
+            (Dialog::FirmwareSetup, Some(0)) => {
+                if self.request_firmware_setup() {
+                    self.reboot();
+                }
+            }
+            (Dialog::FirmwareSetup, Some(_)) => {
+                self.request_firmware_setup();
+            }
+            (_, None) => {}
+        }
+    }
+}
+
+/// Lists the entries known to the boot loader, with the titles and versions
+/// from the ESP where available.
+fn load_entries(backend: &Backend) -> Result<Vec<Entry>> {
+    let entries = backend.entries().context("Unable to load entries")?;
+    let on_disk = match Esp::discover() {
+        Some(esp) => esp.entries().unwrap_or_else(|e| {
+            log::warn!("Unable to read the entries on the ESP: {:#}", e);
+            Vec::new()
+        }),
+        None => {
+            log::debug!("ESP not found, the entry titles are unknown");
+            Vec::new()
+        }
+    };
+    Ok(sdboot::esp::describe(entries, &on_disk)
+        .into_iter()
+        .collect())
+}
+
+/// Checks whether the identifier or the title of the entry contains the
+/// lowercase filter.
+fn matches(entry: &Entry, filter: &str) -> bool {
+    [Some(entry.id()), entry.title()]
+        .into_iter()
+        .flatten()
+        .any(|name| name.to_lowercase().contains(filter))
+}
+
+/// A symbol of what the entry boots.
+fn icon(entry: &Entry) -> &'static str {
+    match entry.id() {
+        "auto-osx" => "🍎",
+        "auto-efi-shell" => "🖥",
+        "auto-efi-default" => "💾",
+        "auto-poweroff" => "🔌",
+        "auto-reboot" => "🔄",
+        "auto-reboot-to-firmware-setup" => "⚙",
+        id if id.to_lowercase().contains("windows") => "🗔",
+        _ => "🐧",
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
 }
 
 impl eframe::App for GuiApplication {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if self.changed.swap(false, Ordering::Relaxed) {
+            log::debug!("The variables have changed, refreshing");
+            self.refresh();
+        }
+        if let Some(dialog) = self.dialog {
+            self.show_dialog(ctx, dialog);
+        }
         egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Boot entries");
+            ui.set_enabled(self.dialog.is_none());
+            egui::Grid::new("state").show(ui, |ui| {
+                for (label, value) in [
+                    ("Default:", &self.state.default),
+                    ("Booted:", &self.state.booted),
+                    ("Next boot only:", &self.state.oneshot),
+                ] {
+                    ui.label(label);
+                    ui.label(value.as_deref().unwrap_or("—"));
+                    ui.end_row();
+                }
+            });
+            ui.separator();
 
-            for entry in self.entries.iter() {
-                ui.radio_value(&mut self.selected, Some(Arc::clone(entry)), entry as &str);
-            }
+            ui.horizontal(|ui| {
+                for (mode, label) in [
+                    (Mode::Oneshot, "Next boot only"),
+                    (Mode::Default, "Default"),
+                ] {
+                    if ui.selectable_label(self.mode == mode, label).clicked() && self.mode != mode
+                    {
+                        self.switch_mode(mode);
+                    }
+                }
+            });
 
-            if ui.button("Unset").clicked() {
-                log::info!("Removing oneshot entry");
-                self.selected = None;
-                if let Err(e) = self.manager.remove_oneshot() {
-                    log::error!("Unable to remove oneshot entry: {:#}", e);
-                    self.message = format!("Unable to remove oneshot entry: {:#}", e);
-                } else {
-                    self.message = "Oneshot entry unset".to_string();
-                    self.selected = None;
+            ui.heading("Boot entries");
+
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.filter)
+                        .hint_text("Filter by identifier or title"),
+                );
+                if ui
+                    .add_enabled(!self.filter.is_empty(), egui::Button::new("✖"))
+                    .on_hover_text("Clear the filter")
+                    .clicked()
+                {
+                    self.filter.clear();
                 }
-            }
+            });
 
-            if ui.button("Apply").clicked() {
-                if let Some(selected) = &self.selected {
-                    log::info!("Setting oneshot entry to {}", selected);
-                    if let Err(e) = self.manager.set_oneshot(selected) {
-                        log::error!("Unable to set oneshot entry to {}: {:#}", selected, e);
-                        self.message =
-                            format!("Unable to set oneshot entry to {}: {:#}", selected, e);
+            let filter = self.filter.trim().to_lowercase();
+            egui::ScrollArea::vertical()
+                .max_height(200.)
+                .show(ui, |ui| {
+                    for entry in self.entries.iter().filter(|entry| matches(entry, &filter)) {
+                        ui.horizontal(|ui| {
+                            ui.radio_value(
+                                &mut self.selected,
+                                Some(Arc::from(entry.id())),
+                                format!("{} {}", icon(entry), entry.display_name()),
+                            )
+                            .on_hover_text(entry.id());
+                            if let Some(version) = entry.version() {
+                                ui.label(RichText::new(version).weak());
+                            }
+                            for (badge, color) in self.state.badges(entry.id()) {
+                                ui.label(
+                                    RichText::new(badge)
+                                        .small()
+                                        .color(Color32::WHITE)
+                                        .background_color(color),
+                                );
+                            }
+                        });
+                    }
+                });
+
+            ui.horizontal(|ui| {
+                if ui.button("Unset").clicked() {
+                    self.unset();
+                }
+                if ui.button("Apply").clicked() {
+                    self.apply();
+                }
+                if self.mode == Mode::Oneshot && ui.button("Apply & Reboot").clicked() {
+                    if self.selected.is_some() {
+                        self.dialog = Some(Dialog::Reboot);
                     } else {
-                        self.message = format!("Oneshot entry set to {}", selected);
+                        self.message = "No entry selected!".to_string();
                     }
-                } else {
-                    self.message = "No entry selected!".to_string();
                 }
+                if ui.button("Refresh").clicked() && self.refresh() {
+                    self.message = "Refreshed".to_string();
+                }
+                let firmware_setup = ui
+                    .add_enabled(self.firmware_setup, egui::Button::new("Firmware setup"))
+                    .on_disabled_hover_text(
+                        "The firmware doesn't support booting into its setup UI on request",
+                    );
+                if firmware_setup.clicked() {
+                    self.dialog = Some(Dialog::FirmwareSetup);
+                }
+            });
+
+            let mut menu_once = self.state.menu_once;
+            if ui
+                .checkbox(&mut menu_once, "Show boot menu next time")
+                .on_hover_text("Wait in the boot menu on the next boot, even if it's hidden")
+                .changed()
+            {
+                self.set_menu_once(menu_once);
             }
 
-            if !self.message.is_empty() {
-                ui.horizontal_wrapped(|ui| {
-                    let spacing =
-                        ui.fonts(|f| f.glyph_width(&TextStyle::Body.resolve(ui.style()), ' '));
-                    ui.spacing_mut().item_spacing.x = spacing;
-                    ui.label(&self.message);
-                    if ui.button("📋").clicked() {
-                        if let Err(e) = copy_to_clipboard(&self.message) {
-                            log::error!("Unable to copy message to the clipboard: {e:#}")
-                        }
+            egui::CollapsingHeader::new("Boot menu timeout").show(ui, |ui| {
+                self.timeout.show(ui);
+                ui.horizontal(|ui| {
+                    let changed = self.timeout.value != self.state.timeout;
+                    if ui.add_enabled(changed, egui::Button::new("Save")).clicked() {
+                        self.save_timeout();
+                    }
+                    if ui
+                        .add_enabled(changed, egui::Button::new("Revert"))
+                        .clicked()
+                    {
+                        self.timeout = TimeoutEditor::new(self.state.timeout);
                     }
                 });
+            });
+
+            if !self.message.is_empty() {
+                message_line(ui, &self.message);
             }
         });
     }
 }
 
-fn copy_to_clipboard(value: &str) -> anyhow::Result<()> {
-    use anyhow::Context;
+/// What is shown in the window.
+pub enum Screen {
+    /// The entries.
+    Main(Box<GuiApplication>),
+    /// The reason the entries couldn't be loaded.
+    Error {
+        message: String,
+        diagnosis: &'static str,
+    },
+}
+
+impl Screen {
+    /// Loads the entries, or shows why it's impossible.
+    pub fn new(ctx: &egui::Context) -> Self {
+        match GuiApplication::new(ctx) {
+            Ok(app) => Screen::Main(Box::new(app)),
+            Err(e) => {
+                log::error!("{:#}", e);
+                Screen::Error {
+                    message: format!("{:#}", e),
+                    diagnosis: diagnosis(&e),
+                }
+            }
+        }
+    }
+}
+
+impl eframe::App for Screen {
+    fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        let (message, diagnosis) = match self {
+            Screen::Main(app) => return app.update(ctx, frame),
+            Screen::Error { message, diagnosis } => (message, diagnosis),
+        };
+        let mut retry = false;
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Unable to load the boot entries");
+            ui.label(*diagnosis);
+            ui.separator();
+            message_line(ui, message);
+            retry = ui.button("Retry").clicked();
+        });
+        if retry {
+            *self = Screen::new(ctx);
+        }
+    }
+}
+
+/// Explains the failure to start in plain words.
+fn diagnosis(error: &anyhow::Error) -> &'static str {
+    match ErrorKind::of(error) {
+        Some(ErrorKind::PermissionDenied) if cfg!(windows) => {
+            "The boot entries can only be managed by an administrator. \
+             Run the application as administrator."
+        }
+        Some(ErrorKind::PermissionDenied) => {
+            "The boot entries can only be managed by root. \
+             Run the application with pkexec or sudo."
+        }
+        Some(ErrorKind::NotSystemdBoot) => {
+            "The system doesn't seem to be booted with systemd-boot in UEFI mode, \
+             there are no boot entries to manage."
+        }
+        Some(ErrorKind::ReadOnly) => {
+            "The EFI variables are read-only. On Linux, remount them read-write with \
+             `mount -o remount,rw /sys/firmware/efi/efivars`."
+        }
+        _ => "See the details below.",
+    }
+}
+
+/// Shows the message along with a button copying it to the clipboard.
+fn message_line(ui: &mut egui::Ui, message: &str) {
+    ui.horizontal_wrapped(|ui| {
+        let spacing = ui.fonts(|f| f.glyph_width(&TextStyle::Body.resolve(ui.style()), ' '));
+        ui.spacing_mut().item_spacing.x = spacing;
+        ui.label(message);
+        if ui.button("📋").clicked() {
+            if let Err(e) = copy_to_clipboard(message) {
+                log::error!("Unable to copy message to the clipboard: {e:#}")
+            }
+        }
+    });
+}
+
+fn copy_to_clipboard(value: &str) -> Result<()> {
     let mut clipboard = arboard::Clipboard::new().context("Can't obtain a clipboard handle")?;
     clipboard
         .set_text(value.to_string())
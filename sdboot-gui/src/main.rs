@@ -7,7 +7,7 @@ use anyhow::{Context, Result};
 use clap::Parser;
 use display_error_chain::DisplayErrorChain;
 use egui::Vec2;
-use sdboot::Manager;
+use sdboot::{Backend, Manager};
 
 mod gui;
 
@@ -36,14 +36,21 @@ fn main() -> Result<()> {
         .apply()
         .context("Unable to initialize logging")?;
 
+    // Don't leave the variables mutable if interrupted in the middle of an
+    // update.
+    #[cfg(target_os = "linux")]
+    if let Err(e) = sdboot::restore_immutability_on_signals() {
+        log::warn!("{:#}", e);
+    }
+
     let native_options = eframe::NativeOptions {
-        initial_window_size: Some(Vec2::new(400., 200.)),
+        initial_window_size: Some(Vec2::new(400., 300.)),
         ..Default::default()
     };
     if let Err(e) = eframe::run_native(
         "Systemd-boot oneshot entries manager",
         native_options,
-        Box::new(|_cc| Box::<gui::GuiApplication>::default()),
+        Box::new(|cc| Box::new(gui::Screen::new(&cc.egui_ctx))),
     ) {
         anyhow::bail!("App terminated with error: {}", DisplayErrorChain::new(&e))
     }
@@ -0,0 +1,139 @@
+//! Node.js bindings for the systemd-boot EFI variables manager.
+//!
+//! ```js
+//! const { Manager } = require('sdboot');
+//!
+//! const manager = new Manager();
+//! if (manager.entries().includes('arch.conf')) {
+//!   manager.setOneshot('arch.conf');
+//! }
+//! ```
+
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+
+/// Converts a library error into a JS exception. The message is prefixed with
+/// the error code, if there is one, e.g. `[3] Permission denied`.
+fn to_js_error(error: anyhow::Error) -> Error {
+    let reason = match sdboot::ErrorKind::of(&error) {
+        Some(kind) => format!("[{}] {:#}", kind.code(), error),
+        None => format!("{:#}", error),
+    };
+    Error::new(Status::GenericFailure, reason)
+}
+
+/// systemd-boot EFI variables manager.
+#[napi(js_name = "Manager")]
+pub struct JsManager {
+    inner: sdboot::Manager,
+}
+
+#[napi]
+impl JsManager {
+    /// Creates a manager.
+    #[napi(constructor)]
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            inner: sdboot::Manager::new(),
+        }
+    }
+
+    /// Returns the identifiers of the available boot entries, in the menu
+    /// order.
+    #[napi]
+    pub fn entries(&self) -> Result<Vec<String>> {
+        let entries = self.inner.entries().map_err(to_js_error)?;
+        Ok(entries.ids().map(str::to_string).collect())
+    }
+
+    /// Returns the oneshot entry, if set.
+    #[napi]
+    pub fn get_oneshot(&self) -> Result<Option<String>> {
+        self.inner.get_oneshot().map_err(to_js_error)
+    }
+
+    /// Sets the oneshot entry.
+    #[napi]
+    pub fn set_oneshot(&mut self, entry: String) -> Result<()> {
+        self.inner.set_oneshot(&entry).map_err(to_js_error)
+    }
+
+    /// Removes the oneshot entry.
+    #[napi]
+    pub fn remove_oneshot(&mut self) -> Result<()> {
+        self.inner.remove_oneshot().map_err(to_js_error)
+    }
+
+    /// Returns the default entry, if set.
+    #[napi]
+    pub fn get_default(&self) -> Result<Option<String>> {
+        self.inner.get_default_entry().map_err(to_js_error)
+    }
+
+    /// Sets the default entry.
+    #[napi]
+    pub fn set_default(&mut self, entry: String) -> Result<()> {
+        self.inner.set_default(&entry).map_err(to_js_error)
+    }
+
+    /// Removes the default entry, so the one from the boot loader
+    /// configuration applies.
+    #[napi]
+    pub fn remove_default(&mut self) -> Result<()> {
+        self.inner.remove_default().map_err(to_js_error)
+    }
+
+    /// Returns the boot menu timeout, e.g. "5" or "menu-force", if set.
+    #[napi]
+    pub fn get_timeout(&self) -> Result<Option<String>> {
+        self.inner.get_timeout().map_err(to_js_error)
+    }
+
+    /// Sets the boot menu timeout: the seconds, "menu-force", "menu-hidden"
+    /// or "menu-disabled".
+    #[napi]
+    pub fn set_timeout(&mut self, timeout: String) -> Result<()> {
+        let timeout = timeout.parse().map_err(to_js_error)?;
+        self.inner.set_timeout(timeout).map_err(to_js_error)
+    }
+
+    /// Removes the boot menu timeout, so the one from the boot loader
+    /// configuration applies.
+    #[napi]
+    pub fn remove_timeout(&mut self) -> Result<()> {
+        self.inner.remove_timeout().map_err(to_js_error)
+    }
+
+    /// Returns the boot menu timeout for the next boot, if set.
+    #[napi]
+    pub fn get_timeout_oneshot(&self) -> Result<Option<String>> {
+        self.inner.get_timeout_oneshot().map_err(to_js_error)
+    }
+
+    /// Sets the boot menu timeout for the next boot only, like
+    /// [JsManager::set_timeout].
+    #[napi]
+    pub fn set_timeout_oneshot(&mut self, timeout: String) -> Result<()> {
+        let timeout = timeout.parse().map_err(to_js_error)?;
+        self.inner.set_timeout_oneshot(timeout).map_err(to_js_error)
+    }
+
+    /// Removes the boot menu timeout for the next boot.
+    #[napi]
+    pub fn remove_timeout_oneshot(&mut self) -> Result<()> {
+        self.inner.remove_timeout_oneshot().map_err(to_js_error)
+    }
+
+    /// Returns the currently booted entry, if known.
+    #[napi]
+    pub fn get_selected(&self) -> Result<Option<String>> {
+        self.inner.get_selected_entry().map_err(to_js_error)
+    }
+
+    /// Returns the boot loader name and version, if booted with systemd-boot.
+    #[napi]
+    pub fn get_loader_info(&self) -> Result<Option<String>> {
+        self.inner.get_loader_info().map_err(to_js_error)
+    }
+}
@@ -0,0 +1,180 @@
+#ifndef SDBOOT_H
+#define SDBOOT_H
+
+/* Generated by cbindgen, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The operation succeeded.
+ */
+#define SDBOOT_OK 0
+
+/**
+ * The operation failed for a reason without a dedicated error code.
+ */
+#define SDBOOT_ERROR 1
+
+/**
+ * Systemd-boot entries manager.
+ */
+typedef struct SdbootManager SdbootManager;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Returns the message of the last error occurred on the current thread, or
+ * null if there was none.
+ *
+ * The message stays valid until the next failed call on the same thread.
+ */
+const char *sdboot_last_error(void);
+
+/**
+ * Frees a string returned by the library. Null is ignored.
+ *
+ * # Safety
+ *
+ * `value` must be null or a string returned by the library, not freed yet.
+ */
+void sdboot_string_free(char *value);
+
+/**
+ * Creates a manager. It must be freed with [sdboot_manager_free].
+ */
+struct SdbootManager *sdboot_manager_new(void);
+
+/**
+ * Frees the manager. Null is ignored.
+ *
+ * # Safety
+ *
+ * `manager` must be null or a manager returned by [sdboot_manager_new], not
+ * freed yet.
+ */
+void sdboot_manager_free(struct SdbootManager *manager);
+
+/**
+ * Fetches the available entries into a newly allocated array of `count`
+ * strings, which must be freed with [sdboot_entries_free].
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager, `entries` and `count` must be valid for
+ * writes.
+ */
+int sdboot_entries(const struct SdbootManager *manager, char ***entries, uintptr_t *count);
+
+/**
+ * Frees the entries returned by [sdboot_entries]. Null is ignored.
+ *
+ * # Safety
+ *
+ * `entries` and `count` must be the values returned by [sdboot_entries],
+ * not freed yet.
+ */
+void sdboot_entries_free(char **entries, uintptr_t count);
+
+/**
+ * Fetches the boot loader state as a JSON object, like the one the
+ * `GetStatus` service method returns:
+ *
+ * ```json
+ * {"loader_info": "systemd-boot 254", "selected": "arch.conf",
+ *  "default": "arch.conf", "oneshot": null, "entries": ["arch.conf"]}
+ * ```
+ *
+ * The string must be freed with [sdboot_string_free].
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager, `value` must be valid for writes.
+ */
+int sdboot_status_json(const struct SdbootManager *manager, char **value);
+
+/**
+ * Fetches the oneshot entry. `value` is set to null if it is not set.
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager, `value` must be valid for writes.
+ */
+int sdboot_get_oneshot(const struct SdbootManager *manager, char **value);
+
+/**
+ * Fetches the default entry. `value` is set to null if it is not set.
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager, `value` must be valid for writes.
+ */
+int sdboot_get_default(const struct SdbootManager *manager, char **value);
+
+/**
+ * Fetches the currently booted entry. `value` is set to null if it is not
+ * known.
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager, `value` must be valid for writes.
+ */
+int sdboot_get_selected(const struct SdbootManager *manager, char **value);
+
+/**
+ * Fetches the boot loader name and version. `value` is set to null if the
+ * system wasn't booted with systemd-boot.
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager, `value` must be valid for writes.
+ */
+int sdboot_get_loader_info(const struct SdbootManager *manager, char **value);
+
+/**
+ * Sets the oneshot entry.
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager, `value` must be a null-terminated
+ * string.
+ */
+int sdboot_set_oneshot(struct SdbootManager *manager, const char *value);
+
+/**
+ * Sets the default entry.
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager, `value` must be a null-terminated
+ * string.
+ */
+int sdboot_set_default(struct SdbootManager *manager, const char *value);
+
+/**
+ * Removes the oneshot entry.
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager.
+ */
+int sdboot_remove_oneshot(struct SdbootManager *manager);
+
+/**
+ * Removes the default entry.
+ *
+ * # Safety
+ *
+ * `manager` must be a valid manager.
+ */
+int sdboot_remove_default(struct SdbootManager *manager);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* SDBOOT_H */
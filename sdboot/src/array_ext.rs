@@ -10,18 +10,20 @@ pub trait U16ArrayExt {
 
 impl U16ArrayExt for [u16] {
     fn as_u8(&self) -> &[u8] {
-        let length = self.len().checked_mul(2).expect("Integer overflow");
+        // A slice never occupies more than isize::MAX bytes, hence its size in
+        // bytes can't overflow.
+        let length = std::mem::size_of_val(self);
         let begin = self.as_ptr().cast::<u8>();
         // The operation is safe because u16 has stronger align requirements
-        // than u8, and size of u16 is two bytes (hence length is multiplied).
+        // than u8, and the length is the size of the slice in bytes.
         unsafe { std::slice::from_raw_parts(begin, length) }
     }
 
     fn as_u8_mut(&mut self) -> &mut [u8] {
-        let length = self.len().checked_mul(2).expect("Integer overflow");
+        let length = std::mem::size_of_val(self);
         let begin = self.as_mut_ptr().cast::<u8>();
         // The operation is safe because u16 has stronger align requirements
-        // than u8, and size of u16 is two bytes (hence length is multiplied).
+        // than u8, and the length is the size of the slice in bytes.
         unsafe { std::slice::from_raw_parts_mut(begin, length) }
     }
 }
@@ -4,6 +4,8 @@ use std::{
     fs::File,
     os::unix::prelude::AsRawFd,
     path::{Path, PathBuf},
+    sync::{Condvar, Mutex, MutexGuard, PoisonError},
+    time::Duration,
 };
 
 use anyhow::{Context, Result};
@@ -11,24 +13,24 @@ use anyhow::{Context, Result};
 /// A [File] extension trait to allow immutability manipulations.
 pub trait FileAttributes {
     /// Returns the currently set inode flags.
-    fn inode_flags(&self) -> nix::Result<libc::c_long>;
+    fn inode_flags(&self) -> nix::Result<libc::c_int>;
 
     /// Updates the inode flags.
-    fn set_inode_flags(&self, flags: libc::c_long) -> nix::Result<()>;
+    fn set_inode_flags(&self, flags: libc::c_int) -> nix::Result<()>;
 
     /// Sets or unsets the "immutable" flag.
     fn set_immutable(&self, immutable: bool) -> nix::Result<()>;
 }
 
 impl FileAttributes for File {
-    fn inode_flags(&self) -> nix::Result<libc::c_long> {
+    fn inode_flags(&self) -> nix::Result<libc::c_int> {
         let mut flags = 0;
         // Safety: the ioctl request is set up correctly.
         unsafe { get_inode_flags(self.as_raw_fd(), &mut flags) }?;
         Ok(flags)
     }
 
-    fn set_inode_flags(&self, flags: libc::c_long) -> nix::Result<()> {
+    fn set_inode_flags(&self, flags: libc::c_int) -> nix::Result<()> {
         // Safety: the ioctl request is set up correctly.
         unsafe { set_inode_flags(self.as_raw_fd(), &flags) }?;
         Ok(())
@@ -53,43 +55,148 @@ impl FileAttributes for File {
 /// Inode flag "Immutable file".
 ///
 /// See `linux/fs.h`.
-const FS_IMMUTABLE_FL: i64 = 0x10;
+const FS_IMMUTABLE_FL: libc::c_int = 0x10;
 
 /// Sets the immutability back on drop.
 pub struct Guard {
-    attr: i64,
+    attr: libc::c_int,
     path: PathBuf,
 }
 
-nix::ioctl_read!(get_inode_flags, b'f', 1, libc::c_long);
-nix::ioctl_write_ptr!(set_inode_flags, b'f', 2, libc::c_long);
+// `FS_IOC_GETFLAGS` and `FS_IOC_SETFLAGS` are declared with the size of a
+// `long`, but the kernel actually transfers an `int`. Hence the request codes
+// are encoded with the `long` size (the `request_code_*` macros take care of
+// the per-architecture direction bits and layout, e.g. on powerpc and sparc),
+// while the data is an `int`, which matters on big-endian 64-bit targets.
+nix::ioctl_read_bad!(
+    get_inode_flags,
+    nix::request_code_read!(b'f', 1, std::mem::size_of::<libc::c_long>()),
+    libc::c_int
+);
+nix::ioctl_write_ptr_bad!(
+    set_inode_flags,
+    nix::request_code_write!(b'f', 2, std::mem::size_of::<libc::c_long>()),
+    libc::c_int
+);
+
+/// Files made mutable by the alive [Guard]s, along with the flags to restore.
+///
+/// Used to restore the immutability when the process is interrupted, see
+/// [restore_immutability_on_signals].
+static PENDING: Mutex<Vec<(PathBuf, libc::c_int)>> = Mutex::new(Vec::new());
+
+/// Notified when a file is removed from [PENDING].
+static PENDING_CHANGED: Condvar = Condvar::new();
+
+/// How long the interrupted process waits for the writes in flight to
+/// complete before restoring the flags itself.
+const WRITE_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+fn pending() -> MutexGuard<'static, Vec<(PathBuf, libc::c_int)>> {
+    // The list stays consistent even if a holder of the lock panicked.
+    PENDING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl Guard {
+    fn new(attr: libc::c_int, path: &Path) -> Self {
+        pending().push((path.to_owned(), attr));
+        Self {
+            attr,
+            path: path.to_owned(),
+        }
+    }
+
+    /// Leaves the file as it is, i.e. mutable, instead of restoring the flags.
+    pub fn disarm(self) {
+        self.unregister();
+        log::debug!("Leaving file {} mutable", self.path.display());
+        // Nothing is left to restore on drop.
+    }
+
+    /// Removes the file from the pending ones. Returns `false` if it has
+    /// already been removed, e.g. restored by the signal handler.
+    fn unregister(&self) -> bool {
+        let mut pending = pending();
+        match pending.iter().position(|(path, _)| *path == self.path) {
+            Some(position) => {
+                pending.remove(position);
+                PENDING_CHANGED.notify_all();
+                true
+            }
+            None => false,
+        }
+    }
+}
 
 impl Drop for Guard {
     fn drop(&mut self) {
-        let file = match std::fs::File::open(&self.path) {
-            Ok(file) => file,
-            Err(e) => {
-                log::warn!(
-                    "Unable to open file {} to make it immutable: {:#}",
-                    self.path.display(),
-                    e
-                );
-                return;
-            }
-        };
+        if self.unregister() {
+            restore(&self.path, self.attr);
+        }
+    }
+}
 
-        if let Err(error) = file.set_inode_flags(self.attr) {
+/// Sets the inode flags back on the file.
+fn restore(path: &Path, attr: libc::c_int) {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
             log::warn!(
-                "Unable make file {} immutable: {:#}",
-                self.path.display(),
-                error
+                "Unable to open file {} to make it immutable: {:#}",
+                path.display(),
+                e
             );
-        } else {
-            log::debug!("Immutability of {} has been restored", self.path.display())
+            return;
         }
+    };
+
+    if let Err(error) = file.set_inode_flags(attr) {
+        log::warn!("Unable make file {} immutable: {:#}", path.display(), error);
+    } else {
+        log::debug!("Immutability of {} has been restored", path.display())
     }
 }
 
+/// Makes sure the variable files get their "immutable" attribute back when
+/// the process is interrupted by SIGINT, SIGTERM or SIGHUP in the middle of
+/// an update.
+///
+/// Spawns a thread waiting for the signals. When one arrives, the writes in
+/// flight get a moment to complete and restore the attribute themselves, then
+/// it is restored on all the files still mutable, and the process is
+/// terminated as it would be without the handler.
+pub fn restore_immutability_on_signals() -> Result<()> {
+    use signal_hook::{
+        consts::{SIGHUP, SIGINT, SIGTERM},
+        iterator::Signals,
+    };
+
+    let mut signals =
+        Signals::new([SIGINT, SIGTERM, SIGHUP]).context("Unable to install signal handlers")?;
+    std::thread::Builder::new()
+        .name("sdboot-signals".to_string())
+        .spawn(move || {
+            if let Some(signal) = signals.forever().next() {
+                let (mut pending, _) = PENDING_CHANGED
+                    .wait_timeout_while(pending(), WRITE_GRACE_PERIOD, |pending| {
+                        !pending.is_empty()
+                    })
+                    .unwrap_or_else(PoisonError::into_inner);
+                let interrupted = std::mem::take(&mut *pending);
+                drop(pending);
+                for (path, attr) in interrupted {
+                    restore(&path, attr);
+                }
+                if let Err(e) = signal_hook::low_level::emulate_default_handler(signal) {
+                    log::warn!("Unable to terminate on signal {}: {:#}", signal, e);
+                }
+                std::process::exit(128 + signal);
+            }
+        })
+        .context("Unable to spawn the signal handling thread")?;
+    Ok(())
+}
+
 /// Removes the "immutable" attribute from a file at the given path and returns
 /// a [Guard] that will restore the attribute back when dropped.
 ///
@@ -108,11 +215,7 @@ where
         Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
             // It's okay that the file doesn't exist. It will be created when
             // the respective EFI variable is set.
-            let guard = Guard {
-                attr: FS_IMMUTABLE_FL,
-                path: path.to_owned(),
-            };
-            return Ok(Some(guard));
+            return Ok(Some(Guard::new(FS_IMMUTABLE_FL, path)));
         }
         Err(e) => Err(e),
     }
@@ -134,10 +237,5 @@ where
 
     log::debug!("Immutable flag removed from file {}", path.display());
 
-    let guard = Guard {
-        attr: original_attr,
-        path: path.to_owned(),
-    };
-
-    Ok(Some(guard))
+    Ok(Some(Guard::new(original_attr, path)))
 }
@@ -0,0 +1,181 @@
+//! Audit trail of the EFI variables changes.
+//!
+//! When enabled with [crate::Manager::with_audit_log], every successful
+//! mutation is appended to the audit log: when it happened, who did it, which
+//! variable was changed and how. The log is kept as JSON lines and is never
+//! rewritten.
+//!
+//! Only the user IDs reported by the kernel identify who made the change. The
+//! names and IDs taken from the environment (e.g. `SUDO_USER`) can be set by
+//! anyone running the program, and are recorded as unverified claims.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::jsonl;
+
+/// Default location of the audit log on linux.
+#[cfg(target_os = "linux")]
+const DEFAULT_PATH: &str = "/var/log/sdboot/audit.jsonl";
+
+/// A change of a variable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Operation {
+    /// The variable was set.
+    Set,
+    /// The variable was removed.
+    Remove,
+}
+
+/// A single audit record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    /// When the change was made, in seconds since the UNIX epoch.
+    pub timestamp: u64,
+    /// Real user ID of the process which made the change.
+    #[serde(default)]
+    pub uid: Option<u32>,
+    /// Effective user ID of the process which made the change.
+    #[serde(default)]
+    pub euid: Option<u32>,
+    /// The user ID of the sudo invoker, from `SUDO_UID`. Unverified: the
+    /// environment is controlled by the caller.
+    #[serde(default)]
+    pub claimed_sudo_uid: Option<u32>,
+    /// Name of the user who made the change, from `SUDO_USER`, `USER` or
+    /// `USERNAME`. Unverified: the environment is controlled by the caller.
+    #[serde(default, alias = "user")]
+    pub claimed_user: Option<String>,
+    /// What was done to the variable.
+    pub operation: Operation,
+    /// Name of the changed variable.
+    pub variable: String,
+    /// The value before the change, if the variable was set.
+    #[serde(default)]
+    pub old_value: Option<String>,
+    /// The value after the change, if the variable is set.
+    #[serde(default)]
+    pub new_value: Option<String>,
+}
+
+impl AuditRecord {
+    /// Describes the change made just now by the current user.
+    pub fn now(variable: &str, old_value: Option<&str>, new_value: Option<&str>) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        Self {
+            timestamp,
+            uid: crate::change::current_uid(),
+            euid: crate::change::current_euid(),
+            claimed_sudo_uid: std::env::var("SUDO_UID")
+                .ok()
+                .and_then(|uid| uid.parse().ok()),
+            claimed_user: claimed_user(),
+            operation: if new_value.is_some() {
+                Operation::Set
+            } else {
+                Operation::Remove
+            },
+            variable: variable.to_string(),
+            old_value: old_value.map(str::to_string),
+            new_value: new_value.map(str::to_string),
+        }
+    }
+}
+
+/// Name of the user running the process according to the environment,
+/// preferring the one who invoked sudo.
+fn claimed_user() -> Option<String> {
+    ["SUDO_USER", "USER", "USERNAME"]
+        .into_iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|user| !user.is_empty())
+}
+
+/// Append-only audit log backed by a JSON-lines file.
+#[derive(Debug, Clone)]
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    /// Opens the audit log stored at the given path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Opens the audit log at the default location:
+    /// `/var/log/sdboot/audit.jsonl` on linux,
+    /// `%ProgramData%\sdboot\audit.jsonl` on windows.
+    pub fn open_default() -> Result<Self> {
+        #[cfg(target_os = "linux")]
+        let path = PathBuf::from(DEFAULT_PATH);
+        #[cfg(not(target_os = "linux"))]
+        let path = {
+            use anyhow::Context;
+            let program_data = std::env::var_os("ProgramData").context("ProgramData is not set")?;
+            PathBuf::from(program_data)
+                .join("sdboot")
+                .join("audit.jsonl")
+        };
+        Ok(Self::new(path))
+    }
+
+    /// Path to the audit log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Appends the record to the log.
+    pub fn append(&self, record: &AuditRecord) -> Result<()> {
+        jsonl::append(&self.path, record)
+    }
+
+    /// Reads all the records, the oldest first.
+    pub fn records(&self) -> Result<Vec<AuditRecord>> {
+        jsonl::read(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_append_and_read() {
+        let path = std::env::temp_dir().join(format!("sdboot-audit-{}.jsonl", std::process::id()));
+        let log = AuditLog::new(&path);
+        assert_eq!(log.records().unwrap(), vec![]);
+
+        let set = AuditRecord::now("LoaderEntryDefault", None, Some("arch.conf"));
+        let remove = AuditRecord::now("LoaderEntryOneShot", Some("arch.conf"), None);
+        log.append(&set).unwrap();
+        log.append(&remove).unwrap();
+        let records = log.records().unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(records, vec![set, remove]);
+        assert_eq!(records[0].operation, Operation::Set);
+        assert_eq!(records[1].operation, Operation::Remove);
+    }
+
+    #[test]
+    fn check_old_record() {
+        let record: AuditRecord = serde_json::from_str(
+            r#"{"timestamp":1,"uid":0,"user":"alice","operation":"set",
+                "variable":"LoaderEntryDefault","new_value":"arch.conf"}"#,
+        )
+        .unwrap();
+        assert_eq!(record.uid, Some(0));
+        assert_eq!(record.euid, None);
+        assert_eq!(record.claimed_user.as_deref(), Some("alice"));
+    }
+}
@@ -0,0 +1,160 @@
+//! Selection of the boot loader whose entries are managed.
+
+use anyhow::Result;
+
+#[cfg(feature = "grub")]
+use crate::{grub::GrubEnv, ErrorKind};
+use crate::{Entries, Manager};
+
+/// The boot loader whose one shot and default entries are managed: systemd-boot
+/// through the EFI variables or, on the systems not booted with it, GRUB
+/// through its environment block.
+pub enum Backend {
+    /// systemd-boot.
+    SystemdBoot(Manager),
+    /// GRUB, which only supports the one shot and the default entries.
+    #[cfg(feature = "grub")]
+    Grub(GrubEnv),
+}
+
+impl Backend {
+    /// Picks the backend of the running system: GRUB if there is an
+    /// environment block and the boot loader doesn't report itself in the EFI
+    /// variables, systemd-boot otherwise. The firmware is only asked when
+    /// there is a GRUB environment block at all.
+    pub fn detect(manager: Manager) -> Result<Self> {
+        #[cfg(feature = "grub")]
+        if let Some(grub) = GrubEnv::detect() {
+            if manager.get_loader_info()?.is_none() {
+                log::debug!("Using GRUB environment block {}", grub.path().display());
+                return Ok(Backend::Grub(grub));
+            }
+        }
+        Ok(Backend::SystemdBoot(manager))
+    }
+
+    /// The systemd-boot manager, for the rest of the operations. `what` names
+    /// the operation in the error if the backend is GRUB, e.g. "Managing the
+    /// boot menu".
+    #[cfg_attr(not(feature = "grub"), allow(unused_variables))]
+    pub fn manager(&self, what: &str) -> Result<&Manager> {
+        match self {
+            Backend::SystemdBoot(manager) => Ok(manager),
+            #[cfg(feature = "grub")]
+            Backend::Grub(_) => Err(unsupported(what)),
+        }
+    }
+
+    /// Like [Backend::manager], for the operations changing the variables.
+    #[cfg_attr(not(feature = "grub"), allow(unused_variables))]
+    pub fn manager_mut(&mut self, what: &str) -> Result<&mut Manager> {
+        match self {
+            Backend::SystemdBoot(manager) => Ok(manager),
+            #[cfg(feature = "grub")]
+            Backend::Grub(_) => Err(unsupported(what)),
+        }
+    }
+
+    /// Lists the boot loader entries, in the menu order.
+    pub fn entries(&self) -> Result<Entries> {
+        match self {
+            Backend::SystemdBoot(manager) => manager.entries(),
+            #[cfg(feature = "grub")]
+            Backend::Grub(grub) => grub.entries(),
+        }
+    }
+
+    /// Returns the currently booted entry, which GRUB doesn't record.
+    pub fn get_selected_entry(&self) -> Result<Option<String>> {
+        match self {
+            Backend::SystemdBoot(manager) => manager.get_selected_entry(),
+            #[cfg(feature = "grub")]
+            Backend::Grub(_) => Ok(None),
+        }
+    }
+
+    /// Fetches the current oneshot entry value.
+    pub fn get_oneshot(&self) -> Result<Option<String>> {
+        match self {
+            Backend::SystemdBoot(manager) => manager.get_oneshot(),
+            #[cfg(feature = "grub")]
+            Backend::Grub(grub) => grub.get_oneshot(),
+        }
+    }
+
+    /// Returns the default entry.
+    pub fn get_default_entry(&self) -> Result<Option<String>> {
+        match self {
+            Backend::SystemdBoot(manager) => manager.get_default_entry(),
+            #[cfg(feature = "grub")]
+            Backend::Grub(grub) => grub.get_default_entry(),
+        }
+    }
+
+    /// Sets value of the oneshot entry.
+    pub fn set_oneshot(&mut self, value: &str) -> Result<()> {
+        match self {
+            Backend::SystemdBoot(manager) => manager.set_oneshot(value),
+            #[cfg(feature = "grub")]
+            Backend::Grub(grub) => grub.set_oneshot(value),
+        }
+    }
+
+    /// Sets value of the default entry.
+    pub fn set_default(&mut self, value: &str) -> Result<()> {
+        match self {
+            Backend::SystemdBoot(manager) => manager.set_default(value),
+            #[cfg(feature = "grub")]
+            Backend::Grub(grub) => grub.set_default(value),
+        }
+    }
+
+    /// Sets value of the oneshot entry unless it already has the same value.
+    ///
+    /// Returns whether the entry has been written.
+    pub fn set_oneshot_if_changed(&mut self, value: &str) -> Result<bool> {
+        if self.get_oneshot()?.as_deref() == Some(value) {
+            log::debug!("Oneshot entry is already set to {}", value);
+            return Ok(false);
+        }
+        self.set_oneshot(value)?;
+        Ok(true)
+    }
+
+    /// Sets value of the default entry unless it already has the same value.
+    ///
+    /// Returns whether the entry has been written.
+    pub fn set_default_if_changed(&mut self, value: &str) -> Result<bool> {
+        if self.get_default_entry()?.as_deref() == Some(value) {
+            log::debug!("Default entry is already set to {}", value);
+            return Ok(false);
+        }
+        self.set_default(value)?;
+        Ok(true)
+    }
+
+    /// Removes the oneshot entry.
+    pub fn remove_oneshot(&mut self) -> Result<()> {
+        match self {
+            Backend::SystemdBoot(manager) => manager.remove_oneshot(),
+            #[cfg(feature = "grub")]
+            Backend::Grub(grub) => grub.remove_oneshot(),
+        }
+    }
+
+    /// Removes the default entry.
+    pub fn remove_default(&mut self) -> Result<()> {
+        match self {
+            Backend::SystemdBoot(manager) => manager.remove_default(),
+            #[cfg(feature = "grub")]
+            Backend::Grub(grub) => grub.remove_default(),
+        }
+    }
+}
+
+/// The error for the operations GRUB doesn't support.
+#[cfg(feature = "grub")]
+fn unsupported(what: &str) -> anyhow::Error {
+    anyhow::Error::new(ErrorKind::NotSystemdBoot)
+        .context(format!("{} is not supported with GRUB", what))
+}
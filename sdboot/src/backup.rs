@@ -0,0 +1,100 @@
+//! Snapshots of the boot loader configuration the OS can change, to roll
+//! back experiments.
+
+use anyhow::{Context, Result};
+
+use crate::{dump::Variable, Manager, Timeout};
+
+/// The values of the variables the OS sets. [None] means the variable is not
+/// set, and is removed on restore.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Backup {
+    /// The default entry (LoaderEntryDefault).
+    pub default: Option<String>,
+    /// The one shot entry (LoaderEntryOneShot).
+    pub oneshot: Option<String>,
+    /// The menu timeout (LoaderConfigTimeout).
+    pub timeout: Option<String>,
+    /// The menu timeout for the next boot (LoaderConfigTimeoutOneShot).
+    pub timeout_oneshot: Option<String>,
+}
+
+impl Backup {
+    /// Reads the current values.
+    pub fn take(manager: &Manager) -> Result<Self> {
+        Ok(Self {
+            default: manager.get_default_entry()?,
+            oneshot: manager.get_oneshot()?,
+            timeout: manager.get_timeout()?,
+            timeout_oneshot: manager.get_timeout_oneshot()?,
+        })
+    }
+
+    /// Takes the values out of the variables, e.g. imported from a dump. The
+    /// variables missing from the dump are considered unset.
+    pub fn from_variables(variables: &[Variable]) -> Self {
+        let value = |name: &str| {
+            variables
+                .iter()
+                .find(|variable| variable.name == name)
+                .and_then(Variable::as_strings)
+                .and_then(|strings| strings.into_iter().next())
+        };
+        Self {
+            default: value("LoaderEntryDefault"),
+            oneshot: value("LoaderEntryOneShot"),
+            timeout: value("LoaderConfigTimeout"),
+            timeout_oneshot: value("LoaderConfigTimeoutOneShot"),
+        }
+    }
+
+    /// Writes the values back, skipping the ones which haven't changed.
+    ///
+    /// Returns the names of the changed variables.
+    pub fn restore(&self, manager: &mut Manager) -> Result<Vec<&'static str>> {
+        // Validate everything before writing anything.
+        let parse = |timeout: &Option<String>| {
+            timeout
+                .as_deref()
+                .map(str::parse::<Timeout>)
+                .transpose()
+                .context("Invalid timeout in the backup")
+        };
+        let timeout = parse(&self.timeout)?;
+        let timeout_oneshot = parse(&self.timeout_oneshot)?;
+
+        let current = Self::take(manager)?;
+        let mut changed = Vec::new();
+        if current.default != self.default {
+            match &self.default {
+                Some(default) => manager.set_default(default)?,
+                None => manager.remove_default()?,
+            }
+            changed.push("LoaderEntryDefault");
+        }
+        if current.oneshot != self.oneshot {
+            match &self.oneshot {
+                Some(oneshot) => manager.set_oneshot(oneshot)?,
+                None => manager.remove_oneshot()?,
+            }
+            changed.push("LoaderEntryOneShot");
+        }
+        if current.timeout != self.timeout {
+            match timeout {
+                Some(timeout) => manager.set_timeout(timeout)?,
+                None => manager.remove_timeout()?,
+            }
+            changed.push("LoaderConfigTimeout");
+        }
+        if current.timeout_oneshot != self.timeout_oneshot {
+            match timeout_oneshot {
+                Some(timeout) => manager.set_timeout_oneshot(timeout)?,
+                None => manager.remove_timeout_oneshot()?,
+            }
+            changed.push("LoaderConfigTimeoutOneShot");
+        }
+        Ok(changed)
+    }
+}
@@ -0,0 +1,355 @@
+//! C ABI bindings.
+//!
+//! All the functions returning `int` return [SDBOOT_OK] on success, or an
+//! error code otherwise: either a code of an [ErrorKind], or [SDBOOT_ERROR]
+//! for failures without a dedicated code. The error message is available via
+//! [sdboot_last_error].
+//!
+//! Strings returned by the library must be freed with [sdboot_string_free].
+
+use std::{
+    cell::RefCell,
+    ffi::{c_char, c_int, CStr, CString},
+    ptr,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{service::Status, ErrorKind, Manager};
+
+/// The operation succeeded.
+pub const SDBOOT_OK: c_int = 0;
+
+/// The operation failed for a reason without a dedicated error code.
+pub const SDBOOT_ERROR: c_int = 1;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Converts the result into a status code, storing the error message.
+fn report(result: Result<()>) -> c_int {
+    match result {
+        Ok(()) => SDBOOT_OK,
+        Err(error) => {
+            let code = ErrorKind::of(&error).map_or(SDBOOT_ERROR, ErrorKind::code);
+            let message = CString::new(format!("{:#}", error).replace('\0', "")).ok();
+            LAST_ERROR.with(|last| *last.borrow_mut() = message);
+            code
+        }
+    }
+}
+
+fn into_c_string(value: String) -> Result<*mut c_char> {
+    Ok(CString::new(value)
+        .context("String contains a null byte")?
+        .into_raw())
+}
+
+/// # Safety
+///
+/// `value` must be null or point to a null-terminated string.
+unsafe fn from_c_str<'a>(value: *const c_char) -> Result<&'a str> {
+    anyhow::ensure!(!value.is_null(), "String is null");
+    CStr::from_ptr(value)
+        .to_str()
+        .context("String is not valid UTF-8")
+}
+
+/// # Safety
+///
+/// `manager` must be null or point to a valid manager, `value` must be null
+/// or valid for writes.
+unsafe fn get_string(
+    manager: *const Manager,
+    value: *mut *mut c_char,
+    getter: fn(&Manager) -> Result<Option<String>>,
+) -> c_int {
+    report((|| {
+        let manager = manager.as_ref().context("Manager is null")?;
+        anyhow::ensure!(!value.is_null(), "Output pointer is null");
+        *value = match getter(manager)? {
+            Some(string) => into_c_string(string)?,
+            None => ptr::null_mut(),
+        };
+        Ok(())
+    })())
+}
+
+/// # Safety
+///
+/// `manager` must be null or point to a valid manager, `value` must be null
+/// or point to a null-terminated string.
+unsafe fn set_string(
+    manager: *mut Manager,
+    value: *const c_char,
+    setter: fn(&mut Manager, &str) -> Result<()>,
+) -> c_int {
+    report((|| {
+        let manager = manager.as_mut().context("Manager is null")?;
+        setter(manager, from_c_str(value)?)
+    })())
+}
+
+/// Returns the message of the last error occurred on the current thread, or
+/// null if there was none.
+///
+/// The message stays valid until the next failed call on the same thread.
+#[no_mangle]
+pub extern "C" fn sdboot_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Frees a string returned by the library. Null is ignored.
+///
+/// # Safety
+///
+/// `value` must be null or a string returned by the library, not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Creates a manager. It must be freed with [sdboot_manager_free].
+#[no_mangle]
+pub extern "C" fn sdboot_manager_new() -> *mut Manager {
+    Box::into_raw(Box::new(Manager::new()))
+}
+
+/// Frees the manager. Null is ignored.
+///
+/// # Safety
+///
+/// `manager` must be null or a manager returned by [sdboot_manager_new], not
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_manager_free(manager: *mut Manager) {
+    if !manager.is_null() {
+        drop(Box::from_raw(manager));
+    }
+}
+
+/// Fetches the available entries into a newly allocated array of `count`
+/// strings, which must be freed with [sdboot_entries_free].
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `entries` and `count` must be valid for
+/// writes.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_entries(
+    manager: *const Manager,
+    entries: *mut *mut *mut c_char,
+    count: *mut usize,
+) -> c_int {
+    report((|| {
+        let manager = manager.as_ref().context("Manager is null")?;
+        anyhow::ensure!(
+            !entries.is_null() && !count.is_null(),
+            "Output pointer is null"
+        );
+        let list = manager
+            .entries()?
+            .ids()
+            .map(|id| into_c_string(id.to_string()))
+            .collect::<Result<Vec<_>>>()?
+            .into_boxed_slice();
+        *count = list.len();
+        *entries = Box::into_raw(list).cast::<*mut c_char>();
+        Ok(())
+    })())
+}
+
+/// Frees the entries returned by [sdboot_entries]. Null is ignored.
+///
+/// # Safety
+///
+/// `entries` and `count` must be the values returned by [sdboot_entries],
+/// not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_entries_free(entries: *mut *mut c_char, count: usize) {
+    if entries.is_null() {
+        return;
+    }
+    let entries = Box::from_raw(ptr::slice_from_raw_parts_mut(entries, count));
+    for &entry in entries.iter() {
+        sdboot_string_free(entry);
+    }
+}
+
+/// Fetches the boot loader state as a JSON object, like the one the
+/// `GetStatus` service method returns:
+///
+/// ```json
+/// {"loader_info": "systemd-boot 254", "selected": "arch.conf",
+///  "default": "arch.conf", "oneshot": null, "entries": ["arch.conf"]}
+/// ```
+///
+/// The string must be freed with [sdboot_string_free].
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `value` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_status_json(
+    manager: *const Manager,
+    value: *mut *mut c_char,
+) -> c_int {
+    get_string(manager, value, |manager| {
+        let status = Status::current(manager)?;
+        Ok(Some(
+            serde_json::to_string(&status).context("Unable to serialize the status")?,
+        ))
+    })
+}
+
+/// Fetches the oneshot entry. `value` is set to null if it is not set.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `value` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_get_oneshot(
+    manager: *const Manager,
+    value: *mut *mut c_char,
+) -> c_int {
+    get_string(manager, value, Manager::get_oneshot)
+}
+
+/// Fetches the default entry. `value` is set to null if it is not set.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `value` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_get_default(
+    manager: *const Manager,
+    value: *mut *mut c_char,
+) -> c_int {
+    get_string(manager, value, Manager::get_default_entry)
+}
+
+/// Fetches the currently booted entry. `value` is set to null if it is not
+/// known.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `value` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_get_selected(
+    manager: *const Manager,
+    value: *mut *mut c_char,
+) -> c_int {
+    get_string(manager, value, Manager::get_selected_entry)
+}
+
+/// Fetches the boot loader name and version. `value` is set to null if the
+/// system wasn't booted with systemd-boot.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `value` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_get_loader_info(
+    manager: *const Manager,
+    value: *mut *mut c_char,
+) -> c_int {
+    get_string(manager, value, Manager::get_loader_info)
+}
+
+/// Sets the oneshot entry.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `value` must be a null-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_set_oneshot(manager: *mut Manager, value: *const c_char) -> c_int {
+    set_string(manager, value, Manager::set_oneshot)
+}
+
+/// Sets the default entry.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager, `value` must be a null-terminated
+/// string.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_set_default(manager: *mut Manager, value: *const c_char) -> c_int {
+    set_string(manager, value, Manager::set_default)
+}
+
+/// Removes the oneshot entry.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_remove_oneshot(manager: *mut Manager) -> c_int {
+    report((|| {
+        let manager = manager.as_mut().context("Manager is null")?;
+        manager.remove_oneshot()
+    })())
+}
+
+/// Removes the default entry.
+///
+/// # Safety
+///
+/// `manager` must be a valid manager.
+#[no_mangle]
+pub unsafe extern "C" fn sdboot_remove_default(manager: *mut Manager) -> c_int {
+    report((|| {
+        let manager = manager.as_mut().context("Manager is null")?;
+        manager.remove_default()
+    })())
+}
+
+#[cfg(test)]
+mod test {
+    /// The committed header must be regenerated when the functions change,
+    /// see the README.
+    #[test]
+    fn check_header() {
+        let header = include_str!("../include/sdboot.h");
+        let exported = include_str!("capi.rs")
+            .lines()
+            .filter_map(|line| line.split_once("extern \"C\" fn "))
+            .filter_map(|(_, rest)| rest.split_once('('))
+            .map(|(name, _)| name)
+            .collect::<Vec<_>>();
+        for name in [
+            "sdboot_status_json",
+            "sdboot_remove_oneshot",
+            "sdboot_remove_default",
+        ] {
+            assert!(exported.contains(&name), "{} is not exported", name);
+        }
+        for name in &exported {
+            assert!(
+                header.contains(&format!(" *{}(", name)) || header.contains(&format!(" {}(", name)),
+                "{} is missing in the header",
+                name
+            );
+        }
+        let declared = header
+            .lines()
+            .filter_map(|line| line.split_once("sdboot_"))
+            .filter_map(|(_, rest)| rest.split_once('('))
+            .map(|(name, _)| format!("sdboot_{}", name))
+            .collect::<Vec<_>>();
+        for name in &declared {
+            assert!(
+                exported.contains(&name.as_str()),
+                "{} is not exported anymore",
+                name
+            );
+        }
+    }
+}
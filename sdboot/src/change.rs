@@ -0,0 +1,48 @@
+//! Reporting of the EFI variables changes.
+//!
+//! Every successful mutation performed by [crate::Manager] is logged with the
+//! [LOG_TARGET] target and the following structured fields (`log` key-value
+//! pairs), which end up as journal fields when logging to journald:
+//! `sdboot_variable`, `sdboot_old_value`, `sdboot_new_value` and `sdboot_uid`.
+
+/// Log target of the variable change records.
+pub const LOG_TARGET: &str = "sdboot::change";
+
+/// Logs a change of the variable. [None] stands for a missing variable.
+pub(crate) fn report(variable: &str, old: Option<&str>, new: Option<&str>) {
+    log::info!(
+        target: LOG_TARGET,
+        sdboot_variable = variable,
+        sdboot_old_value = old,
+        sdboot_new_value = new,
+        sdboot_uid = current_uid();
+        "{} changed from {:?} to {:?}",
+        variable,
+        old,
+        new
+    );
+}
+
+/// Real user ID of the process, if the platform has one.
+#[cfg(target_os = "linux")]
+pub(crate) fn current_uid() -> Option<u32> {
+    Some(nix::unistd::getuid().as_raw())
+}
+
+/// Real user ID of the process, if the platform has one.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn current_uid() -> Option<u32> {
+    None
+}
+
+/// Effective user ID of the process.
+#[cfg(all(target_os = "linux", feature = "audit"))]
+pub(crate) fn current_euid() -> Option<u32> {
+    Some(nix::unistd::geteuid().as_raw())
+}
+
+/// Effective user ID of the process, if the platform has one.
+#[cfg(all(not(target_os = "linux"), feature = "audit"))]
+pub(crate) fn current_euid() -> Option<u32> {
+    None
+}
@@ -0,0 +1,271 @@
+//! Consistency checks of the boot loader state.
+
+use std::{fmt, path::PathBuf};
+
+use crate::{
+    esp::{Divergence, EspEntry},
+    parse, Entries,
+};
+
+/// A problem found by the diagnostics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Finding {
+    /// The boot loader reports the same entry identifier several times.
+    DuplicateLoaderEntry(String),
+    /// Several files on the disk map to the same entry identifier.
+    DuplicateEspEntry {
+        /// The entry identifier.
+        id: String,
+        /// The conflicting files.
+        paths: Vec<PathBuf>,
+    },
+    /// Several entries share the same sort key and version, hence their
+    /// order in the menu is not well defined.
+    ConflictingSortKey {
+        /// The shared sort key.
+        sort_key: String,
+        /// The shared version.
+        version: String,
+        /// The conflicting entries.
+        ids: Vec<String>,
+    },
+    /// The default entry is a pattern matching several entries.
+    AmbiguousDefault {
+        /// The default entry pattern.
+        pattern: String,
+        /// The matching entries.
+        matches: Vec<String>,
+    },
+    /// The default entry doesn't match any entry.
+    UnknownDefault(String),
+    /// The oneshot entry doesn't match any entry.
+    StaleOneshot(String),
+    /// The boot loader reports an entry which is missing on the disk.
+    MissingOnDisk(String),
+    /// An entry on the disk is not reported by the boot loader.
+    UnknownToLoader(String),
+    /// The EFI variables can't be changed because efivarfs is mounted
+    /// read-only.
+    ReadOnlyEfivarfs,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Finding::DuplicateLoaderEntry(id) => {
+                write!(f, "Entry {:?} is reported several times by the boot loader", id)
+            }
+            Finding::DuplicateEspEntry { id, paths } => {
+                write!(f, "Entry {:?} is defined by several files:", id)?;
+                for path in paths {
+                    write!(f, " {}", path.display())?;
+                }
+                Ok(())
+            }
+            Finding::ConflictingSortKey {
+                sort_key,
+                version,
+                ids,
+            } => write!(
+                f,
+                "Entries {:?} share sort key {:?} and version {:?}",
+                ids, sort_key, version
+            ),
+            Finding::AmbiguousDefault { pattern, matches } => write!(
+                f,
+                "Default entry {:?} matches several entries: {:?}",
+                pattern, matches
+            ),
+            Finding::UnknownDefault(pattern) => {
+                write!(f, "Default entry {:?} doesn't match any entry", pattern)
+            }
+            Finding::StaleOneshot(id) => write!(
+                f,
+                "Oneshot entry {:?} doesn't match any entry, the default entry will be booted instead",
+                id
+            ),
+            Finding::MissingOnDisk(id) => write!(f, "Entry {:?} is not found on the ESP", id),
+            Finding::UnknownToLoader(id) => write!(
+                f,
+                "Entry {:?} is on the ESP, but not reported by the boot loader",
+                id
+            ),
+            Finding::ReadOnlyEfivarfs => f.write_str(
+                "efivarfs is mounted read-only, remount it with \
+                 `mount -o remount,rw /sys/firmware/efi/efivars`",
+            ),
+        }
+    }
+}
+
+/// The state to check.
+#[derive(Debug, Default)]
+pub struct State<'a> {
+    /// Entries reported by the boot loader.
+    pub loader_entries: Option<&'a Entries>,
+    /// Entries found on the disk.
+    pub esp_entries: Option<&'a [EspEntry]>,
+    /// The default entry (pattern).
+    pub default: Option<&'a str>,
+    /// The oneshot entry.
+    pub oneshot: Option<&'a str>,
+    /// Whether the variables storage is read-only.
+    pub read_only: bool,
+}
+
+/// Runs all the checks applicable to the given state.
+pub fn check(state: &State<'_>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    if state.read_only {
+        findings.push(Finding::ReadOnlyEfivarfs);
+    }
+
+    if let Some(loader_entries) = state.loader_entries {
+        let mut seen = Vec::new();
+        for id in loader_entries.ids() {
+            if seen.contains(&id) {
+                findings.push(Finding::DuplicateLoaderEntry(id.to_string()));
+            }
+            seen.push(id);
+        }
+        if let Some(oneshot) = state.oneshot {
+            if !loader_entries.contains(oneshot) {
+                findings.push(Finding::StaleOneshot(oneshot.to_string()));
+            }
+        }
+        if let Some(pattern) = state.default {
+            let matches = loader_entries
+                .ids()
+                .filter(|id| parse::glob_match(pattern, id))
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            match matches.len() {
+                0 => findings.push(Finding::UnknownDefault(pattern.to_string())),
+                1 => { /* Unambiguous */ }
+                _ => findings.push(Finding::AmbiguousDefault {
+                    pattern: pattern.to_string(),
+                    matches,
+                }),
+            }
+        }
+    }
+
+    if let Some(esp_entries) = state.esp_entries {
+        let mut seen: Vec<(&str, Vec<PathBuf>)> = Vec::new();
+        for entry in esp_entries {
+            match seen.iter_mut().find(|(id, _)| *id == entry.id) {
+                Some((_, paths)) => paths.push(entry.path.clone()),
+                None => seen.push((&entry.id, vec![entry.path.clone()])),
+            }
+        }
+        findings.extend(seen.into_iter().filter(|(_, paths)| paths.len() > 1).map(
+            |(id, paths)| Finding::DuplicateEspEntry {
+                id: id.to_string(),
+                paths,
+            },
+        ));
+
+        let mut groups: Vec<(&str, &str, Vec<String>)> = Vec::new();
+        for entry in esp_entries {
+            let (Some(sort_key), Some(version)) = (&entry.sort_key, &entry.version) else {
+                continue;
+            };
+            match groups
+                .iter_mut()
+                .find(|(key, ver, _)| key == sort_key && ver == version)
+            {
+                Some((_, _, ids)) => ids.push(entry.id.clone()),
+                None => groups.push((sort_key, version, vec![entry.id.clone()])),
+            }
+        }
+        findings.extend(groups.into_iter().filter(|(_, _, ids)| ids.len() > 1).map(
+            |(sort_key, version, ids)| Finding::ConflictingSortKey {
+                sort_key: sort_key.to_string(),
+                version: version.to_string(),
+                ids,
+            },
+        ));
+
+        if let Some(loader_entries) = state.loader_entries {
+            let divergence = Divergence::new(loader_entries, esp_entries);
+            findings.extend(
+                divergence
+                    .only_in_loader
+                    .into_iter()
+                    .map(Finding::MissingOnDisk),
+            );
+            findings.extend(
+                divergence
+                    .only_on_disk
+                    .into_iter()
+                    .map(Finding::UnknownToLoader),
+            );
+        }
+    }
+
+    findings
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Entry;
+
+    fn on_disk(id: &str, path: &str, sort_key: &str, version: &str) -> EspEntry {
+        EspEntry {
+            id: id.to_string(),
+            path: PathBuf::from(path),
+            title: None,
+            version: Some(version.to_string()),
+            machine_id: None,
+            sort_key: Some(sort_key.to_string()),
+            boot_counter: None,
+        }
+    }
+
+    #[test]
+    fn check_findings() {
+        let loader: Entries = ["arch.conf", "arch-lts.conf", "arch.conf"]
+            .into_iter()
+            .map(Entry::new)
+            .collect();
+        let esp = [
+            on_disk("arch.conf", "/efi/arch.conf", "arch", "6.8"),
+            on_disk("arch.conf", "/boot/arch.conf", "arch", "6.8"),
+            on_disk("arch-lts.conf", "/efi/arch-lts.conf", "arch", "6.6"),
+        ];
+        let findings = check(&State {
+            loader_entries: Some(&loader),
+            esp_entries: Some(&esp),
+            default: Some("arch*"),
+            oneshot: Some("gone.conf"),
+            read_only: false,
+        });
+        assert_eq!(
+            findings,
+            [
+                Finding::DuplicateLoaderEntry("arch.conf".into()),
+                Finding::StaleOneshot("gone.conf".into()),
+                Finding::AmbiguousDefault {
+                    pattern: "arch*".into(),
+                    matches: vec![
+                        "arch.conf".into(),
+                        "arch-lts.conf".into(),
+                        "arch.conf".into()
+                    ],
+                },
+                Finding::DuplicateEspEntry {
+                    id: "arch.conf".into(),
+                    paths: vec!["/efi/arch.conf".into(), "/boot/arch.conf".into()],
+                },
+                Finding::ConflictingSortKey {
+                    sort_key: "arch".into(),
+                    version: "6.8".into(),
+                    ids: vec!["arch.conf".into(), "arch.conf".into()],
+                },
+            ]
+        );
+    }
+}
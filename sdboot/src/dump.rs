@@ -0,0 +1,222 @@
+//! Raw contents of the boot loader interface variables, for bug reports.
+//!
+//! The variables can be exchanged with the UEFI shell's `dmpstore` tool in
+//! the format of `dmpstore -s`: for every variable, the little-endian name
+//! and value sizes, the NUL-terminated UTF-16 name, the vendor GUID, the
+//! attributes, the value and the CRC32 of all of the above.
+
+use anyhow::Context;
+
+use crate::manager::SYSTEMD_BOOT_VENDOR_RAW;
+
+/// Names of the variable attributes, by their bits.
+const ATTRIBUTES: [(u32, &str); 7] = [
+    (0x01, "NV"),
+    (0x02, "BS"),
+    (0x04, "RT"),
+    (0x08, "HW-ERROR"),
+    (0x10, "AUTH-WRITE"),
+    (0x20, "TIME-AUTH"),
+    (0x40, "APPEND"),
+];
+
+/// An EFI variable as it is stored in the NVRAM.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Variable {
+    /// The variable name without the vendor UUID, e.g. "LoaderEntryDefault".
+    pub name: String,
+    /// The raw attributes bits.
+    pub attributes: u32,
+    /// The value.
+    pub value: Vec<u8>,
+}
+
+impl Variable {
+    /// Lists the names of the attributes, e.g. `["NV", "BS", "RT"]`.
+    pub fn attribute_names(&self) -> Vec<&'static str> {
+        ATTRIBUTES
+            .iter()
+            .filter(|(bit, _)| self.attributes & bit != 0)
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    /// Decodes the value as NUL-separated UTF-16 strings, like most of the
+    /// boot loader interface variables are. [None] if the value doesn't look
+    /// like text, i.e. it is not NUL-terminated, not valid UTF-16, contains
+    /// control characters or empty strings.
+    pub fn as_strings(&self) -> Option<Vec<String>> {
+        let words = crate::parse::u16_le_words(&self.value)?;
+        let [text @ .., 0] = &words[..] else {
+            return None;
+        };
+        text.split(|&word| word == 0)
+            .map(|string| {
+                String::from_utf16(string)
+                    .ok()
+                    .filter(|string| !string.is_empty() && !string.chars().any(char::is_control))
+            })
+            .collect()
+    }
+
+    /// Formats the value as space-separated hex bytes.
+    pub fn hex(&self) -> String {
+        self.value
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Encodes the variables of the boot loader interface in the `dmpstore -s`
+/// format.
+pub fn to_dmpstore(variables: &[Variable]) -> Vec<u8> {
+    let mut output = Vec::new();
+    for variable in variables {
+        let name = crate::parse::encode_utf16(&variable.name);
+        let start = output.len();
+        output.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        output.extend_from_slice(&(variable.value.len() as u32).to_le_bytes());
+        output.extend_from_slice(&name);
+        output.extend_from_slice(&SYSTEMD_BOOT_VENDOR_RAW.to_bytes_le());
+        output.extend_from_slice(&variable.attributes.to_le_bytes());
+        output.extend_from_slice(&variable.value);
+        let crc = crc32(&output[start..]);
+        output.extend_from_slice(&crc.to_le_bytes());
+    }
+    output
+}
+
+/// Decodes the variables saved with `dmpstore -s`, skipping the ones of the
+/// vendors other than the boot loader interface.
+pub fn from_dmpstore(mut input: &[u8]) -> anyhow::Result<Vec<Variable>> {
+    let mut variables = Vec::new();
+    let mut index = 0;
+    while !input.is_empty() {
+        index += 1;
+        let (variable, rest) = dmpstore_variable(input)
+            .with_context(|| format!("Malformed variable #{} in the dump", index))?;
+        input = rest;
+        if let Some(variable) = variable {
+            variables.push(variable);
+        }
+    }
+    Ok(variables)
+}
+
+/// Decodes a single variable, [None] if it is of another vendor. Returns the
+/// rest of the input as well.
+fn dmpstore_variable(input: &[u8]) -> anyhow::Result<(Option<Variable>, &[u8])> {
+    let read_u32 = |offset: usize| {
+        input
+            .get(offset..offset + 4)
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(u32::from_le_bytes)
+            .context("Unexpected end of the dump")
+    };
+    let name_size = read_u32(0)? as usize;
+    let value_size = read_u32(4)? as usize;
+    let name_end = 8 + name_size;
+    let vendor_end = name_end + 16;
+    let value_start = vendor_end + 4;
+    let crc_start = value_start + value_size;
+    let attributes = read_u32(vendor_end)?;
+    let crc = read_u32(crc_start)?;
+    anyhow::ensure!(
+        crc == crc32(&input[..crc_start]),
+        "Checksum mismatch, the dump is corrupted"
+    );
+
+    let rest = &input[crc_start + 4..];
+    if input[name_end..vendor_end] != SYSTEMD_BOOT_VENDOR_RAW.to_bytes_le() {
+        return Ok((None, rest));
+    }
+    let name = crate::parse::u16_le_words(&input[8..name_end])
+        .and_then(|words| match &words[..] {
+            [name @ .., 0] => String::from_utf16(name).ok(),
+            _ => None,
+        })
+        .context("Malformed variable name")?;
+    let variable = Variable {
+        name,
+        attributes,
+        value: input[value_start..crc_start].to_vec(),
+    };
+    Ok((Some(variable), rest))
+}
+
+/// The CRC32 the UEFI boot services calculate, i.e. the one of IEEE 802.3.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_decoding() {
+        let variable = Variable {
+            name: "LoaderEntries".into(),
+            attributes: 0x6,
+            value: b"a\0.\0c\0\0\0b\0\0\0".to_vec(),
+        };
+        assert_eq!(variable.attribute_names(), ["BS", "RT"]);
+        assert_eq!(
+            variable.as_strings(),
+            Some(vec!["a.c".to_string(), "b".to_string()])
+        );
+
+        let features = Variable {
+            name: "LoaderFeatures".into(),
+            attributes: 0x6,
+            value: vec![0x7f, 0, 0, 0, 0, 0, 0, 0],
+        };
+        assert_eq!(features.as_strings(), None);
+        assert_eq!(features.hex(), "7f 00 00 00 00 00 00 00");
+
+        let unterminated = Variable {
+            value: b"a\0".to_vec(),
+            ..variable
+        };
+        assert_eq!(unterminated.as_strings(), None);
+    }
+
+    #[test]
+    fn check_dmpstore() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let variables = vec![
+            Variable {
+                name: "LoaderEntryDefault".into(),
+                attributes: 0x7,
+                value: b"a\0\0\0".to_vec(),
+            },
+            Variable {
+                name: "LoaderConfigTimeout".into(),
+                attributes: 0x7,
+                value: b"5\0\0\0".to_vec(),
+            },
+        ];
+        let mut dump = to_dmpstore(&variables);
+        assert_eq!(from_dmpstore(&dump).unwrap(), variables);
+
+        let last = dump.len() - 1;
+        dump[last] ^= 1;
+        assert!(from_dmpstore(&dump).is_err());
+        assert!(from_dmpstore(&dump[..10]).is_err());
+    }
+}
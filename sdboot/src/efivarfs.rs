@@ -0,0 +1,147 @@
+//! Linux efivarfs specifics.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use efivar::efi::VariableName;
+
+use crate::{
+    error::{NvramFull, NvramUsage},
+    ErrorKind,
+};
+
+/// Where efivarfs is mounted.
+pub(crate) const MOUNT_POINT: &str = "/sys/firmware/efi/efivars";
+
+/// Path to the file backing the variable, i.e.
+/// `/sys/firmware/efi/efivars/<Name>-<vendor UUID>`.
+pub(crate) fn variable_path(var: &VariableName) -> PathBuf {
+    Path::new(MOUNT_POINT).join(var.to_string())
+}
+
+/// Checks whether efivarfs is mounted read-only, according to the contents of
+/// `/proc/self/mounts`. [None] means efivarfs is not mounted.
+fn mount_read_only(mounts: &str) -> Option<bool> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (_device, mount_point, fs_type, options) = (
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+                fields.next()?,
+            );
+            (fs_type == "efivarfs" && mount_point == MOUNT_POINT)
+                .then(|| options.split(',').any(|option| option == "ro"))
+        })
+        // The last mount wins.
+        .last()
+}
+
+/// Checks whether efivarfs is mounted at all. Assumes it is when the mount
+/// table can't be read.
+pub(crate) fn is_mounted() -> bool {
+    match std::fs::read_to_string("/proc/self/mounts") {
+        Ok(mounts) => mount_read_only(&mounts).is_some(),
+        Err(e) => {
+            log::debug!("Unable to read the mount table: {:#}", e);
+            true
+        }
+    }
+}
+
+/// Checks whether efivarfs is mounted read-only.
+pub(crate) fn is_read_only() -> bool {
+    match std::fs::read_to_string("/proc/self/mounts") {
+        Ok(mounts) => mount_read_only(&mounts).unwrap_or(false),
+        Err(e) => {
+            log::debug!("Unable to read the mount table: {:#}", e);
+            false
+        }
+    }
+}
+
+/// Fails with [ErrorKind::ReadOnly] if efivarfs is mounted read-only.
+pub(crate) fn ensure_writable() -> Result<()> {
+    if is_read_only() {
+        Err(anyhow::Error::new(ErrorKind::ReadOnly).context(format!(
+            "efivarfs is mounted read-only, remount it with `mount -o remount,rw {}`",
+            MOUNT_POINT
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the NVRAM usage. efivarfs reports the firmware storage limits as
+/// the file system size.
+pub(crate) fn usage() -> Result<NvramUsage> {
+    let stat = nix::sys::statvfs::statvfs(MOUNT_POINT)
+        .with_context(|| format!("Unable to query {}", MOUNT_POINT))?;
+    let dump_variables = std::fs::read_dir(MOUNT_POINT)
+        .with_context(|| format!("Unable to list {}", MOUNT_POINT))?
+        .filter_map(|item| item.ok())
+        .filter(|item| item.file_name().to_string_lossy().starts_with("dump-"))
+        .count();
+    #[allow(clippy::useless_conversion)] // The field types vary across targets.
+    let (total_bytes, free_bytes) = usage_bytes(
+        u64::from(stat.blocks()),
+        u64::from(stat.blocks_free()),
+        u64::from(stat.fragment_size()),
+    );
+    Ok(NvramUsage {
+        total_bytes,
+        free_bytes,
+        dump_variables,
+    })
+}
+
+/// Converts the block counts into the total and the free bytes. Both counts
+/// are in the fragment size units (`f_frsize`), not in `f_bsize`.
+fn usage_bytes(blocks: u64, blocks_free: u64, fragment_size: u64) -> (u64, u64) {
+    let total = blocks.saturating_mul(fragment_size);
+    let free = blocks_free.min(blocks).saturating_mul(fragment_size);
+    (total, free)
+}
+
+/// Attaches the NVRAM usage to the "no space left" errors.
+pub(crate) fn explain_no_space(error: anyhow::Error) -> anyhow::Error {
+    if ErrorKind::of(&error) != Some(ErrorKind::NoSpace) {
+        return error;
+    }
+    let usage = usage()
+        .map_err(|e| log::debug!("Unable to determine the NVRAM usage: {:#}", e))
+        .ok();
+    error.context(NvramFull { usage })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_mount_read_only() {
+        let mounts = "sysfs /sys sysfs rw,nosuid 0 0\n\
+                      efivarfs /sys/firmware/efi/efivars efivarfs ro,nosuid,nodev 0 0\n";
+        assert_eq!(mount_read_only(mounts), Some(true));
+
+        let remounted = format!(
+            "{}efivarfs /sys/firmware/efi/efivars efivarfs rw,nosuid,nodev 0 0\n",
+            mounts
+        );
+        assert_eq!(mount_read_only(&remounted), Some(false));
+
+        assert_eq!(mount_read_only("sysfs /sys sysfs ro 0 0\n"), None);
+    }
+
+    #[test]
+    fn check_usage_bytes() {
+        // 64 KiB of NVRAM in 1 KiB fragments, a quarter of it free.
+        assert_eq!(usage_bytes(64, 16, 1024), (64 * 1024, 16 * 1024));
+        assert_eq!(usage_bytes(64, 0, 1024), (64 * 1024, 0));
+        // The free space never exceeds the total.
+        assert_eq!(usage_bytes(64, 100, 1024), (64 * 1024, 64 * 1024));
+        assert_eq!(usage_bytes(u64::MAX, 1, 4096), (u64::MAX, 4096));
+    }
+}
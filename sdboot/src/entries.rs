@@ -0,0 +1,276 @@
+//! Boot loader entries collection.
+
+use crate::parse::fuzzy_match;
+
+/// Titles of the entries systemd-boot generates automatically.
+const PSEUDO_ENTRIES: &[(&str, &str)] = &[
+    ("auto-windows", "Windows Boot Manager"),
+    ("auto-osx", "macOS"),
+    ("auto-efi-shell", "EFI Shell"),
+    ("auto-efi-default", "EFI Default Loader"),
+    ("auto-poweroff", "Power Off The System"),
+    ("auto-reboot", "Reboot The System"),
+    (
+        "auto-reboot-to-firmware-setup",
+        "Reboot Into Firmware Interface",
+    ),
+];
+
+/// The outcome of [Entries::find].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lookup<'a> {
+    /// The entries matching the name, several if it is ambiguous.
+    Matches(Vec<&'a Entry>),
+    /// Nothing matches the name, these entries are close to it, possibly
+    /// none.
+    Suggestions(Vec<&'a Entry>),
+}
+
+/// A boot loader entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    id: String,
+    title: Option<String>,
+    version: Option<String>,
+}
+
+impl Entry {
+    /// Creates an entry with the given identifier.
+    ///
+    /// The title is known only for the automatically generated entries.
+    pub fn new(id: impl Into<String>) -> Self {
+        let id = id.into();
+        let title = PSEUDO_ENTRIES
+            .iter()
+            .find(|(pseudo_id, _)| *pseudo_id == id)
+            .map(|(_, title)| title.to_string());
+        Self {
+            id,
+            title,
+            version: None,
+        }
+    }
+
+    /// Sets the human-readable title of the entry.
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the version of the entry, e.g. the kernel version.
+    pub fn with_version(mut self, version: impl Into<String>) -> Self {
+        self.version = Some(version.into());
+        self
+    }
+
+    /// The entry identifier, as used in the oneshot/default variables.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The human-readable title of the entry, if known.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// The version of the entry, if known.
+    pub fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    /// The title if known, otherwise the identifier.
+    pub fn display_name(&self) -> &str {
+        self.title().unwrap_or(&self.id)
+    }
+}
+
+/// Boot loader entries in the boot menu order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entries(Vec<Entry>);
+
+impl Entries {
+    /// Looks up an entry by its identifier.
+    pub fn get_by_id(&self, id: &str) -> Option<&Entry> {
+        self.0.iter().find(|entry| entry.id == id)
+    }
+
+    /// Looks up an entry by its title.
+    pub fn get_by_title(&self, title: &str) -> Option<&Entry> {
+        self.0.iter().find(|entry| entry.title() == Some(title))
+    }
+
+    /// Returns the entry at the given (zero-based) position in the menu.
+    pub fn get_by_index(&self, index: usize) -> Option<&Entry> {
+        self.0.get(index)
+    }
+
+    /// Returns the position of the entry with the given identifier.
+    pub fn position(&self, id: &str) -> Option<usize> {
+        self.0.iter().position(|entry| entry.id == id)
+    }
+
+    /// Returns the entry `offset` positions away from the one with the given
+    /// identifier in the menu order, wrapping around at the ends. Negative
+    /// offsets go backwards.
+    pub fn relative_to(&self, id: &str, offset: isize) -> Option<&Entry> {
+        let position = self.position(id)? as isize;
+        let len = self.0.len() as isize;
+        self.0.get((position + offset).rem_euclid(len) as usize)
+    }
+
+    /// Finds the entries matching a possibly abbreviated or mistyped name.
+    ///
+    /// An exact identifier match wins. Otherwise the entries whose
+    /// identifier (with or without the ".conf" suffix) or title equals the
+    /// query ignoring the case match, and if there are none, the ones
+    /// containing it. More than one match means the query is ambiguous. If
+    /// nothing matches, the entries whose names contain the characters of the
+    /// query in order (see [fuzzy_match]) are suggested instead.
+    pub fn find(&self, query: &str) -> Lookup<'_> {
+        if let Some(entry) = self.get_by_id(query) {
+            return Lookup::Matches(vec![entry]);
+        }
+        let query = query.to_lowercase();
+        let names = |entry: &Entry| {
+            let id = entry.id.to_lowercase();
+            let stem = id.strip_suffix(".conf").map(str::to_string);
+            let title = entry.title().map(str::to_lowercase);
+            [Some(id), stem, title].into_iter().flatten()
+        };
+        let equal = self
+            .iter()
+            .filter(|entry| names(entry).any(|name| name == query))
+            .collect::<Vec<_>>();
+        if !equal.is_empty() {
+            return Lookup::Matches(equal);
+        }
+        let containing = self
+            .iter()
+            .filter(|entry| names(entry).any(|name| name.contains(&query)))
+            .collect::<Vec<_>>();
+        if !containing.is_empty() {
+            return Lookup::Matches(containing);
+        }
+        Lookup::Suggestions(
+            self.iter()
+                .filter(|entry| names(entry).any(|name| fuzzy_match(&query, &name)))
+                .collect(),
+        )
+    }
+
+    /// Checks whether an entry with the given identifier is present.
+    pub fn contains(&self, id: &str) -> bool {
+        self.get_by_id(id).is_some()
+    }
+
+    /// Iterates over the entries in the menu order.
+    pub fn iter(&self) -> std::slice::Iter<'_, Entry> {
+        self.0.iter()
+    }
+
+    /// Iterates over the entries identifiers in the menu order.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(Entry::id)
+    }
+
+    /// Number of the entries.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Checks whether there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl FromIterator<Entry> for Entries {
+    fn from_iter<T: IntoIterator<Item = Entry>>(iter: T) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for Entries {
+    type Item = Entry;
+    type IntoIter = std::vec::IntoIter<Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Entries {
+    type Item = &'a Entry;
+    type IntoIter = std::slice::Iter<'a, Entry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_lookups() {
+        let entries: Entries = ["arch.conf", "auto-windows"]
+            .into_iter()
+            .map(Entry::new)
+            .collect();
+        assert!(entries.contains("arch.conf"));
+        assert!(!entries.contains("arch"));
+        assert_eq!(entries.get_by_index(1).map(Entry::id), Some("auto-windows"));
+        assert_eq!(
+            entries.get_by_title("Windows Boot Manager").map(Entry::id),
+            Some("auto-windows")
+        );
+        assert_eq!(entries.position("arch.conf"), Some(0));
+        assert_eq!(entries.get_by_id("arch.conf").and_then(Entry::title), None);
+        assert_eq!(
+            entries.relative_to("arch.conf", 1).map(Entry::id),
+            Some("auto-windows")
+        );
+        assert_eq!(
+            entries.relative_to("arch.conf", -1).map(Entry::id),
+            Some("auto-windows")
+        );
+        assert_eq!(
+            entries.relative_to("auto-windows", 1).map(Entry::id),
+            Some("arch.conf")
+        );
+        assert_eq!(entries.relative_to("arch", 1), None);
+    }
+
+    #[test]
+    fn check_find() {
+        let entries: Entries = [
+            Entry::new("arch.conf"),
+            Entry::new("arch-lts.conf"),
+            Entry::new("auto-windows"),
+            Entry::new("fedora.conf").with_title("Fedora Linux"),
+        ]
+        .into_iter()
+        .collect();
+        let find = |query| match entries.find(query) {
+            Lookup::Matches(matches) => matches.into_iter().map(Entry::id).collect::<Vec<_>>(),
+            Lookup::Suggestions(_) => Vec::new(),
+        };
+        let suggest = |query| match entries.find(query) {
+            Lookup::Matches(_) => Vec::new(),
+            Lookup::Suggestions(suggestions) => {
+                suggestions.into_iter().map(Entry::id).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(find("arch.conf"), ["arch.conf"]);
+        assert_eq!(find("arch"), ["arch.conf"]);
+        assert_eq!(find("wind"), ["auto-windows"]);
+        assert_eq!(find("Windows boot manager"), ["auto-windows"]);
+        assert_eq!(find("linux"), ["fedora.conf"]);
+        assert_eq!(find("ar"), ["arch.conf", "arch-lts.conf"]);
+        assert!(find("debian").is_empty());
+        assert!(suggest("debian").is_empty());
+        assert_eq!(suggest("fdr"), ["fedora.conf"]);
+        assert_eq!(suggest("archlts"), ["arch-lts.conf"]);
+    }
+}
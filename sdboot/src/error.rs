@@ -1,9 +1,177 @@
 use std::fmt;
 
+/// Typed failure reasons attached to the errors returned by the library.
+///
+/// Use [ErrorKind::of] to find out the reason of a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The requested entry is not among the boot loader entries.
+    EntryNotFound,
+    /// The process lacks privileges to perform the operation.
+    PermissionDenied,
+    /// The system is not a UEFI system booted with systemd-boot.
+    NotSystemdBoot,
+    /// The requested variable is not set.
+    VariableNotSet,
+    /// The installed boot loader does not support oneshot entries.
+    OneshotNotSupported,
+    /// The EFI variables storage is read-only, e.g. efivarfs is mounted with
+    /// the `ro` option.
+    ReadOnly,
+    /// There is no space left in the NVRAM to store the variable.
+    NoSpace,
+}
+
+impl ErrorKind {
+    /// Looks for an [ErrorKind] in the error and its causes.
+    pub fn of(error: &anyhow::Error) -> Option<ErrorKind> {
+        error.downcast_ref::<ErrorKind>().copied().or_else(|| {
+            error.chain().find_map(|cause| {
+                if let Some(kind) = cause.downcast_ref::<ErrorKind>() {
+                    Some(*kind)
+                } else if let Some(RebootIntoError::EntryNotFound(_)) =
+                    cause.downcast_ref::<RebootIntoError>()
+                {
+                    Some(ErrorKind::EntryNotFound)
+                } else if cause.is::<crate::MissingPrivilege>() {
+                    Some(ErrorKind::PermissionDenied)
+                } else {
+                    None
+                }
+            })
+        })
+    }
+
+    /// Returns a stable numeric code of the error kind.
+    ///
+    /// The codes never change between releases, so they can be relied upon
+    /// by FFI bindings and scripts.
+    pub const fn code(self) -> i32 {
+        match self {
+            ErrorKind::EntryNotFound => 2,
+            ErrorKind::PermissionDenied => 3,
+            ErrorKind::NotSystemdBoot => 4,
+            ErrorKind::VariableNotSet => 5,
+            ErrorKind::OneshotNotSupported => 6,
+            ErrorKind::ReadOnly => 7,
+            ErrorKind::NoSpace => 8,
+        }
+    }
+
+    /// Looks up the error kind by its [code](ErrorKind::code).
+    pub const fn from_code(code: i32) -> Option<ErrorKind> {
+        match code {
+            2 => Some(ErrorKind::EntryNotFound),
+            3 => Some(ErrorKind::PermissionDenied),
+            4 => Some(ErrorKind::NotSystemdBoot),
+            5 => Some(ErrorKind::VariableNotSet),
+            6 => Some(ErrorKind::OneshotNotSupported),
+            7 => Some(ErrorKind::ReadOnly),
+            8 => Some(ErrorKind::NoSpace),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ErrorKind::EntryNotFound => "entry not found",
+            ErrorKind::PermissionDenied => "permission denied",
+            ErrorKind::NotSystemdBoot => "not booted with systemd-boot",
+            ErrorKind::VariableNotSet => "variable is not set",
+            ErrorKind::OneshotNotSupported => "loader does not support oneshot entries",
+            ErrorKind::ReadOnly => "EFI variables are read-only",
+            ErrorKind::NoSpace => "no space left in NVRAM",
+        })
+    }
+}
+
+impl std::error::Error for ErrorKind {}
+
+/// NVRAM usage, as reported by the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NvramUsage {
+    /// The space available for the variables, in bytes.
+    pub total_bytes: u64,
+    /// The remaining space, in bytes.
+    pub free_bytes: u64,
+    /// Number of the crash dump variables (`dump-*`) stored by the kernel.
+    pub dump_variables: usize,
+}
+
+/// A variable couldn't be written because the NVRAM is full.
+///
+/// Attached to the errors of kind [ErrorKind::NoSpace].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NvramFull {
+    /// The NVRAM usage, if it could be determined.
+    pub usage: Option<NvramUsage>,
+}
+
+impl fmt::Display for NvramFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("The NVRAM is full")?;
+        let Some(usage) = self.usage else {
+            return f.write_str(", consider removing unneeded EFI variables");
+        };
+        write!(
+            f,
+            " ({} of {} bytes free)",
+            usage.free_bytes, usage.total_bytes
+        )?;
+        if usage.dump_variables > 0 {
+            write!(
+                f,
+                "; {} crash dump variables (dump-*) left by the kernel take space, \
+                 consider removing them",
+                usage.dump_variables
+            )
+        } else {
+            f.write_str(", consider removing unneeded EFI variables, e.g. stale boot entries")
+        }
+    }
+}
+
+impl std::error::Error for NvramFull {}
+
 /// A thin wrapper around [efivar::Error] to provide [std::error::Error]
 /// implementation.
 pub struct EfiError(pub efivar::Error);
 
+impl EfiError {
+    /// Converts the error into an [anyhow::Error], attaching the respective
+    /// [ErrorKind] when it can be identified.
+    pub(crate) fn into_anyhow(self) -> anyhow::Error {
+        let kind = self.kind();
+        let error = anyhow::Error::new(self);
+        match kind {
+            Some(kind) => error.context(kind),
+            None => error,
+        }
+    }
+
+    fn kind(&self) -> Option<ErrorKind> {
+        use efivar::Error;
+        match &self.0 {
+            Error::PermissionDenied { .. } => Some(ErrorKind::PermissionDenied),
+            Error::VarUnknownError { name: _, error } | Error::UnknownIoError { error }
+                if error.kind() == std::io::ErrorKind::PermissionDenied =>
+            {
+                Some(ErrorKind::PermissionDenied)
+            }
+            #[cfg(target_os = "linux")]
+            Error::VarUnknownError { name: _, error } | Error::UnknownIoError { error }
+                if error.raw_os_error() == Some(libc::ENOSPC) =>
+            {
+                Some(ErrorKind::NoSpace)
+            }
+            _ => None,
+        }
+    }
+}
+
 impl fmt::Debug for EfiError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Debug::fmt(&self.0, f)
@@ -34,3 +202,61 @@ impl std::error::Error for EfiError {
         }
     }
 }
+
+/// An error returned by [Manager::reboot_into](crate::Manager::reboot_into),
+/// identifying the stage that failed.
+#[derive(Debug)]
+pub enum RebootIntoError {
+    /// Unable to fetch the available entries.
+    Entries(anyhow::Error),
+    /// The requested entry is not among the available entries.
+    EntryNotFound(String),
+    /// Unable to set the oneshot entry.
+    SetOneshot(anyhow::Error),
+    /// The oneshot entry has been set, but the reboot failed.
+    Reboot(anyhow::Error),
+}
+
+impl fmt::Display for RebootIntoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RebootIntoError::Entries(_) => f.write_str("Unable to fetch entries"),
+            RebootIntoError::EntryNotFound(entry) => write!(f, "Entry {:?} not found", entry),
+            RebootIntoError::SetOneshot(_) => f.write_str("Unable to set the oneshot entry"),
+            RebootIntoError::Reboot(_) => {
+                f.write_str("Oneshot entry has been set, but unable to reboot")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RebootIntoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RebootIntoError::Entries(error)
+            | RebootIntoError::SetOneshot(error)
+            | RebootIntoError::Reboot(error) => Some(error.as_ref()),
+            RebootIntoError::EntryNotFound(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn check_error_kind_lookup() {
+        let error = Err::<(), _>(ErrorKind::OneshotNotSupported)
+            .context("Setting oneshot")
+            .context("Rebooting")
+            .unwrap_err();
+        assert_eq!(ErrorKind::of(&error), Some(ErrorKind::OneshotNotSupported));
+
+        let error = anyhow::Error::new(RebootIntoError::EntryNotFound("arch".into()));
+        assert_eq!(ErrorKind::of(&error), Some(ErrorKind::EntryNotFound));
+
+        assert_eq!(ErrorKind::of(&anyhow::anyhow!("Unknown")), None);
+    }
+}
@@ -0,0 +1,541 @@
+//! Boot loader entries stored on the EFI system partition (ESP).
+//!
+//! systemd-boot discovers Type #1 entries (`loader/entries/*.conf`) and Type
+//! #2 entries (unified kernel images, `EFI/Linux/*.efi`) on the ESP and the
+//! XBOOTLDR partition.
+
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{parse, parse::BootCounter, Entries};
+
+/// Usual mount points of the ESP and the XBOOTLDR partition.
+#[cfg(target_os = "linux")]
+const MOUNT_POINTS: &[&str] = &["/efi", "/boot", "/boot/efi"];
+
+/// The ESP is not mounted by default on other systems.
+#[cfg(not(target_os = "linux"))]
+const MOUNT_POINTS: &[&str] = &[];
+
+/// The boot loader configuration file, relative to the partition root.
+const LOADER_CONF: &str = "loader/loader.conf";
+
+/// Directory with Type #1 entries, relative to the partition root.
+const TYPE1_DIRECTORY: &str = "loader/entries";
+
+/// Directory with Type #2 entries, relative to the partition root.
+const TYPE2_DIRECTORY: &str = "EFI/Linux";
+
+/// The largest metadata section of a unified kernel image read.
+const MAX_SECTION_SIZE: u32 = 64 * 1024;
+
+/// An entry discovered on the disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EspEntry {
+    /// The entry identifier, as the boot loader reports it.
+    pub id: String,
+    /// Path to the entry file.
+    pub path: PathBuf,
+    /// The `title` field of a Type #1 entry, or the name of the OS embedded
+    /// in a unified kernel image.
+    pub title: Option<String>,
+    /// The `version` field of a Type #1 entry, or the kernel version embedded
+    /// in a unified kernel image.
+    pub version: Option<String>,
+    /// The `machine-id` field of a Type #1 entry.
+    pub machine_id: Option<String>,
+    /// The `sort-key` field of a Type #1 entry.
+    pub sort_key: Option<String>,
+    /// The boot counter, if the entry uses boot counting.
+    pub boot_counter: Option<BootCounter>,
+}
+
+impl EspEntry {
+    /// Parses a Type #1 entry file.
+    fn type1(path: PathBuf, id: String, boot_counter: Option<BootCounter>) -> Result<Self> {
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        let mut entry = Self {
+            id,
+            path,
+            title: None,
+            version: None,
+            machine_id: None,
+            sort_key: None,
+            boot_counter,
+        };
+        for (key, value) in parse_conf(&contents) {
+            let field = match key {
+                "title" => &mut entry.title,
+                "version" => &mut entry.version,
+                "machine-id" => &mut entry.machine_id,
+                "sort-key" => &mut entry.sort_key,
+                _ => continue,
+            };
+            *field = Some(value.to_string());
+        }
+        Ok(entry)
+    }
+
+    /// Reads the metadata of a Type #2 entry (unified kernel image) like the
+    /// boot loader does: the title from the `.osrel` section and the version
+    /// from the `.uname` one. Unreadable metadata is logged and left unknown.
+    fn type2(path: PathBuf, id: String, boot_counter: Option<BootCounter>) -> Self {
+        let mut entry = Self {
+            id,
+            path,
+            title: None,
+            version: None,
+            machine_id: None,
+            sort_key: None,
+            boot_counter,
+        };
+        let sections = File::open(&entry.path)
+            .with_context(|| format!("Unable to open {}", entry.path.display()))
+            .and_then(|mut image| pe_sections(&mut image, &[".osrel", ".uname"]));
+        let (os_release, uname) = match sections.as_deref() {
+            Ok([os_release, uname]) => (os_release.as_deref(), uname.as_deref()),
+            Ok(_) => (None, None),
+            Err(e) => {
+                log::debug!("No metadata for {}: {:#}", entry.path.display(), e);
+                (None, None)
+            }
+        };
+        let os_release = os_release
+            .map(|os_release| parse_os_release(&String::from_utf8_lossy(os_release)))
+            .unwrap_or_default();
+        let field = |keys: &[&str]| {
+            keys.iter().find_map(|key| {
+                os_release
+                    .iter()
+                    .find(|(name, value)| name == key && !value.is_empty())
+                    .map(|(_, value)| value.clone())
+            })
+        };
+        entry.title = field(&["PRETTY_NAME", "NAME", "ID"]);
+        entry.version = uname
+            .map(|uname| {
+                String::from_utf8_lossy(uname)
+                    .trim_end_matches('\0')
+                    .trim()
+                    .to_string()
+            })
+            .filter(|uname| !uname.is_empty())
+            .or_else(|| field(&["IMAGE_VERSION", "VERSION_ID", "BUILD_ID"]));
+        entry
+    }
+
+    /// Marks the entry as good by dropping the boot counter from its file
+    /// name, like `systemd-bless-boot` does, so the boot loader stops counting
+    /// the boot attempts.
+    ///
+    /// Returns the new path, or [None] if the entry doesn't use boot counting.
+    pub fn bless(&self) -> Result<Option<PathBuf>> {
+        if self.boot_counter.is_none() {
+            return Ok(None);
+        }
+        let blessed = self.path.with_file_name(&self.id);
+        std::fs::rename(&self.path, &blessed).with_context(|| {
+            format!(
+                "Unable to rename {} to {}",
+                self.path.display(),
+                blessed.display()
+            )
+        })?;
+        Ok(Some(blessed))
+    }
+}
+
+/// Splits a `key value` configuration file (like `loader.conf` or a Type #1
+/// entry) into the key-value pairs, skipping comments and empty lines.
+pub fn parse_conf(contents: &str) -> impl Iterator<Item = (&str, &str)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| match line.split_once(char::is_whitespace) {
+            Some((key, value)) => (key, value.trim()),
+            None => (line, ""),
+        })
+}
+
+/// Partitions that may contain boot loader entries.
+#[derive(Debug, Clone)]
+pub struct Esp {
+    roots: Vec<PathBuf>,
+}
+
+impl Esp {
+    /// Uses the given partition roots (mount points).
+    pub fn new(roots: impl IntoIterator<Item = PathBuf>) -> Self {
+        Self {
+            roots: roots.into_iter().collect(),
+        }
+    }
+
+    /// Looks for the mounted partitions with boot loader entries at the usual
+    /// mount points.
+    pub fn discover() -> Option<Self> {
+        let roots = MOUNT_POINTS
+            .iter()
+            .map(PathBuf::from)
+            .filter(|root| {
+                root.join(TYPE1_DIRECTORY).is_dir() || root.join(TYPE2_DIRECTORY).is_dir()
+            })
+            .collect::<Vec<_>>();
+        if roots.is_empty() {
+            None
+        } else {
+            Some(Self { roots })
+        }
+    }
+
+    /// Returns the partition roots.
+    pub fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    /// Returns the `default` entry pattern configured in `loader.conf`.
+    pub fn loader_default(&self) -> Result<Option<String>> {
+        for root in &self.roots {
+            let path = root.join(LOADER_CONF);
+            let contents = match std::fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Unable to read {}", path.display()))
+                }
+            };
+            return Ok(parse_conf(&contents)
+                .filter(|(key, _)| *key == "default")
+                .last()
+                .map(|(_, value)| value.to_string()));
+        }
+        Ok(None)
+    }
+
+    /// Lists the entries stored on the partitions.
+    pub fn entries(&self) -> Result<Vec<EspEntry>> {
+        let mut entries = Vec::new();
+        for root in &self.roots {
+            for (directory, extension) in [(TYPE1_DIRECTORY, "conf"), (TYPE2_DIRECTORY, "efi")] {
+                for path in list_files(&root.join(directory), extension)? {
+                    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+                        log::warn!("Skipping a non-UTF8 entry file {}", path.display());
+                        continue;
+                    };
+                    let (id, boot_counter) = parse::split_boot_counter(file_name);
+                    if extension == "conf" {
+                        match EspEntry::type1(path.clone(), id, boot_counter) {
+                            Ok(entry) => entries.push(entry),
+                            Err(e) => log::warn!("Skipping entry {}: {:#}", path.display(), e),
+                        }
+                    } else {
+                        entries.push(EspEntry::type2(path, id, boot_counter));
+                    }
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Adds the titles and versions of the entries on the disk to the entries
+/// reported by the boot loader.
+pub fn describe(entries: Entries, on_disk: &[EspEntry]) -> Entries {
+    entries
+        .into_iter()
+        .map(|entry| {
+            let Some(esp_entry) = on_disk.iter().find(|esp_entry| esp_entry.id == entry.id())
+            else {
+                return entry;
+            };
+            let entry = match &esp_entry.title {
+                Some(title) => entry.with_title(title),
+                None => entry,
+            };
+            match &esp_entry.version {
+                Some(version) => entry.with_version(version),
+                None => entry,
+            }
+        })
+        .collect()
+}
+
+/// Parses an os-release file into the key-value pairs, unquoting the values.
+fn parse_os_release(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = ['"', '\'']
+                .into_iter()
+                .find_map(|quote| {
+                    value
+                        .strip_prefix(quote)
+                        .and_then(|value| value.strip_suffix(quote))
+                })
+                .unwrap_or(value);
+            (key.trim().to_string(), value.replace("\\\"", "\""))
+        })
+        .collect()
+}
+
+/// Reads the named sections of a PE image, e.g. the `.osrel` section of a
+/// unified kernel image. The missing sections are [None].
+fn pe_sections(image: &mut (impl Read + Seek), names: &[&str]) -> Result<Vec<Option<Vec<u8>>>> {
+    let u16_at =
+        |bytes: &[u8], offset: usize| u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+    let u32_at = |bytes: &[u8], offset: usize| {
+        u32::from_le_bytes([
+            bytes[offset],
+            bytes[offset + 1],
+            bytes[offset + 2],
+            bytes[offset + 3],
+        ])
+    };
+
+    let mut dos_header = [0u8; 64];
+    image
+        .read_exact(&mut dos_header)
+        .context("Truncated DOS header")?;
+    anyhow::ensure!(dos_header.starts_with(b"MZ"), "Not a PE image");
+    image.seek(SeekFrom::Start(u32_at(&dos_header, 0x3c).into()))?;
+
+    // The signature followed by the COFF header.
+    let mut pe_header = [0u8; 24];
+    image
+        .read_exact(&mut pe_header)
+        .context("Truncated PE header")?;
+    anyhow::ensure!(pe_header.starts_with(b"PE\0\0"), "Not a PE image");
+    let section_count = u16_at(&pe_header, 6);
+    let optional_header_size = u16_at(&pe_header, 20);
+    image.seek(SeekFrom::Current(optional_header_size.into()))?;
+
+    let mut section_table = vec![0u8; usize::from(section_count) * 40];
+    image
+        .read_exact(&mut section_table)
+        .context("Truncated section table")?;
+    let mut sections = vec![None; names.len()];
+    for header in section_table.chunks_exact(40) {
+        let name = header[..8]
+            .split(|&byte| byte == 0)
+            .next()
+            .unwrap_or_default();
+        let Some(index) = names.iter().position(|wanted| wanted.as_bytes() == name) else {
+            continue;
+        };
+        let (virtual_size, raw_size, offset) =
+            (u32_at(header, 8), u32_at(header, 16), u32_at(header, 20));
+        // The raw data is padded to the file alignment.
+        let size = match virtual_size {
+            0 => raw_size,
+            virtual_size => virtual_size.min(raw_size),
+        };
+        anyhow::ensure!(
+            size <= MAX_SECTION_SIZE,
+            "Section {} is too large ({} bytes)",
+            names[index],
+            size
+        );
+        let mut contents = vec![0; size as usize];
+        image.seek(SeekFrom::Start(offset.into()))?;
+        image
+            .read_exact(&mut contents)
+            .with_context(|| format!("Truncated section {}", names[index]))?;
+        sections[index] = Some(contents);
+    }
+    Ok(sections)
+}
+
+/// Lists the files with the given extension (case-insensitive) in the
+/// directory. A missing directory contains no files.
+fn list_files(directory: &Path, extension: &str) -> Result<Vec<PathBuf>> {
+    let read_dir = match std::fs::read_dir(directory) {
+        Ok(read_dir) => read_dir,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Unable to list {}", directory.display())),
+    };
+    let mut files = Vec::new();
+    for item in read_dir {
+        let path = item
+            .with_context(|| format!("Unable to list {}", directory.display()))?
+            .path();
+        let matches = path
+            .extension()
+            .and_then(|actual| actual.to_str())
+            .map_or(false, |actual| actual.eq_ignore_ascii_case(extension));
+        if matches && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Entries known only to one side: the boot loader or the disk.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Divergence {
+    /// Entries reported by the boot loader but missing on the disk, e.g.
+    /// removed since the last boot.
+    pub only_in_loader: Vec<String>,
+    /// Entries present on the disk but unknown to the boot loader, e.g.
+    /// installed since the last boot.
+    pub only_on_disk: Vec<String>,
+}
+
+impl Divergence {
+    /// Compares the entries reported by the boot loader with the ones on the
+    /// disk. Automatically generated entries (`auto-*`) are ignored.
+    pub fn new(loader: &Entries, on_disk: &[EspEntry]) -> Self {
+        let only_in_loader = loader
+            .ids()
+            .filter(|id| !id.starts_with("auto-"))
+            .filter(|id| !on_disk.iter().any(|entry| entry.id == *id))
+            .map(str::to_string)
+            .collect();
+        let only_on_disk = on_disk
+            .iter()
+            .filter(|entry| !loader.contains(&entry.id))
+            .map(|entry| entry.id.clone())
+            .collect();
+        Self {
+            only_in_loader,
+            only_on_disk,
+        }
+    }
+
+    /// Checks whether both sides agree.
+    pub fn is_empty(&self) -> bool {
+        self.only_in_loader.is_empty() && self.only_on_disk.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Entry;
+
+    fn on_disk(id: &str) -> EspEntry {
+        EspEntry {
+            id: id.to_string(),
+            path: PathBuf::from(id),
+            title: None,
+            version: None,
+            machine_id: None,
+            sort_key: None,
+            boot_counter: None,
+        }
+    }
+
+    #[test]
+    fn check_parse_conf() {
+        let conf = "# comment\ntitle  Arch Linux \n\nversion 6.8.9\noptions\n";
+        assert_eq!(
+            parse_conf(conf).collect::<Vec<_>>(),
+            [
+                ("title", "Arch Linux"),
+                ("version", "6.8.9"),
+                ("options", "")
+            ]
+        );
+    }
+
+    #[test]
+    fn check_divergence() {
+        let loader: Entries = ["arch.conf", "old.conf", "auto-windows"]
+            .into_iter()
+            .map(Entry::new)
+            .collect();
+        let divergence = Divergence::new(&loader, &[on_disk("arch.conf"), on_disk("new.efi")]);
+        assert_eq!(divergence.only_in_loader, ["old.conf"]);
+        assert_eq!(divergence.only_on_disk, ["new.efi"]);
+        assert!(!divergence.is_empty());
+    }
+
+    /// Builds a PE image with the given sections and no optional header.
+    fn pe_image(sections: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut image = vec![0u8; 64];
+        image[..2].copy_from_slice(b"MZ");
+        image[0x3c] = 64;
+        image.extend_from_slice(b"PE\0\0");
+        let mut coff_header = [0u8; 20];
+        coff_header[2] = sections.len() as u8;
+        image.extend_from_slice(&coff_header);
+        let mut offset = image.len() + sections.len() * 40;
+        for (name, contents) in sections {
+            let mut header = [0u8; 40];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            // Padded to 16 bytes.
+            let raw_size = contents.len().next_multiple_of(16);
+            header[8..12].copy_from_slice(&(contents.len() as u32).to_le_bytes());
+            header[16..20].copy_from_slice(&(raw_size as u32).to_le_bytes());
+            header[20..24].copy_from_slice(&(offset as u32).to_le_bytes());
+            image.extend_from_slice(&header);
+            offset += raw_size;
+        }
+        for (_, contents) in sections {
+            image.extend_from_slice(contents);
+            image.resize(
+                image.len() + contents.len().next_multiple_of(16) - contents.len(),
+                0,
+            );
+        }
+        image
+    }
+
+    #[test]
+    fn check_pe_sections() {
+        let image = pe_image(&[
+            (".osrel", b"NAME=Arch\nPRETTY_NAME=\"Arch Linux\"\n"),
+            (".uname", b"6.8.9-arch1-1"),
+        ]);
+        let sections = pe_sections(
+            &mut std::io::Cursor::new(image),
+            &[".uname", ".osrel", ".cmdline"],
+        )
+        .unwrap();
+        assert_eq!(
+            sections,
+            [
+                Some(b"6.8.9-arch1-1".to_vec()),
+                Some(b"NAME=Arch\nPRETTY_NAME=\"Arch Linux\"\n".to_vec()),
+                None
+            ]
+        );
+        assert!(pe_sections(&mut std::io::Cursor::new(b"#!/bin/sh\n"), &[".osrel"]).is_err());
+
+        assert_eq!(
+            parse_os_release("# comment\nID=arch\nPRETTY_NAME='Arch \\\"rolling\\\"'\n"),
+            [
+                ("ID".to_string(), "arch".to_string()),
+                ("PRETTY_NAME".to_string(), "Arch \"rolling\"".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn check_bless() {
+        let directory = std::env::temp_dir().join(format!("sdboot-bless-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("arch+2-1.conf");
+        std::fs::write(&path, "title Arch Linux\n").unwrap();
+
+        let (id, boot_counter) = parse::split_boot_counter("arch+2-1.conf");
+        let entry = EspEntry::type1(path.clone(), id, boot_counter).unwrap();
+        assert_eq!(entry.bless().unwrap(), Some(directory.join("arch.conf")));
+        assert!(!path.exists());
+        assert!(directory.join("arch.conf").exists());
+
+        assert_eq!(on_disk("arch.conf").bless().unwrap(), None);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
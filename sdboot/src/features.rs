@@ -0,0 +1,166 @@
+//! Boot loader features reported via the `LoaderFeatures` EFI variable, and
+//! the UKI stub ones reported via `StubFeatures`.
+
+use std::fmt;
+
+/// A set of features supported by the boot loader.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct LoaderFeatures(u64);
+
+/// cbindgen:ignore
+impl LoaderFeatures {
+    /// `LoaderConfigTimeout` is honoured.
+    pub const CONFIG_TIMEOUT: Self = Self(1 << 0);
+    /// `LoaderConfigTimeoutOneShot` is honoured.
+    pub const CONFIG_TIMEOUT_ONESHOT: Self = Self(1 << 1);
+    /// `LoaderEntryDefault` is honoured.
+    pub const ENTRY_DEFAULT: Self = Self(1 << 2);
+    /// `LoaderEntryOneShot` is honoured.
+    pub const ENTRY_ONESHOT: Self = Self(1 << 3);
+    /// Boot counting is supported.
+    pub const BOOT_COUNTING: Self = Self(1 << 4);
+    /// The XBOOTLDR partition is supported.
+    pub const XBOOTLDR: Self = Self(1 << 5);
+    /// The random seed is passed to the OS.
+    pub const RANDOM_SEED: Self = Self(1 << 6);
+    /// EFI drivers are loaded from the ESP.
+    pub const LOAD_DRIVER: Self = Self(1 << 7);
+    /// Entries are sorted by their `sort-key`.
+    pub const SORT_KEY: Self = Self(1 << 8);
+    /// The `@saved` default entry pseudo-identifier is supported.
+    pub const SAVED_ENTRY: Self = Self(1 << 9);
+    /// Devicetree blobs are supported.
+    pub const DEVICETREE: Self = Self(1 << 10);
+    /// Secure Boot keys can be enrolled.
+    pub const SECUREBOOT_ENROLL: Self = Self(1 << 11);
+    /// The shim protocol is retained for the OS.
+    pub const RETAIN_SHIM: Self = Self(1 << 12);
+    /// The menu can be disabled entirely (`menu-disabled` timeout).
+    pub const MENU_DISABLE: Self = Self(1 << 13);
+    /// Multi-profile UKIs are supported.
+    pub const MULTI_PROFILE_UKI: Self = Self(1 << 14);
+
+    /// Human-readable names of the known features.
+    pub const NAMES: &'static [(Self, &'static str)] = &[
+        (Self::CONFIG_TIMEOUT, "config-timeout"),
+        (Self::CONFIG_TIMEOUT_ONESHOT, "config-timeout-oneshot"),
+        (Self::ENTRY_DEFAULT, "entry-default"),
+        (Self::ENTRY_ONESHOT, "entry-oneshot"),
+        (Self::BOOT_COUNTING, "boot-counting"),
+        (Self::XBOOTLDR, "xbootldr"),
+        (Self::RANDOM_SEED, "random-seed"),
+        (Self::LOAD_DRIVER, "load-driver"),
+        (Self::SORT_KEY, "sort-key"),
+        (Self::SAVED_ENTRY, "saved-entry"),
+        (Self::DEVICETREE, "devicetree"),
+        (Self::SECUREBOOT_ENROLL, "secureboot-enroll"),
+        (Self::RETAIN_SHIM, "retain-shim"),
+        (Self::MENU_DISABLE, "menu-disable"),
+        (Self::MULTI_PROFILE_UKI, "multi-profile-uki"),
+    ];
+
+    /// Creates a feature set from the raw bits.
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw bits.
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Checks whether all the `other` features are present.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns names of the known features present in the set.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .iter()
+            .filter(move |(feature, _)| self.contains(*feature))
+            .map(|(_, name)| *name)
+    }
+}
+
+impl fmt::Debug for LoaderFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "LoaderFeatures({:#x}: ", self.0)?;
+        f.debug_list().entries(self.names()).finish()?;
+        f.write_str(")")
+    }
+}
+
+/// A set of features supported by the systemd-stub of the booted unified
+/// kernel image.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct StubFeatures(u64);
+
+/// cbindgen:ignore
+impl StubFeatures {
+    /// The partition the UKI was loaded from is reported.
+    pub const REPORT_BOOT_PARTITION: Self = Self(1 << 0);
+    /// Credentials are picked up from the ESP.
+    pub const PICK_UP_CREDENTIALS: Self = Self(1 << 1);
+    /// System extensions are picked up from the ESP.
+    pub const PICK_UP_SYSEXTS: Self = Self(1 << 2);
+    /// The measurements are split over three PCRs.
+    pub const THREE_PCRS: Self = Self(1 << 3);
+    /// The random seed is passed to the kernel.
+    pub const RANDOM_SEED: Self = Self(1 << 4);
+    /// Command line addons are supported.
+    pub const CMDLINE_ADDONS: Self = Self(1 << 5);
+    /// The command line can be extended via SMBIOS.
+    pub const CMDLINE_SMBIOS: Self = Self(1 << 6);
+    /// Devicetree addons are supported.
+    pub const DEVICETREE_ADDONS: Self = Self(1 << 7);
+    /// Configuration extensions are picked up from the ESP.
+    pub const PICK_UP_CONFEXTS: Self = Self(1 << 8);
+    /// Multi-profile UKIs are supported.
+    pub const MULTI_PROFILE_UKI: Self = Self(1 << 9);
+
+    /// Human-readable names of the known features.
+    pub const NAMES: &'static [(Self, &'static str)] = &[
+        (Self::REPORT_BOOT_PARTITION, "report-boot-partition"),
+        (Self::PICK_UP_CREDENTIALS, "pick-up-credentials"),
+        (Self::PICK_UP_SYSEXTS, "pick-up-sysexts"),
+        (Self::THREE_PCRS, "three-pcrs"),
+        (Self::RANDOM_SEED, "random-seed"),
+        (Self::CMDLINE_ADDONS, "cmdline-addons"),
+        (Self::CMDLINE_SMBIOS, "cmdline-smbios"),
+        (Self::DEVICETREE_ADDONS, "devicetree-addons"),
+        (Self::PICK_UP_CONFEXTS, "pick-up-confexts"),
+        (Self::MULTI_PROFILE_UKI, "multi-profile-uki"),
+    ];
+
+    /// Creates a feature set from the raw bits.
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// Returns the raw bits.
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// Checks whether all the `other` features are present.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns names of the known features present in the set.
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        Self::NAMES
+            .iter()
+            .filter(move |(feature, _)| self.contains(*feature))
+            .map(|(_, name)| *name)
+    }
+}
+
+impl fmt::Debug for StubFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StubFeatures({:#x}: ", self.0)?;
+        f.debug_list().entries(self.names()).finish()?;
+        f.write_str(")")
+    }
+}
@@ -0,0 +1,335 @@
+//! GRUB environment block (`grubenv`) backend.
+//!
+//! GRUB doesn't use EFI variables to pick the entry to boot; instead it reads
+//! the `next_entry` (oneshot) and `saved_entry` (default) keys from a fixed
+//! size environment block stored next to its configuration.
+//!
+//! [crate::Backend] falls back to it on the systems not booted with
+//! systemd-boot.
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{Entries, Entry};
+
+/// Size of the environment block. GRUB refuses to load blocks of other sizes.
+const BLOCK_SIZE: usize = 1024;
+
+/// The mandatory first line of the environment block.
+const SIGNATURE: &str = "# GRUB Environment Block\n";
+
+/// The key GRUB reads the oneshot entry from. GRUB clears it on boot.
+const NEXT_ENTRY: &str = "next_entry";
+
+/// The key GRUB reads the default entry from (when `GRUB_DEFAULT=saved`).
+const SAVED_ENTRY: &str = "saved_entry";
+
+/// The configuration holding the menu, next to the environment block.
+const CONFIG: &str = "grub.cfg";
+
+/// Separates the titles of the submenus and their entries in the entry
+/// paths, e.g. "Advanced options>Ubuntu, with Linux 6.5.0-14-generic".
+const SUBMENU_SEPARATOR: char = '>';
+
+/// Well-known locations of the environment block.
+const GRUBENV_PATHS: &[&str] = &[
+    "/boot/grub/grubenv",
+    "/boot/grub2/grubenv",
+    "/boot/efi/EFI/grub/grubenv",
+];
+
+/// A GRUB environment block manager.
+pub struct GrubEnv {
+    path: PathBuf,
+}
+
+impl GrubEnv {
+    /// Initializes the manager for the environment block at the given path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Looks for the environment block at the well-known locations.
+    pub fn detect() -> Option<Self> {
+        GRUBENV_PATHS
+            .iter()
+            .map(Path::new)
+            .find(|path| path.is_file())
+            .map(Self::new)
+    }
+
+    /// Path to the environment block.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<Vec<(String, String)>> {
+        let data = std::fs::read(&self.path)
+            .with_context(|| format!("Unable to read {}", self.path.display()))?;
+        parse(&data).with_context(|| format!("Malformed environment block {}", self.path.display()))
+    }
+
+    fn store(&self, variables: &[(String, String)]) -> Result<()> {
+        let block = serialize(variables)?;
+        // The block is overwritten in place rather than replaced, since GRUB
+        // may locate it by its sectors.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(&self.path)
+            .with_context(|| format!("Unable to open {} for writing", self.path.display()))?;
+        file.write_all(&block)
+            .and_then(|()| file.sync_all())
+            .with_context(|| format!("Unable to write {}", self.path.display()))
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .load()?
+            .into_iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value))
+    }
+
+    fn set(&self, key: &str, value: Option<&str>) -> Result<()> {
+        let mut variables = self.load()?;
+        variables.retain(|(name, _)| name != key);
+        if let Some(value) = value {
+            variables.push((key.to_string(), value.to_string()));
+        }
+        self.store(&variables)
+    }
+
+    /// Fetches the current oneshot entry value (`next_entry`).
+    pub fn get_oneshot(&self) -> Result<Option<String>> {
+        self.get(NEXT_ENTRY)
+    }
+
+    /// Returns the default entry (`saved_entry`).
+    pub fn get_default_entry(&self) -> Result<Option<String>> {
+        self.get(SAVED_ENTRY)
+    }
+
+    /// Sets value of the oneshot entry.
+    pub fn set_oneshot(&mut self, value: &str) -> Result<()> {
+        self.set(NEXT_ENTRY, Some(value))
+    }
+
+    /// Sets value of the default entry.
+    pub fn set_default(&mut self, value: &str) -> Result<()> {
+        self.set(SAVED_ENTRY, Some(value))
+    }
+
+    /// Removes the oneshot entry.
+    pub fn remove_oneshot(&mut self) -> Result<()> {
+        self.set(NEXT_ENTRY, None)
+    }
+
+    /// Removes the default entry, so GRUB boots the first one.
+    pub fn remove_default(&mut self) -> Result<()> {
+        self.set(SAVED_ENTRY, None)
+    }
+
+    /// Lists the menu entries of the `grub.cfg` next to the environment
+    /// block, in the menu order, by the titles `next_entry` and `saved_entry`
+    /// accept. The entries in the submenus are prefixed with the titles of
+    /// the submenus and ">".
+    pub fn entries(&self) -> Result<Entries> {
+        let path = self.path.with_file_name(CONFIG);
+        let config = std::fs::read_to_string(&path)
+            .with_context(|| format!("Unable to read {}", path.display()))?;
+        Ok(menu_entries(&config).into_iter().map(Entry::new).collect())
+    }
+}
+
+/// Finds the titles of the `menuentry` commands in the configuration,
+/// following the nesting of the `submenu` ones.
+fn menu_entries(config: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    // The titles of the enclosing submenus and the nesting depth of their
+    // bodies.
+    let mut submenus: Vec<(String, usize)> = Vec::new();
+    let mut depth = 0;
+    for line in config.lines() {
+        let line = line.trim_start();
+        if line.starts_with('#') {
+            continue;
+        }
+        let (command, arguments) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        if let ("menuentry" | "submenu", Some(title)) = (command, first_word(arguments)) {
+            let path = submenus
+                .iter()
+                .map(|(submenu, _)| submenu.as_str())
+                .chain([title.as_str()])
+                .collect::<Vec<_>>()
+                .join(&SUBMENU_SEPARATOR.to_string());
+            if command == "menuentry" {
+                entries.push(path);
+            } else {
+                submenus.push((title, depth + 1));
+            }
+        }
+        depth = nesting(line, depth);
+        while submenus.last().is_some_and(|(_, body)| *body > depth) {
+            submenus.pop();
+        }
+    }
+    entries
+}
+
+/// Reads the first word of the arguments the way GRUB's shell-like parser
+/// does, removing the quotes and the backslash escapes.
+fn first_word(arguments: &str) -> Option<String> {
+    let mut word = String::new();
+    let mut quote = None;
+    let mut chars = arguments.trim_start().chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() || c == '{' => break,
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (Some('\''), c) => word.push(c),
+            (_, '\\') => word.extend(chars.next()),
+            (_, c) => word.push(c),
+        }
+    }
+    (!word.is_empty()).then_some(word)
+}
+
+/// Returns the nesting depth after the line, counting the braces outside
+/// the quotes.
+fn nesting(line: &str, mut depth: usize) -> usize {
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'') | (None, '"') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                chars.next();
+            }
+            (None, '{') => depth += 1,
+            (None, '}') => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Parses the environment block into a list of key-value pairs.
+fn parse(data: &[u8]) -> Result<Vec<(String, String)>> {
+    let text = std::str::from_utf8(data).context("Non-UTF8 environment block")?;
+    let text = text
+        .strip_prefix(SIGNATURE)
+        .context("GRUB environment block signature is missing")?;
+
+    // Backslashes escape newlines and backslashes within values.
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => line.extend(chars.next()),
+            '\n' => lines.push(std::mem::take(&mut line)),
+            c => line.push(c),
+        }
+    }
+    lines.push(line);
+
+    let mut variables = Vec::new();
+    for line in lines {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            log::warn!("Skipping a malformed grubenv line {:?}", line);
+            continue;
+        };
+        variables.push((key.to_string(), value.to_string()));
+    }
+    Ok(variables)
+}
+
+/// Serializes the variables into an environment block padded with `#`.
+fn serialize(variables: &[(String, String)]) -> Result<Vec<u8>> {
+    let mut block = String::from(SIGNATURE);
+    for (key, value) in variables {
+        let value = value.replace('\\', "\\\\").replace('\n', "\\\n");
+        block.push_str(key);
+        block.push('=');
+        block.push_str(&value);
+        block.push('\n');
+    }
+    anyhow::ensure!(
+        block.len() <= BLOCK_SIZE,
+        "GRUB environment block would exceed {} bytes",
+        BLOCK_SIZE
+    );
+    let mut block = block.into_bytes();
+    block.resize(BLOCK_SIZE, b'#');
+    Ok(block)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_roundtrip() {
+        let variables = vec![
+            ("saved_entry".to_string(), "Arch Linux".to_string()),
+            ("next_entry".to_string(), "line\\one\nline two".to_string()),
+        ];
+        let block = serialize(&variables).unwrap();
+        assert_eq!(block.len(), BLOCK_SIZE);
+        assert_eq!(parse(&block).unwrap(), variables);
+    }
+
+    #[test]
+    fn check_parse() {
+        let mut block = b"# GRUB Environment Block\nsaved_entry=0\nbroken\n".to_vec();
+        block.resize(BLOCK_SIZE, b'#');
+        assert_eq!(
+            parse(&block).unwrap(),
+            vec![("saved_entry".to_string(), "0".to_string())]
+        );
+        assert!(parse(b"saved_entry=0\n").is_err());
+    }
+
+    #[test]
+    fn check_menu_entries() {
+        let config = r#"
+### BEGIN /etc/grub.d/10_linux ###
+menuentry 'Ubuntu' --class ubuntu $menuentry_id_option 'gnulinux-simple' {
+	recordfail
+	linux /boot/vmlinuz root=UUID=1234 ro quiet splash
+}
+submenu 'Advanced options for Ubuntu' $menuentry_id_option 'gnulinux-advanced' {
+	menuentry 'Ubuntu, with Linux 6.5.0-14-generic' --class ubuntu {
+		if [ "${grub_platform}" = "efi" ]; then echo '}'; fi
+	}
+	menuentry 'Bob'\''s {kernel}' {
+		linux /boot/vmlinuz
+	}
+}
+# menuentry 'Commented out' {
+menuentry "UEFI Firmware Settings" {
+	fwsetup
+}
+"#;
+        assert_eq!(
+            menu_entries(config),
+            [
+                "Ubuntu",
+                "Advanced options for Ubuntu>Ubuntu, with Linux 6.5.0-14-generic",
+                "Advanced options for Ubuntu>Bob's {kernel}",
+                "UEFI Firmware Settings",
+            ]
+        );
+    }
+}
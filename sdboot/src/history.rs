@@ -0,0 +1,197 @@
+//! Boot history store.
+//!
+//! Every record describes a single boot: which entry was booted, what the
+//! default entry was at the time and how long the boot loader took. Records
+//! are kept as JSON lines, one per boot.
+
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{jsonl, BootTimings, Manager};
+
+/// Name of the history file within the data directory.
+const HISTORY_FILE: &str = "history.jsonl";
+
+/// The data directory of root.
+#[cfg(target_os = "linux")]
+const SYSTEM_DIRECTORY: &str = "/var/lib/sdboot";
+
+/// A single boot record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootRecord {
+    /// When the record was made, in seconds since the UNIX epoch.
+    pub timestamp: u64,
+    /// Kernel boot identifier, used to record every boot only once.
+    #[serde(default)]
+    pub boot_id: Option<String>,
+    /// The entry that was booted.
+    #[serde(default)]
+    pub selected: Option<String>,
+    /// The default entry at the time of the record.
+    #[serde(default)]
+    pub default: Option<String>,
+    /// The boot loader timings.
+    #[serde(default)]
+    pub timings: BootTimings,
+}
+
+impl BootRecord {
+    /// Collects the information about the current boot.
+    pub fn current(manager: &Manager) -> Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default();
+        Ok(Self {
+            timestamp,
+            boot_id: boot_id(),
+            selected: manager.get_selected_entry()?,
+            default: manager.get_default_entry()?,
+            timings: manager.get_boot_timings()?,
+        })
+    }
+
+    /// Checks whether a non-default entry was booted, e.g. due to a oneshot
+    /// entry or a manual choice in the menu. The default entry may be a glob
+    /// pattern.
+    pub fn is_non_default(&self) -> bool {
+        match (&self.selected, &self.default) {
+            (Some(selected), Some(default)) => !crate::parse::glob_match(default, selected),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn boot_id() -> Option<String> {
+    std::fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .map(|id| id.trim().to_string())
+        .ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn boot_id() -> Option<String> {
+    None
+}
+
+/// Boot history store backed by a JSON-lines file.
+pub struct History {
+    path: PathBuf,
+}
+
+impl History {
+    /// Opens the history stored at the given path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Opens the history at the default location: `/var/lib/sdboot` for
+    /// root and the XDG data directory for other users on linux,
+    /// `%ProgramData%\sdboot` on windows.
+    pub fn open_default() -> Result<Self> {
+        Ok(Self::new(default_directory()?.join(HISTORY_FILE)))
+    }
+
+    /// Opens the history to read the boots from: the one at the default
+    /// location, or the system-wide one if the former doesn't exist, e.g. for
+    /// a user reading the boots a system service has recorded.
+    pub fn open_readable() -> Result<Self> {
+        let history = Self::open_default()?;
+        #[cfg(target_os = "linux")]
+        if !history.path.exists() {
+            let system = Path::new(SYSTEM_DIRECTORY).join(HISTORY_FILE);
+            if system.exists() {
+                return Ok(Self::new(system));
+            }
+        }
+        Ok(history)
+    }
+
+    /// Path to the history file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records the current boot, unless it has already been recorded.
+    ///
+    /// Returns the new record, if any.
+    pub fn record(&self, manager: &Manager) -> Result<Option<BootRecord>> {
+        let record = BootRecord::current(manager)?;
+        if record.boot_id.is_some() {
+            let last = self.last(1)?;
+            if last.first().map(|last| &last.boot_id) == Some(&record.boot_id) {
+                log::debug!("The current boot has already been recorded");
+                return Ok(None);
+            }
+        }
+        self.append(&record)?;
+        Ok(Some(record))
+    }
+
+    /// Appends the record to the history.
+    pub fn append(&self, record: &BootRecord) -> Result<()> {
+        jsonl::append(&self.path, record)
+    }
+
+    /// Returns up to `count` most recent records, the most recent first.
+    pub fn last(&self, count: usize) -> Result<Vec<BootRecord>> {
+        let mut records = jsonl::read(&self.path)?;
+        records.reverse();
+        records.truncate(count);
+        Ok(records)
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn default_directory() -> Result<PathBuf> {
+    if nix::unistd::geteuid().is_root() {
+        return Ok(PathBuf::from(SYSTEM_DIRECTORY));
+    }
+    if let Some(data_home) = std::env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(data_home).join("sdboot"));
+    }
+    let home = std::env::var_os("HOME").context("Neither XDG_DATA_HOME nor HOME is set")?;
+    Ok(PathBuf::from(home).join(".local/share/sdboot"))
+}
+
+#[cfg(target_os = "windows")]
+fn default_directory() -> Result<PathBuf> {
+    let program_data = std::env::var_os("ProgramData").context("ProgramData is not set")?;
+    Ok(PathBuf::from(program_data).join("sdboot"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn record(timestamp: u64, boot_id: &str) -> BootRecord {
+        BootRecord {
+            timestamp,
+            boot_id: Some(boot_id.to_string()),
+            selected: Some("arch.conf".to_string()),
+            default: Some("arch.conf".to_string()),
+            timings: BootTimings::default(),
+        }
+    }
+
+    #[test]
+    fn check_append_and_last() {
+        let path =
+            std::env::temp_dir().join(format!("sdboot-history-{}.jsonl", std::process::id()));
+        let history = History::new(&path);
+        assert_eq!(history.last(10).unwrap(), vec![]);
+
+        history.append(&record(1, "a")).unwrap();
+        history.append(&record(2, "b")).unwrap();
+        history.append(&record(3, "c")).unwrap();
+        let last = history.last(2).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(last, vec![record(3, "c"), record(2, "b")]);
+    }
+}
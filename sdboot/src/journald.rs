@@ -0,0 +1,14 @@
+//! Logging to the systemd journal.
+
+use anyhow::{Context, Result};
+
+/// Creates a logger sending the records to the systemd journal.
+///
+/// The structured fields of the records (see [crate::change]) are kept as
+/// journal fields, e.g. `SDBOOT_VARIABLE`.
+pub fn logger() -> Result<Box<dyn log::Log>> {
+    let logger = systemd_journal_logger::JournalLog::new()
+        .context("Unable to connect to the systemd journal")?
+        .with_syslog_identifier("sdboot".to_string());
+    Ok(Box::new(logger))
+}
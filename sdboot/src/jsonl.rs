@@ -0,0 +1,43 @@
+//! JSON-lines files, one record per line.
+
+use std::{fs::OpenOptions, io::Write, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Appends the record to the file, creating it (and its directory) if needed.
+pub(crate) fn append<T: Serialize>(path: &Path, record: &T) -> Result<()> {
+    if let Some(directory) = path.parent() {
+        std::fs::create_dir_all(directory)
+            .with_context(|| format!("Unable to create {}", directory.display()))?;
+    }
+    let mut line = serde_json::to_string(record).context("Unable to serialize the record")?;
+    line.push('\n');
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(line.as_bytes()))
+        .with_context(|| format!("Unable to append to {}", path.display()))
+}
+
+/// Reads all the records from the file, in order. A missing file contains no
+/// records; malformed lines are skipped with a warning.
+pub(crate) fn read<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>> {
+    let contents = match std::fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Unable to read {}", path.display())),
+    };
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                log::warn!("Skipping a malformed record {:?}: {}", line, e);
+                None
+            }
+        })
+        .collect())
+}
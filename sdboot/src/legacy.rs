@@ -0,0 +1,210 @@
+//! The legacy sysfs interface to the EFI variables, `/sys/firmware/efi/vars`,
+//! used by old kernels which don't provide efivarfs.
+//!
+//! Every variable is a directory named `<Name>-<vendor UUID>`, whose `raw_var`
+//! file holds a `struct efi_variable`. A variable is created by writing the
+//! structure into `new_var`, updated by writing it into its `raw_var` and
+//! deleted by writing it into `del_var`. The values are limited to 1024 bytes.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use efivar::{
+    efi::{VariableFlags, VariableName},
+    Error, VarEnumerator, VarManager, VarReader, VarWriter,
+};
+use uuid::Uuid;
+
+/// Where the legacy interface lives.
+pub(crate) const ROOT: &str = "/sys/firmware/efi/vars";
+
+/// Size of the `VariableName` field, in bytes.
+const NAME_SIZE: usize = 1024;
+
+/// Size of the `Data` field, in bytes.
+const DATA_SIZE: usize = 1024;
+
+/// Size of `unsigned long`, used for the `DataSize` and `Status` fields.
+const ULONG_SIZE: usize = std::mem::size_of::<libc::c_ulong>();
+
+// Offsets of the fields of the packed `struct efi_variable`, see
+// `linux/efi.h`.
+const GUID_OFFSET: usize = NAME_SIZE;
+const DATA_SIZE_OFFSET: usize = GUID_OFFSET + 16;
+const DATA_OFFSET: usize = DATA_SIZE_OFFSET + ULONG_SIZE;
+const STATUS_OFFSET: usize = DATA_OFFSET + DATA_SIZE;
+const ATTRIBUTES_OFFSET: usize = STATUS_OFFSET + ULONG_SIZE;
+const STRUCT_SIZE: usize = ATTRIBUTES_OFFSET + 4;
+
+/// Checks whether the legacy interface has to be used, i.e. efivarfs is not
+/// mounted while the legacy interface is there.
+pub(crate) fn is_active() -> bool {
+    !crate::efivarfs::is_mounted() && Path::new(ROOT).join("new_var").exists()
+}
+
+/// Directory of the variable.
+fn variable_dir(var: &VariableName) -> PathBuf {
+    Path::new(ROOT).join(var.to_string())
+}
+
+/// Extracts the vendor UUID from the variable name, formatted as
+/// `<Name>-<vendor UUID>`.
+fn vendor(var: &VariableName) -> io::Result<Uuid> {
+    let full = var.to_string();
+    full.len()
+        .checked_sub(36)
+        .and_then(|start| full.get(start..))
+        .and_then(|uuid| Uuid::parse_str(uuid).ok())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Malformed variable name"))
+}
+
+/// Encodes the `struct efi_variable`.
+fn encode(var: &VariableName, attributes: u32, value: &[u8]) -> io::Result<Vec<u8>> {
+    let name = crate::parse::encode_utf16(var.short_name());
+    if name.len() > NAME_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "The variable name is too long for the legacy interface",
+        ));
+    }
+    if value.len() > DATA_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "The legacy interface doesn't support values larger than {} bytes",
+                DATA_SIZE
+            ),
+        ));
+    }
+
+    let mut raw = vec![0u8; STRUCT_SIZE];
+    raw[..name.len()].copy_from_slice(&name);
+    raw[GUID_OFFSET..DATA_SIZE_OFFSET].copy_from_slice(&vendor(var)?.to_bytes_le());
+    raw[DATA_SIZE_OFFSET..DATA_OFFSET]
+        .copy_from_slice(&(value.len() as libc::c_ulong).to_ne_bytes());
+    raw[DATA_OFFSET..DATA_OFFSET + value.len()].copy_from_slice(value);
+    raw[ATTRIBUTES_OFFSET..].copy_from_slice(&attributes.to_ne_bytes());
+    Ok(raw)
+}
+
+/// Decodes the attributes and the value out of the `struct efi_variable`.
+fn decode(raw: &[u8]) -> Option<(u32, &[u8])> {
+    if raw.len() != STRUCT_SIZE {
+        return None;
+    }
+    let data_size =
+        libc::c_ulong::from_ne_bytes(raw[DATA_SIZE_OFFSET..DATA_OFFSET].try_into().ok()?);
+    let data_size = usize::try_from(data_size)
+        .ok()
+        .filter(|&size| size <= DATA_SIZE)?;
+    let attributes = u32::from_ne_bytes(raw[ATTRIBUTES_OFFSET..].try_into().ok()?);
+    Some((attributes, &raw[DATA_OFFSET..DATA_OFFSET + data_size]))
+}
+
+/// Deletes the variable, returning whether it existed.
+pub(crate) fn delete(var: &VariableName) -> io::Result<bool> {
+    if !variable_dir(var).exists() {
+        return Ok(false);
+    }
+    std::fs::write(Path::new(ROOT).join("del_var"), encode(var, 0, &[])?)?;
+    Ok(true)
+}
+
+/// Variables manager on top of the legacy interface.
+pub(crate) struct LegacyVars;
+
+impl VarEnumerator for LegacyVars {
+    fn get_var_names<'a>(&'a self) -> efivar::Result<Box<dyn Iterator<Item = VariableName> + 'a>> {
+        let names = std::fs::read_dir(ROOT)
+            .map_err(|error| Error::UnknownIoError { error })?
+            .filter_map(|item| item.ok())
+            .filter(|item| item.path().is_dir())
+            .filter_map(|item| item.file_name().to_str()?.parse().ok())
+            .collect::<Vec<VariableName>>();
+        Ok(Box::new(names.into_iter()))
+    }
+}
+
+impl VarReader for LegacyVars {
+    fn read(
+        &self,
+        name: &VariableName,
+        value: &mut [u8],
+    ) -> efivar::Result<(usize, VariableFlags)> {
+        let raw = match std::fs::read(variable_dir(name).join("raw_var")) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Err(Error::VarNotFound { name: name.clone() })
+            }
+            Err(error) => {
+                return Err(Error::VarUnknownError {
+                    name: name.clone(),
+                    error,
+                })
+            }
+        };
+        let (attributes, data) = decode(&raw).ok_or_else(|| Error::VarUnknownError {
+            name: name.clone(),
+            error: io::Error::new(io::ErrorKind::InvalidData, "Malformed raw_var"),
+        })?;
+        let Some(target) = value.get_mut(..data.len()) else {
+            return Err(Error::BufferTooSmall { name: name.clone() });
+        };
+        target.copy_from_slice(data);
+        Ok((data.len(), VariableFlags::from_bits_truncate(attributes)))
+    }
+}
+
+impl VarWriter for LegacyVars {
+    fn write(
+        &mut self,
+        name: &VariableName,
+        attributes: VariableFlags,
+        value: &[u8],
+    ) -> efivar::Result<()> {
+        let dir = variable_dir(name);
+        let target = if dir.exists() {
+            dir.join("raw_var")
+        } else {
+            Path::new(ROOT).join("new_var")
+        };
+        encode(name, attributes.bits(), value)
+            .and_then(|raw| std::fs::write(target, raw))
+            .map_err(|error| Error::VarUnknownError {
+                name: name.clone(),
+                error,
+            })
+    }
+}
+
+impl VarManager for LegacyVars {}
+
+#[cfg(test)]
+mod test {
+    use efivar::efi::VariableVendor;
+
+    use super::*;
+
+    #[test]
+    fn check_encoding() {
+        let vendor = Uuid::parse_str("4a67b082-0a4c-41cf-b6c7-440b29bb8c4f").unwrap();
+        let var =
+            VariableName::new_with_vendor("LoaderEntryOneShot", VariableVendor::Custom(vendor));
+        let raw = encode(&var, 7, b"a\0b\0").unwrap();
+        assert_eq!(raw.len(), STRUCT_SIZE);
+        assert_eq!(&raw[..4], b"L\0o\0");
+        assert_eq!(
+            raw[GUID_OFFSET..DATA_SIZE_OFFSET],
+            [
+                0x82, 0xb0, 0x67, 0x4a, 0x4c, 0x0a, 0xcf, 0x41, 0xb6, 0xc7, 0x44, 0x0b, 0x29, 0xbb,
+                0x8c, 0x4f
+            ]
+        );
+        assert_eq!(decode(&raw), Some((7, &b"a\0b\0"[..])));
+
+        assert!(encode(&var, 7, &[0; DATA_SIZE + 1]).is_err());
+        assert_eq!(decode(&raw[1..]), None);
+    }
+}
@@ -1,14 +1,72 @@
 //! systemd-boot EFI variables manipulation library.
 
 #![deny(missing_docs)]
+// Library code reports errors instead of aborting the embedding application.
+#![cfg_attr(
+    not(test),
+    deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)
+)]
 
 mod array_ext;
+mod backend;
+pub mod backup;
+pub mod change;
+pub mod diagnostics;
+pub mod dump;
+mod entries;
 mod error;
+pub mod esp;
+mod features;
 mod manager;
+pub mod next_boot;
+pub mod parse;
+pub mod power;
+mod privileges;
 mod read;
+mod timeout;
+pub mod verify;
 mod write;
 
 #[cfg(target_os = "linux")]
 mod attributes;
+#[cfg(target_os = "linux")]
+mod efivarfs;
+#[cfg(target_os = "linux")]
+mod legacy;
+#[cfg(target_os = "linux")]
+pub mod watch;
+
+#[cfg(feature = "audit")]
+pub mod audit;
+
+#[cfg(feature = "capi")]
+pub mod capi;
+
+#[cfg(feature = "grub")]
+pub mod grub;
 
-pub use manager::Manager;
+#[cfg(all(feature = "journald", target_os = "linux"))]
+pub mod journald;
+
+#[cfg(feature = "history")]
+pub mod history;
+
+#[cfg(feature = "service")]
+pub mod service;
+
+#[cfg(any(feature = "history", feature = "audit"))]
+mod jsonl;
+
+pub use backend::Backend;
+pub use entries::{Entries, Entry, Lookup};
+pub use error::{ErrorKind, NvramFull, NvramUsage, RebootIntoError};
+pub use features::{LoaderFeatures, StubFeatures};
+pub use manager::{
+    BootOption, BootTimings, EntriesSource, ImmutableAfterWrite, ImmutableHandling, Manager,
+    OneshotCheck, SecureBoot,
+};
+pub use privileges::{MissingPrivilege, SecurityModule};
+pub use timeout::Timeout;
+
+#[cfg(target_os = "linux")]
+pub use attributes::restore_immutability_on_signals;
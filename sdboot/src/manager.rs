@@ -7,16 +7,31 @@ use efivar::{
 };
 use uuid::Uuid;
 
-use crate::{read, write};
+use crate::{
+    change,
+    diagnostics::{self, Finding},
+    esp::{Divergence, Esp},
+    next_boot::NextBoot,
+    read, write, Entries, Entry, ErrorKind, LoaderFeatures, RebootIntoError, StubFeatures, Timeout,
+};
 
 /// Vendor bytes from https://systemd.io/BOOT_LOADER_INTERFACE/
-const SYSTEMD_BOOT_VENDOR_RAW: Uuid = Uuid::from_bytes([
+pub(crate) const SYSTEMD_BOOT_VENDOR_RAW: Uuid = Uuid::from_bytes([
     0x4a, 0x67, 0xb0, 0x82, 0x0a, 0x4c, 0x41, 0xcf, 0xb6, 0xc7, 0x44, 0x0b, 0x29, 0xbb, 0x8c, 0x4f,
 ]);
 
 /// SystemD vendor UUID.
 const SYSTEMD_BOOT_VENDOR: VariableVendor = VariableVendor::Custom(SYSTEMD_BOOT_VENDOR_RAW);
 
+/// Private vendor of the scratch variable written by [Manager::self_test], so
+/// it never clashes with the variables of the boot loader or the firmware.
+const SELF_TEST_VENDOR: VariableVendor = VariableVendor::Custom(Uuid::from_bytes([
+    0x6b, 0x0e, 0x3f, 0x52, 0x9d, 0x4a, 0x4c, 0x21, 0x8f, 0x17, 0x2e, 0xa5, 0x5c, 0x90, 0xd3, 0x6e,
+]));
+
+/// Name of the scratch variable written by [Manager::self_test].
+const SELF_TEST_VARIABLE: &str = "SdbootSelfTest";
+
 /// The EFI variable LoaderEntryOneShot contains the default boot loader entry
 /// to use for a single following boot. It is set by the OS in order to request
 /// booting into a specific menu entry on the following boot. When set overrides
@@ -57,11 +72,229 @@ const LOADER_ENTRY_SELECTED: &str = "LoaderEntrySelected";
 /// (c) https://systemd.io/BOOT_LOADER_INTERFACE/
 const LOADER_ENTRY_DEFAULT: &str = "LoaderEntryDefault";
 
+/// The EFI variable LoaderInfo contains a NUL-terminated string identifying
+/// the boot loader and its version.
+///
+/// (c) https://systemd.io/BOOT_LOADER_INTERFACE/
+const LOADER_INFO: &str = "LoaderInfo";
+
+/// The EFI variable LoaderFeatures contains a 64-bit unsigned integer with a
+/// number of flags bits that are set by the boot loader and passed to the OS
+/// and indicate the features the boot loader supports.
+///
+/// (c) https://systemd.io/BOOT_LOADER_INTERFACE/
+const LOADER_FEATURES: &str = "LoaderFeatures";
+
+/// The features of the systemd-stub, set only when a unified kernel image is
+/// booted.
+const STUB_FEATURES: &str = "StubFeatures";
+
+/// The EFI variables LoaderTimeInitUSec and LoaderTimeExecUSec are set to
+/// the timestamps (in µs) when the boot loader was initialized and when it
+/// passed control to the selected entry, respectively.
+///
+/// (c) https://systemd.io/BOOT_LOADER_INTERFACE/
+const LOADER_TIME_INIT: &str = "LoaderTimeInitUSec";
+const LOADER_TIME_EXEC: &str = "LoaderTimeExecUSec";
+
+/// The EFI variable LoaderTimeMenuUSec contains the time (in µs) the user
+/// spent in the boot menu.
+///
+/// (c) https://systemd.io/BOOT_LOADER_INTERFACE/
+const LOADER_TIME_MENU: &str = "LoaderTimeMenuUSec";
+
+/// The EFI variables LoaderFirmwareInfo and LoaderFirmwareType contain
+/// NUL-terminated strings identifying the firmware, e.g. "EDK II 1.00" and
+/// "UEFI 2.70" respectively.
+///
+/// (c) https://systemd.io/BOOT_LOADER_INTERFACE/
+const LOADER_FIRMWARE_INFO: &str = "LoaderFirmwareInfo";
+const LOADER_FIRMWARE_TYPE: &str = "LoaderFirmwareType";
+
+/// The EFI variable LoaderConfigTimeout contains the boot menu timeout
+/// currently in use. It may be modified both by the boot loader and by the
+/// host.
+///
+/// (c) https://systemd.io/BOOT_LOADER_INTERFACE/
+const TIMEOUT_SHORT: &str = "LoaderConfigTimeout";
+
+/// The global EFI variables SecureBoot and SetupMode contain a single byte,
+/// set to 1 when Secure Boot is enforced and when the platform is in setup
+/// mode respectively.
+///
+/// (c) UEFI specification, section 3.3 "Globally Defined Variables"
+const SECURE_BOOT: &str = "SecureBoot";
+const SETUP_MODE: &str = "SetupMode";
+
+/// The global EFI variable OsIndications contains a 64-bit bit mask the OS
+/// uses to request features of the firmware on the next boot, and
+/// OsIndicationsSupported contains the mask of the supported ones.
+///
+/// (c) UEFI specification, section 8.5.4 "Exchanging information between the
+/// OS and Firmware"
+const OS_INDICATIONS: &str = "OsIndications";
+const OS_INDICATIONS_SUPPORTED: &str = "OsIndicationsSupported";
+
+/// The OsIndications bit requesting the firmware to show its setup UI.
+const BOOT_TO_FW_UI: u64 = 0x1;
+
+/// The firmware boot manager variables, with the EFI global vendor UUID.
+const BOOT_NEXT: &str = "BootNext";
+const BOOT_ORDER: &str = "BootOrder";
+
+/// A firmware boot manager option, i.e. a `Boot####` variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BootOption {
+    /// The option number, the `####` of `Boot####`.
+    pub number: u16,
+    /// The description shown by the firmware.
+    pub description: String,
+    /// Whether the firmware will try to boot the option.
+    pub active: bool,
+}
+
+impl BootOption {
+    /// The variable name, e.g. "Boot0003".
+    pub fn name(&self) -> String {
+        format!("Boot{:04X}", self.number)
+    }
+}
+
+/// Secure Boot state reported by the firmware.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum SecureBoot {
+    /// Secure Boot is enforced.
+    Enabled,
+    /// Secure Boot is not enforced.
+    Disabled,
+    /// No platform key is enrolled, so the platform is in setup mode.
+    SetupMode,
+}
+
+impl std::fmt::Display for SecureBoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SecureBoot::Enabled => "enabled",
+            SecureBoot::Disabled => "disabled",
+            SecureBoot::SetupMode => "disabled (setup mode)",
+        })
+    }
+}
+
+/// Boot timings reported by the boot loader, in microseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BootTimings {
+    /// When the boot loader was initialized, since the firmware start.
+    pub init_usec: Option<u64>,
+    /// When the boot loader passed control to the entry, since the firmware
+    /// start.
+    pub exec_usec: Option<u64>,
+    /// How long the user spent in the boot menu.
+    pub menu_usec: Option<u64>,
+}
+
+impl BootTimings {
+    /// How long the firmware took before starting the boot loader.
+    pub fn firmware_usec(&self) -> Option<u64> {
+        self.init_usec
+    }
+
+    /// How long the boot loader took before starting the entry, including
+    /// the time spent in the menu.
+    pub fn loader_usec(&self) -> Option<u64> {
+        self.exec_usec?.checked_sub(self.init_usec?)
+    }
+}
+
+/// Result of the oneshot entry consistency check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OneshotCheck {
+    /// The oneshot entry is not set.
+    NotSet,
+    /// The oneshot entry points at one of the discovered entries.
+    Valid(String),
+    /// The oneshot entry points at an entry the boot loader doesn't know of,
+    /// hence the boot loader will silently fall back to the default entry.
+    Stale {
+        /// The oneshot entry value.
+        entry: String,
+        /// Whether the stale oneshot entry has been removed.
+        cleared: bool,
+    },
+}
+
+/// The EFI variable LoaderConfigTimeoutOneShot contains a boot menu timeout
+/// for a single following boot. It is set by the OS in order to request
+/// display of the boot menu on the following boot. When set overrides
+/// LoaderConfigTimeout. It is removed automatically after being read by the
+/// boot loader.
+///
+/// (c) https://systemd.io/BOOT_LOADER_INTERFACE/
+const TIMEOUT_ONESHOT_SHORT: &str = "LoaderConfigTimeoutOneShot";
+
+/// How to deal with failures to remove the "immutable" attribute of the
+/// variable files before writing them on linux.
+///
+/// Some environments (e.g. containers) don't allow the attribute ioctls on
+/// efivarfs even though the writes themselves succeed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImmutableHandling {
+    /// Fail the operation.
+    #[default]
+    Strict,
+    /// Log a warning and try to write anyway.
+    Warn,
+    /// Don't touch the attribute at all.
+    Skip,
+}
+
+/// What to do with the "immutable" attribute of the variable files on linux
+/// once they have been written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImmutableAfterWrite {
+    /// Restore the attribute as it was before the write.
+    #[default]
+    Restore,
+    /// Leave the file mutable, e.g. for other tools managing the variables.
+    LeaveMutable,
+    /// Make the file immutable, even if it wasn't before the write.
+    SetImmutable,
+}
+
+/// Where [Manager::entries] takes the entries from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum EntriesSource {
+    /// The `LoaderEntries` variable the boot loader sets.
+    #[default]
+    Loader,
+    /// The `LoaderEntries` variable, or the entries on the ESP if it is not
+    /// set, e.g. when the system hasn't been rebooted since the boot loader
+    /// has been installed.
+    LoaderOrEsp,
+    /// The entries on the ESP.
+    Esp,
+}
+
 /// Systemd-boot entries manager.
 pub struct Manager {
     inner: Box<dyn VarManager>,
     oneshot_var: VariableName,
     default_var: VariableName,
+    check_features: bool,
+    entries_source: EntriesSource,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    immutable_handling: ImmutableHandling,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    immutable_after_write: ImmutableAfterWrite,
+    #[cfg(feature = "audit")]
+    audit_log: Option<crate::audit::AuditLog>,
+    /// Whether the legacy sysfs interface is used instead of efivarfs.
+    #[cfg(target_os = "linux")]
+    legacy: bool,
 }
 
 // Flags on the oneshot/default entries EFI variables.
@@ -69,37 +302,172 @@ fn entry_flags() -> VariableFlags {
     VariableFlags::NON_VOLATILE | VariableFlags::BOOTSERVICE_ACCESS | VariableFlags::RUNTIME_ACCESS
 }
 
-#[cfg(target_os = "linux")]
-const ONESHOT_PATH: &str = concat!(
-    // Path to the EFI variables storage on linux.
-    "/sys/firmware/efi/efivars/",
-    // Name of the EFI variable in question.
-    "LoaderEntryOneShot",
-    // Delimiter.
-    "-",
-    // SystemD vendor UUID.
-    "4a67b082-0a4c-41cf-b6c7-440b29bb8c4f"
-);
-
-#[cfg(target_os = "linux")]
-const DEFAULT_PATH: &str = concat!(
-    // Path to the EFI variables storage on linux.
-    "/sys/firmware/efi/efivars/",
-    // Name of the EFI variable in question.
-    "LoaderEntryDefault",
-    // Delimiter.
-    "-",
-    // SystemD vendor UUID.
-    "4a67b082-0a4c-41cf-b6c7-440b29bb8c4f"
-);
-
 impl Manager {
     /// Initializes the manager.
     pub fn new() -> Self {
+        #[cfg(target_os = "linux")]
+        let legacy = crate::legacy::is_active();
+        #[cfg(target_os = "linux")]
+        let inner: Box<dyn VarManager> = if legacy {
+            log::debug!(
+                "efivarfs is not mounted, using the legacy {} interface",
+                crate::legacy::ROOT
+            );
+            Box::new(crate::legacy::LegacyVars)
+        } else {
+            efivar::system()
+        };
+        #[cfg(not(target_os = "linux"))]
+        let inner = efivar::system();
+
         Self {
-            inner: efivar::system(),
+            inner,
             oneshot_var: VariableName::new_with_vendor(ONESHOT_ENTRY_SHORT, SYSTEMD_BOOT_VENDOR),
             default_var: VariableName::new_with_vendor(DEFAULT_ENTRY_SHORT, SYSTEMD_BOOT_VENDOR),
+            check_features: false,
+            entries_source: EntriesSource::default(),
+            immutable_handling: ImmutableHandling::default(),
+            immutable_after_write: ImmutableAfterWrite::default(),
+            #[cfg(feature = "audit")]
+            audit_log: None,
+            #[cfg(target_os = "linux")]
+            legacy,
+        }
+    }
+
+    /// Enables or disables verification of the boot loader features before
+    /// writing variables the boot loader might not support.
+    ///
+    /// When enabled, [Manager::set_oneshot] fails with
+    /// [ErrorKind::OneshotNotSupported] if the boot loader reports it doesn't
+    /// support oneshot entries.
+    pub fn with_feature_check(mut self, check_features: bool) -> Self {
+        self.check_features = check_features;
+        self
+    }
+
+    /// Sets where [Manager::entries] takes the entries from.
+    pub fn with_entries_source(mut self, entries_source: EntriesSource) -> Self {
+        self.entries_source = entries_source;
+        self
+    }
+
+    /// Sets how to deal with failures to remove the "immutable" attribute of
+    /// the variable files. Has no effect on other systems than linux.
+    pub fn with_immutable_handling(mut self, immutable_handling: ImmutableHandling) -> Self {
+        self.immutable_handling = immutable_handling;
+        self
+    }
+
+    /// Sets what to do with the "immutable" attribute of the variable files
+    /// after writing them. Has no effect on other systems than linux, or when
+    /// the attribute is not touched at all ([ImmutableHandling::Skip]).
+    pub fn with_immutable_after_write(
+        mut self,
+        immutable_after_write: ImmutableAfterWrite,
+    ) -> Self {
+        self.immutable_after_write = immutable_after_write;
+        self
+    }
+
+    /// Records every change of the variables in the audit log.
+    #[cfg(feature = "audit")]
+    pub fn with_audit_log(mut self, audit_log: crate::audit::AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
+    /// Reports a successful change of the variable.
+    fn report_change(&self, variable: &str, old: Option<&str>, new: Option<&str>) {
+        change::report(variable, old, new);
+        #[cfg(feature = "audit")]
+        if let Some(audit_log) = &self.audit_log {
+            let record = crate::audit::AuditRecord::now(variable, old, new);
+            if let Err(e) = audit_log.append(&record) {
+                log::error!("Unable to record the change of {}: {:#}", variable, e);
+            }
+        }
+    }
+
+    /// Prepares the variable file for writing: makes sure efivarfs is
+    /// writable and temporarily removes the "immutable" attribute from the
+    /// file, according to the [ImmutableHandling].
+    #[cfg(target_os = "linux")]
+    fn prepare_write(&self, var: &VariableName) -> Result<Option<crate::attributes::Guard>> {
+        crate::efivarfs::ensure_writable()?;
+        // The legacy interface has no attributes to deal with.
+        if self.legacy || self.immutable_handling == ImmutableHandling::Skip {
+            return Ok(None);
+        }
+        let path = crate::efivarfs::variable_path(var);
+        self.immutable_outcome(crate::attributes::temp_mutable(&path), var)
+    }
+
+    /// Applies the [ImmutableAfterWrite] policy to the written variable file.
+    #[cfg(target_os = "linux")]
+    fn finish_write(
+        &self,
+        var: &VariableName,
+        guard: Option<crate::attributes::Guard>,
+    ) -> Result<()> {
+        use crate::attributes::FileAttributes;
+
+        match self.immutable_after_write {
+            // The guard restores the original attribute when dropped.
+            ImmutableAfterWrite::Restore => Ok(()),
+            ImmutableAfterWrite::LeaveMutable => {
+                if let Some(guard) = guard {
+                    guard.disarm();
+                }
+                Ok(())
+            }
+            ImmutableAfterWrite::SetImmutable => {
+                drop(guard);
+                if self.legacy || self.immutable_handling == ImmutableHandling::Skip {
+                    return Ok(());
+                }
+                let path = crate::efivarfs::variable_path(var);
+                let result = std::fs::File::open(&path)
+                    .context("Unable to open the file")
+                    .and_then(|file| file.set_immutable(true).map_err(anyhow::Error::from));
+                match result {
+                    Ok(()) => Ok(()),
+                    Err(e) if self.immutable_handling == ImmutableHandling::Warn => {
+                        log::warn!("Unable to make file {} immutable: {:#}", path.display(), e);
+                        Ok(())
+                    }
+                    Err(e) => Err(crate::privileges::explain(e, var).context(format!(
+                        "The variable has been written, but unable to make file {} immutable",
+                        path.display()
+                    ))),
+                }
+            }
+        }
+    }
+
+    /// Applies the [ImmutableHandling] to the outcome of the "immutable"
+    /// attribute removal from the file.
+    #[cfg(target_os = "linux")]
+    fn immutable_outcome<T>(
+        &self,
+        result: Result<Option<T>>,
+        var: &VariableName,
+    ) -> Result<Option<T>> {
+        let path = crate::efivarfs::variable_path(var);
+        match result {
+            Ok(value) => Ok(value),
+            Err(e) if self.immutable_handling == ImmutableHandling::Warn => {
+                log::warn!(
+                    "Unable to remove immutability flag on file {}, writing anyway: {:#}",
+                    path.display(),
+                    e
+                );
+                Ok(None)
+            }
+            Err(e) => Err(crate::privileges::explain(e, var).context(format!(
+                "Unable to remove immutability flag on file {}",
+                path.display()
+            ))),
         }
     }
 
@@ -121,6 +489,97 @@ impl Manager {
         self.get_string(LOADER_ENTRY_DEFAULT)
     }
 
+    /// Returns the boot loader name and version, e.g. "systemd-boot 254".
+    ///
+    /// [None] means the system wasn't booted with a loader implementing the
+    /// boot loader interface.
+    pub fn get_loader_info(&self) -> Result<Option<String>> {
+        self.get_string(LOADER_INFO)
+    }
+
+    /// Returns the firmware type and version, e.g. "UEFI 2.70", as reported
+    /// by the boot loader.
+    pub fn get_firmware_type(&self) -> Result<Option<String>> {
+        self.get_string(LOADER_FIRMWARE_TYPE)
+    }
+
+    /// Returns the firmware vendor and version, e.g. "EDK II 1.00", as
+    /// reported by the boot loader.
+    pub fn get_firmware_info(&self) -> Result<Option<String>> {
+        self.get_string(LOADER_FIRMWARE_INFO)
+    }
+
+    /// Returns the boot menu timeout currently in use, e.g. "5" or
+    /// "menu-force".
+    pub fn get_timeout(&self) -> Result<Option<String>> {
+        self.get_string(TIMEOUT_SHORT)
+    }
+
+    /// Returns the boot menu timeout requested for the next boot.
+    pub fn get_timeout_oneshot(&self) -> Result<Option<String>> {
+        self.get_string(TIMEOUT_ONESHOT_SHORT)
+    }
+
+    fn get_global_flag(&self, var_name: &str) -> Result<Option<bool>> {
+        Ok(read::read_u8(&*self.inner, &VariableName::new(var_name))?
+            .map(|(value, _flags)| value == 1))
+    }
+
+    /// Returns the Secure Boot state. [None] means the firmware doesn't
+    /// support Secure Boot.
+    pub fn get_secure_boot(&self) -> Result<Option<SecureBoot>> {
+        let Some(enabled) = self.get_global_flag(SECURE_BOOT)? else {
+            return Ok(None);
+        };
+        let state = if enabled {
+            SecureBoot::Enabled
+        } else if self.get_global_flag(SETUP_MODE)? == Some(true) {
+            SecureBoot::SetupMode
+        } else {
+            SecureBoot::Disabled
+        };
+        Ok(Some(state))
+    }
+
+    fn get_usec(&self, var_name: &str) -> Result<Option<u64>> {
+        self.get_string(var_name)?
+            .map(|value| {
+                value
+                    .trim()
+                    .parse()
+                    .with_context(|| format!("Invalid {} value {:?}", var_name, value))
+            })
+            .transpose()
+    }
+
+    /// Returns the boot timings reported by the boot loader.
+    pub fn get_boot_timings(&self) -> Result<BootTimings> {
+        Ok(BootTimings {
+            init_usec: self.get_usec(LOADER_TIME_INIT)?,
+            exec_usec: self.get_usec(LOADER_TIME_EXEC)?,
+            menu_usec: self.get_usec(LOADER_TIME_MENU)?,
+        })
+    }
+
+    /// Returns the features reported by the boot loader.
+    pub fn get_loader_features(&self) -> Result<Option<LoaderFeatures>> {
+        Ok(read::read_u64(
+            &*self.inner,
+            &VariableName::new_with_vendor(LOADER_FEATURES, SYSTEMD_BOOT_VENDOR),
+        )?
+        .map(|(bits, _flags)| LoaderFeatures::from_bits(bits)))
+    }
+
+    /// Returns the features reported by the systemd-stub, [None] unless a
+    /// unified kernel image has been booted.
+    pub fn get_stub_features(&self) -> Result<Option<StubFeatures>> {
+        Ok(read::read_u64(
+            &*self.inner,
+            &VariableName::new_with_vendor(STUB_FEATURES, SYSTEMD_BOOT_VENDOR),
+        )?
+        .map(|(bits, _flags)| StubFeatures::from_bits(bits)))
+    }
+
     /// Fetches the current oneshot entry value.
     pub fn get_oneshot(&self) -> Result<Option<String>> {
         let (value, flags) = match read::read_utf16_string(&*self.inner, &self.oneshot_var)? {
@@ -140,20 +599,32 @@ impl Manager {
 
     /// Sets value of the oneshot entry.
     pub fn set_oneshot(&mut self, value: &str) -> Result<()> {
+        if self.check_features {
+            match self.get_loader_features()? {
+                Some(features) if !features.contains(LoaderFeatures::ENTRY_ONESHOT) => {
+                    return Err(ErrorKind::OneshotNotSupported.into())
+                }
+                Some(_) => { /* Supported */ }
+                None => log::warn!(
+                    "The boot loader doesn't report its features, assuming oneshot entries are supported"
+                ),
+            }
+        }
+
         let flags = entry_flags();
 
         // On linux, we want to preserve the "immutable" extended attribute on
         // the variable file, but we need to make it mutable to save the new
         // value temporary.
         #[cfg(target_os = "linux")]
-        let _guard = crate::attributes::temp_mutable(ONESHOT_PATH).with_context(|| {
-            format!(
-                "Unable to remove immutability flag on file {}",
-                ONESHOT_PATH
-            )
-        })?;
+        let guard = self.prepare_write(&self.oneshot_var)?;
 
-        write::write_utf16_string(&mut *self.inner, &self.oneshot_var, flags, value)
+        let old = self.get_string(ONESHOT_ENTRY_SHORT).ok().flatten();
+        write::write_utf16_string(&mut *self.inner, &self.oneshot_var, flags, value)?;
+        self.report_change(ONESHOT_ENTRY_SHORT, old.as_deref(), Some(value));
+        #[cfg(target_os = "linux")]
+        self.finish_write(&self.oneshot_var, guard)?;
+        Ok(())
     }
 
     /// Sets value of the default entry.
@@ -164,41 +635,309 @@ impl Manager {
         // the variable file, but we need to make it mutable to save the new
         // value temporary.
         #[cfg(target_os = "linux")]
-        let _guard = crate::attributes::temp_mutable(DEFAULT_PATH).with_context(|| {
-            format!(
-                "Unable to remove immutability flag on file {}",
-                DEFAULT_PATH
-            )
-        })?;
+        let guard = self.prepare_write(&self.default_var)?;
 
-        write::write_utf16_string(&mut *self.inner, &self.default_var, flags, value)
+        let old = self.get_string(DEFAULT_ENTRY_SHORT).ok().flatten();
+        write::write_utf16_string(&mut *self.inner, &self.default_var, flags, value)?;
+        self.report_change(DEFAULT_ENTRY_SHORT, old.as_deref(), Some(value));
+        #[cfg(target_os = "linux")]
+        self.finish_write(&self.default_var, guard)?;
+        Ok(())
+    }
+
+    /// Rewrites the malformed variable with the correct attributes and the
+    /// NUL terminator, see [crate::verify::repairs].
+    pub fn repair(&mut self, repair: &crate::verify::Repair) -> Result<()> {
+        let var = VariableName::new_with_vendor(&repair.variable, SYSTEMD_BOOT_VENDOR);
+        let old = read::read_bytes(&*self.inner, &var)?;
+        // The old value is malformed, it is recorded as close to the original
+        // as possible.
+        let old_value =
+            old.as_ref()
+                .map(|(bytes, _flags)| match crate::parse::u16_le_words(bytes) {
+                    Some(words) => String::from_utf16_lossy(&words),
+                    None => format!("{:02x?}", bytes),
+                });
+
+        // efivarfs refuses to change the attributes of an existing variable,
+        // hence it is recreated, and restored as it was if that fails.
+        #[cfg(target_os = "linux")]
+        self.delete_variable(&var)?;
+        #[cfg(target_os = "linux")]
+        let guard = self.prepare_write(&var)?;
+
+        if let Err(e) =
+            write::write_utf16_string(&mut *self.inner, &var, entry_flags(), &repair.value)
+        {
+            #[cfg(target_os = "linux")]
+            if let Some((bytes, flags)) = &old {
+                if let Err(restore_error) =
+                    efivar::VarWriter::write(&mut *self.inner, &var, *flags, bytes)
+                {
+                    return Err(e.context(format!(
+                        "Unable to restore {} either ({}), set it to {:?} manually",
+                        repair.variable,
+                        crate::error::EfiError(restore_error),
+                        repair.value
+                    )));
+                }
+                log::info!("Restored the previous value of {}", repair.variable);
+            }
+            return Err(e.context(format!("Unable to repair {}", repair.variable)));
+        }
+        self.report_change(&repair.variable, old_value.as_deref(), Some(&repair.value));
+        #[cfg(target_os = "linux")]
+        self.finish_write(&var, guard)?;
+        Ok(())
+    }
+
+    /// Arranges for the boot menu to be shown on the next boot, waiting for
+    /// the user to pick an entry.
+    ///
+    /// On linux, if the variable can't be written directly (e.g. the process
+    /// is not privileged), logind is asked to do it instead.
+    pub fn show_menu_once(&mut self) -> Result<()> {
+        match self.set_timeout_oneshot(Timeout::MenuForce) {
+            Ok(()) => Ok(()),
+            #[cfg(target_os = "linux")]
+            Err(error) => {
+                log::debug!(
+                    "Unable to set {} directly, falling back to logind: {:#}",
+                    TIMEOUT_ONESHOT_SHORT,
+                    error
+                );
+                // Five minutes, in microseconds.
+                crate::power::logind_call("SetRebootToBootLoaderMenu", "t", &["300000000"])
+                    .map_err(|logind_error| error.context(format!("{:#}", logind_error)))
+            }
+            #[cfg(not(target_os = "linux"))]
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Checks whether the firmware can be asked to show its setup UI on the
+    /// next boot.
+    pub fn firmware_setup_supported(&self) -> Result<bool> {
+        Ok(
+            read::read_u64(&*self.inner, &VariableName::new(OS_INDICATIONS_SUPPORTED))?
+                .map_or(false, |(bits, _flags)| bits & BOOT_TO_FW_UI != 0),
+        )
+    }
+
+    /// Asks the firmware to show its setup UI on the next boot, instead of
+    /// booting the OS.
+    ///
+    /// On linux, if the variable can't be written directly (e.g. the process
+    /// is not privileged), logind is asked to do it instead.
+    pub fn show_firmware_setup_once(&mut self) -> Result<()> {
+        anyhow::ensure!(
+            self.firmware_setup_supported()?,
+            "The firmware doesn't support booting into its setup UI on request"
+        );
+        match self.set_os_indications(BOOT_TO_FW_UI) {
+            Ok(()) => Ok(()),
+            #[cfg(target_os = "linux")]
+            Err(error) => {
+                log::debug!(
+                    "Unable to set {} directly, falling back to logind: {:#}",
+                    OS_INDICATIONS,
+                    error
+                );
+                crate::power::logind_call("SetRebootToFirmwareSetup", "b", &["true"])
+                    .map_err(|logind_error| error.context(format!("{:#}", logind_error)))
+            }
+            #[cfg(not(target_os = "linux"))]
+            Err(error) => Err(error),
+        }
+    }
+
+    /// Sets the given bits of OsIndications, keeping the rest.
+    fn set_os_indications(&mut self, bits: u64) -> Result<()> {
+        let var = VariableName::new(OS_INDICATIONS);
+        let old = read::read_u64(&*self.inner, &var)?.map(|(value, _flags)| value);
+        let new = old.unwrap_or(0) | bits;
+        if old == Some(new) {
+            log::debug!("{} already has the bits {:#x}", OS_INDICATIONS, bits);
+            return Ok(());
+        }
+
+        #[cfg(target_os = "linux")]
+        let guard = self.prepare_write(&var)?;
+
+        write::write_u64(&mut *self.inner, &var, entry_flags(), new)?;
+        self.report_change(
+            OS_INDICATIONS,
+            old.map(|old| format!("{:#x}", old)).as_deref(),
+            Some(&format!("{:#x}", new)),
+        );
+        #[cfg(target_os = "linux")]
+        self.finish_write(&var, guard)?;
+        Ok(())
+    }
+
+    /// Sets the boot menu timeout.
+    pub fn set_timeout(&mut self, timeout: Timeout) -> Result<()> {
+        self.set_string(TIMEOUT_SHORT, &timeout.to_string())
+    }
+
+    /// Sets the boot menu timeout for the next boot only.
+    pub fn set_timeout_oneshot(&mut self, timeout: Timeout) -> Result<()> {
+        self.set_string(TIMEOUT_ONESHOT_SHORT, &timeout.to_string())
+    }
+
+    /// Removes the boot menu timeout, so the one from the boot loader
+    /// configuration applies.
+    #[cfg(target_os = "linux")]
+    pub fn remove_timeout(&mut self) -> Result<()> {
+        self.remove_variable(&VariableName::new_with_vendor(
+            TIMEOUT_SHORT,
+            SYSTEMD_BOOT_VENDOR,
+        ))
+        .context("Unable to remove the timeout")
+    }
+
+    /// Removes the boot menu timeout for the next boot.
+    #[cfg(target_os = "linux")]
+    pub fn remove_timeout_oneshot(&mut self) -> Result<()> {
+        self.remove_variable(&VariableName::new_with_vendor(
+            TIMEOUT_ONESHOT_SHORT,
+            SYSTEMD_BOOT_VENDOR,
+        ))
+        .context("Unable to remove the oneshot timeout")
+    }
+
+    /// Removes the boot menu timeout, so the one from the boot loader
+    /// configuration applies.
+    #[cfg(target_os = "windows")]
+    pub fn remove_timeout(&mut self) -> Result<()> {
+        // On windows, to delete a variable one needs to set it to an empty
+        // (size = 0) value.
+        self.set_string(TIMEOUT_SHORT, "")
+    }
+
+    /// Removes the boot menu timeout for the next boot.
+    #[cfg(target_os = "windows")]
+    pub fn remove_timeout_oneshot(&mut self) -> Result<()> {
+        self.set_string(TIMEOUT_ONESHOT_SHORT, "")
+    }
+
+    fn set_string(&mut self, var_name: &str, value: &str) -> Result<()> {
+        let flags = entry_flags();
+        let var = VariableName::new_with_vendor(var_name, SYSTEMD_BOOT_VENDOR);
+
+        #[cfg(target_os = "linux")]
+        let guard = self.prepare_write(&var)?;
+
+        let old = self.get_string(var_name).ok().flatten();
+        write::write_utf16_string(&mut *self.inner, &var, flags, value)?;
+        self.report_change(var_name, old.as_deref(), Some(value));
+        #[cfg(target_os = "linux")]
+        self.finish_write(&var, guard)?;
+        Ok(())
+    }
+
+    /// Sets value of the oneshot entry unless it already has the same value.
+    ///
+    /// Returns whether the variable has been written. Useful to spare NVRAM
+    /// writes when the desired state is applied repeatedly.
+    pub fn set_oneshot_if_changed(&mut self, value: &str) -> Result<bool> {
+        if self.get_oneshot()?.as_deref() == Some(value) {
+            log::debug!("Oneshot entry is already set to {}", value);
+            return Ok(false);
+        }
+        self.set_oneshot(value)?;
+        Ok(true)
+    }
+
+    /// Sets value of the default entry unless it already has the same value.
+    ///
+    /// Returns whether the variable has been written.
+    pub fn set_default_if_changed(&mut self, value: &str) -> Result<bool> {
+        if self.get_default_entry()?.as_deref() == Some(value) {
+            log::debug!("Default entry is already set to {}", value);
+            return Ok(false);
+        }
+        self.set_default(value)?;
+        Ok(true)
+    }
+
+    /// Sets the boot menu timeout unless it already has the same value.
+    ///
+    /// Returns whether the variable has been written.
+    pub fn set_timeout_if_changed(&mut self, timeout: Timeout) -> Result<bool> {
+        let value = timeout.to_string();
+        if self.get_timeout()?.as_deref() == Some(value.as_str()) {
+            log::debug!("Timeout is already set to {}", value);
+            return Ok(false);
+        }
+        self.set_timeout(timeout)?;
+        Ok(true)
     }
 
     #[cfg(target_os = "linux")]
     /// Removes the oneshot entry.
     pub fn remove_oneshot(&mut self) -> Result<()> {
+        self.remove_variable(&self.oneshot_var)
+            .context("Unable to remove a oneshot entry")
+    }
+
+    #[cfg(target_os = "linux")]
+    /// Removes the default entry, so the one from the boot loader
+    /// configuration applies.
+    pub fn remove_default(&mut self) -> Result<()> {
+        self.remove_variable(&self.default_var)
+            .context("Unable to remove the default entry")
+    }
+
+    /// Removes the variable by deleting its efivarfs file (or via the legacy
+    /// interface). A missing variable is not an error.
+    #[cfg(target_os = "linux")]
+    fn remove_variable(&self, var: &VariableName) -> Result<()> {
+        let old = read::read_utf16_string(&*self.inner, var)
+            .ok()
+            .flatten()
+            .map(|(string, _flags)| string);
+        if self.delete_variable(var)? {
+            self.report_change(var.short_name(), old.as_deref(), None);
+        }
+        Ok(())
+    }
+
+    /// Deletes the variable without reporting the change. Returns whether
+    /// the variable existed.
+    #[cfg(target_os = "linux")]
+    fn delete_variable(&self, var: &VariableName) -> Result<bool> {
         use crate::attributes::FileAttributes;
 
-        match std::fs::File::open(ONESHOT_PATH) {
-            Ok(file) => file.set_immutable(false).with_context(|| {
-                format!("Unable to make oneshot file {} non-immutable", ONESHOT_PATH)
-            })?,
-            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
-                // No file => nothing to delete => success.
-            }
-            Err(e) => {
-                return Err(e)
-                    .with_context(|| format!("Unable to open a oneshot entry at {}", ONESHOT_PATH))
-            }
-        };
-        match std::fs::remove_file(ONESHOT_PATH) {
-            Ok(()) => Ok(()),
+        let path = crate::efivarfs::variable_path(var);
+        if self.legacy {
+            return crate::legacy::delete(var)
+                .map_err(|e| crate::privileges::explain(e.into(), var))
+                .with_context(|| format!("Unable to delete variable {}", var));
+        }
+        crate::efivarfs::ensure_writable()?;
+        if self.immutable_handling != ImmutableHandling::Skip {
+            let result = match std::fs::File::open(&path) {
+                Ok(file) => file
+                    .set_immutable(false)
+                    .map(Some)
+                    .map_err(anyhow::Error::from),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    // No file => nothing to delete => success.
+                    Ok(None)
+                }
+                Err(e) => Err(anyhow::Error::new(e).context("Unable to open the file")),
+            };
+            self.immutable_outcome(result, var)?;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => Ok(true),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
                 // File disappeared => nothing to delete => success.
-                Ok(())
+                Ok(false)
             }
-            Err(e) => Err(e)
-                .with_context(|| format!("Unable to remove a oneshot entry at {}", ONESHOT_PATH)),
+            Err(e) => Err(crate::privileges::explain(e.into(), var))
+                .with_context(|| format!("Unable to remove {}", path.display())),
         }
     }
 
@@ -210,33 +949,300 @@ impl Manager {
         self.set_oneshot("")
     }
 
-    /// Fetches the available entries.
-    pub fn entries(&self) -> Result<Vec<String>> {
+    #[cfg(target_os = "windows")]
+    /// Removes the default entry, so the one from the boot loader
+    /// configuration applies.
+    pub fn remove_default(&mut self) -> Result<()> {
+        self.set_default("")
+    }
+
+    /// Checks the variables can be written end to end, i.e. the privileges,
+    /// the immutability handling and the firmware, without touching the boot
+    /// loader ones: writes a scratch variable under a private vendor GUID,
+    /// reads it back and removes it.
+    pub fn self_test(&mut self) -> Result<()> {
+        let var = VariableName::new_with_vendor(SELF_TEST_VARIABLE, SELF_TEST_VENDOR);
+        let value = format!("sdboot self-test {}", std::process::id());
+
+        #[cfg(target_os = "linux")]
+        let guard = self.prepare_write(&var)?;
+        write::write_utf16_string(&mut *self.inner, &var, entry_flags(), &value)
+            .context("Unable to write the scratch variable")?;
+        #[cfg(target_os = "linux")]
+        drop(guard);
+
+        let read_back = read::read_utf16_string(&*self.inner, &var)
+            .context("Unable to read the scratch variable back");
+
+        // Clean up whatever the read back shows.
+        #[cfg(target_os = "linux")]
+        self.delete_variable(&var)
+            .context("Unable to remove the scratch variable")?;
+        // On windows, to delete a variable one needs to set it to an empty
+        // (size = 0) value.
+        #[cfg(target_os = "windows")]
+        efivar::VarWriter::write(&mut *self.inner, &var, entry_flags(), &[])
+            .map_err(|e| crate::error::EfiError(e).into_anyhow())
+            .context("Unable to remove the scratch variable")?;
+
+        match read_back? {
+            Some((read_back, _flags)) if read_back == value => Ok(()),
+            Some((read_back, _flags)) => anyhow::bail!(
+                "The scratch variable reads back as {:?} instead of {:?}",
+                read_back,
+                value
+            ),
+            None => anyhow::bail!("The scratch variable is missing after writing it"),
+        }
+    }
+
+    /// Checks whether the oneshot entry points at one of the available
+    /// entries, e.g. it might not after a kernel has been removed.
+    ///
+    /// When `clear` is set, a stale oneshot entry is removed.
+    pub fn check_oneshot(&mut self, clear: bool) -> Result<OneshotCheck> {
+        let entry = match self.get_oneshot()? {
+            Some(entry) => entry,
+            None => return Ok(OneshotCheck::NotSet),
+        };
+        if self.entries()?.contains(&entry) {
+            return Ok(OneshotCheck::Valid(entry));
+        }
+        if clear {
+            self.remove_oneshot()
+                .with_context(|| format!("Unable to clear stale oneshot entry {}", entry))?;
+        }
+        Ok(OneshotCheck::Stale {
+            entry,
+            cleared: clear,
+        })
+    }
+
+    /// Compares the entries reported by the boot loader with the entries on
+    /// the disk.
+    pub fn compare_with_esp(&self, esp: &Esp) -> Result<Divergence> {
+        Ok(Divergence::new(&self.entries()?, &esp.entries()?))
+    }
+
+    /// Runs the consistency checks of the boot loader state, including the
+    /// entries on the disk if the ESP is given.
+    ///
+    /// The default entry configured in `loader.conf` is checked if the
+    /// `LoaderEntryDefault` variable is not set.
+    pub fn diagnose(&self, esp: Option<&Esp>) -> Result<Vec<Finding>> {
+        let loader_entries = self.entries()?;
+        let esp_entries = esp.map(Esp::entries).transpose()?;
+        let default = match self.get_default_entry()? {
+            Some(default) => Some(default),
+            None => esp.map(Esp::loader_default).transpose()?.flatten(),
+        };
+        let oneshot = self.get_oneshot()?;
+        Ok(diagnostics::check(&diagnostics::State {
+            loader_entries: Some(&loader_entries),
+            esp_entries: esp_entries.as_deref(),
+            default: default.as_deref(),
+            oneshot: oneshot.as_deref(),
+            #[cfg(target_os = "linux")]
+            read_only: crate::efivarfs::is_read_only(),
+            #[cfg(not(target_os = "linux"))]
+            read_only: false,
+        }))
+    }
+
+    /// Resolves the entry the boot loader boots next, see
+    /// [next_boot::resolve](crate::next_boot::resolve). The default entry of
+    /// `loader.conf` is considered only if the ESP is given.
+    pub fn next_boot(&self, esp: Option<&Esp>) -> Result<Option<NextBoot>> {
+        let entries = self.entries()?;
+        let loader_default = esp.map(Esp::loader_default).transpose()?.flatten();
+        Ok(crate::next_boot::resolve(
+            &entries,
+            self.get_oneshot()?.as_deref(),
+            self.get_default_entry()?.as_deref(),
+            loader_default.as_deref(),
+        ))
+    }
+
+    /// Sets the oneshot entry to the given (existing) entry and reboots.
+    pub fn reboot_into(&mut self, entry: &str) -> Result<(), RebootIntoError> {
+        let entries = self.entries().map_err(RebootIntoError::Entries)?;
+        if !entries.contains(entry) {
+            return Err(RebootIntoError::EntryNotFound(entry.to_string()));
+        }
+        self.set_oneshot(entry)
+            .map_err(RebootIntoError::SetOneshot)?;
+        log::info!("Oneshot entry set to {}, rebooting", entry);
+        crate::power::reboot().map_err(RebootIntoError::Reboot)
+    }
+
+    /// Fetches the available entries, see [Manager::with_entries_source].
+    pub fn entries(&self) -> Result<Entries> {
+        match self.entries_source {
+            EntriesSource::Loader => self.loader_entries(),
+            EntriesSource::Esp => esp_entries(),
+            EntriesSource::LoaderOrEsp => match self.loader_entries() {
+                Err(e) if ErrorKind::of(&e) == Some(ErrorKind::NotSystemdBoot) => {
+                    log::debug!("{:#}, reading the entries from the ESP", e);
+                    esp_entries().map_err(|esp_error| {
+                        log::debug!("{:#}", esp_error);
+                        e
+                    })
+                }
+                result => result,
+            },
+        }
+    }
+
+    /// Fetches the entries the boot loader reports.
+    fn loader_entries(&self) -> Result<Entries> {
         let (entries_bytes, _flags) = read::read_u16_bytes(
             &*self.inner,
             &VariableName::new_with_vendor(LOADER_ENTRIES_SHORT, SYSTEMD_BOOT_VENDOR),
         )?
+        .ok_or(ErrorKind::NotSystemdBoot)
         .with_context(|| format!(r#"Variable {} is not set"#, LOADER_ENTRIES_SHORT))?;
-        Ok(entries_bytes
-            .split(|&byte| byte == 0)
-            .take_while(|entry| {
-                // Entries must be non-empty.
-                !entry.is_empty()
+        Ok(crate::parse::loader_entries(&entries_bytes))
+    }
+
+    /// Lists the firmware boot manager options, in the boot order followed by
+    /// the ones missing from it. Undecodable options are skipped.
+    pub fn boot_options(&self) -> Result<Vec<BootOption>> {
+        let order = read::read_bytes(&*self.inner, &VariableName::new(BOOT_ORDER))?
+            .map(|(bytes, _flags)| {
+                bytes
+                    .chunks_exact(2)
+                    .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                    .collect::<Vec<_>>()
             })
-            .filter_map(|entry| match String::from_utf16(entry) {
-                Ok(value) => Some(value),
-                Err(_) => {
-                    log::warn!(
-                        "Discovered an invalid utf16 entry: '{}'; skipping it.",
-                        String::from_utf16_lossy(entry)
-                    );
-                    None
-                }
+            .unwrap_or_default();
+        let names = self
+            .inner
+            .get_var_names()
+            .map_err(|e| crate::error::EfiError(e).into_anyhow())
+            .context("Unable to enumerate the EFI variables")?
+            .filter(|name| *name == VariableName::new(name.short_name()))
+            .collect::<Vec<_>>();
+        let mut options = Vec::new();
+        for name in names {
+            let Some(number) = crate::parse::boot_option_number(name.short_name()) else {
+                continue;
+            };
+            let Some((bytes, _flags)) = read::read_bytes(&*self.inner, &name)? else {
+                continue;
+            };
+            match crate::parse::load_option(&bytes) {
+                Ok(option) => options.push(BootOption {
+                    number,
+                    active: option.is_active(),
+                    description: option.description,
+                }),
+                Err(e) => log::debug!("Skipping {}: {:#}", name, e),
+            }
+        }
+        options.sort_by_key(|option| {
+            (
+                order
+                    .iter()
+                    .position(|&number| number == option.number)
+                    .unwrap_or(order.len()),
+                option.number,
+            )
+        });
+        Ok(options)
+    }
+
+    /// Fetches the firmware boot manager option to boot once on the next
+    /// boot.
+    pub fn get_boot_next(&self) -> Result<Option<u16>> {
+        Ok(read::read_u16(&*self.inner, &VariableName::new(BOOT_NEXT))?.map(|(value, _)| value))
+    }
+
+    /// Asks the firmware to boot the given boot manager option once on the
+    /// next boot, bypassing the boot order.
+    pub fn set_boot_next(&mut self, number: u16) -> Result<()> {
+        let var = VariableName::new(BOOT_NEXT);
+        let old = self.get_boot_next().ok().flatten();
+
+        #[cfg(target_os = "linux")]
+        let guard = self.prepare_write(&var)?;
+
+        write::write_u16(&mut *self.inner, &var, entry_flags(), number)?;
+        self.report_change(
+            BOOT_NEXT,
+            old.map(|old| format!("Boot{:04X}", old)).as_deref(),
+            Some(&format!("Boot{:04X}", number)),
+        );
+        #[cfg(target_os = "linux")]
+        self.finish_write(&var, guard)?;
+        Ok(())
+    }
+
+    /// Removes the firmware boot manager option to boot once.
+    #[cfg(target_os = "linux")]
+    pub fn remove_boot_next(&mut self) -> Result<()> {
+        self.remove_variable(&VariableName::new(BOOT_NEXT))
+            .context("Unable to remove BootNext")
+    }
+
+    /// Removes the firmware boot manager option to boot once.
+    #[cfg(target_os = "windows")]
+    pub fn remove_boot_next(&mut self) -> Result<()> {
+        // On windows, to delete a variable one needs to set it to an empty
+        // (size = 0) value.
+        let var = VariableName::new(BOOT_NEXT);
+        efivar::VarWriter::write(&mut *self.inner, &var, entry_flags(), &[])
+            .map_err(|e| crate::error::EfiError(e).into_anyhow())
+            .context("Unable to remove BootNext")
+    }
+
+    /// Reads all the variables of the boot loader interface, i.e. the ones
+    /// with the systemd vendor UUID, sorted by name.
+    pub fn dump(&self) -> Result<Vec<crate::dump::Variable>> {
+        let names = self
+            .inner
+            .get_var_names()
+            .map_err(|e| crate::error::EfiError(e).into_anyhow())
+            .context("Unable to enumerate the EFI variables")?
+            .filter(|name| {
+                *name == VariableName::new_with_vendor(name.short_name(), SYSTEMD_BOOT_VENDOR)
             })
-            .collect())
+            .collect::<Vec<_>>();
+        let mut variables = Vec::with_capacity(names.len());
+        for name in names {
+            // The variable may vanish in the meantime.
+            if let Some((value, flags)) = read::read_bytes(&*self.inner, &name)? {
+                variables.push(crate::dump::Variable {
+                    name: name.short_name().to_string(),
+                    attributes: flags.bits(),
+                    value,
+                });
+            }
+        }
+        variables.sort_by(|left, right| left.name.cmp(&right.name));
+        Ok(variables)
     }
 }
 
+/// Reads the entries stored on the ESP, with their titles and versions.
+fn esp_entries() -> Result<Entries> {
+    let esp = Esp::discover().context("ESP not found")?;
+    Ok(esp
+        .entries()?
+        .into_iter()
+        .map(|esp_entry| {
+            let entry = Entry::new(esp_entry.id);
+            let entry = match esp_entry.title {
+                Some(title) => entry.with_title(title),
+                None => entry,
+            };
+            match esp_entry.version {
+                Some(version) => entry.with_version(version),
+                None => entry,
+            }
+        })
+        .collect())
+}
+
 impl Default for Manager {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,113 @@
+//! Which entry the boot loader picks on the next boot.
+
+use std::fmt;
+
+use crate::{parse::glob_match, Entries, Entry};
+
+/// Why the entry is booted next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum Reason {
+    /// It is the one shot entry.
+    Oneshot,
+    /// It matches the `LoaderEntryDefault` variable.
+    Default,
+    /// It matches the `default` of `loader.conf`.
+    LoaderConf,
+    /// Nothing else applies, so the boot loader picks the first entry.
+    FirstEntry,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Reason::Oneshot => "one shot entry",
+            Reason::Default => "default entry",
+            Reason::LoaderConf => "default of loader.conf",
+            Reason::FirstEntry => "first entry",
+        })
+    }
+}
+
+/// The entry booted next.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NextBoot {
+    /// The entry.
+    pub entry: Entry,
+    /// Why it is booted.
+    pub reason: Reason,
+}
+
+/// Resolves the entry the boot loader boots next the way systemd-boot does:
+/// the one shot entry, then the first entry matching the default pattern of
+/// the `LoaderEntryDefault` variable, then the one of `loader.conf`, then the
+/// first entry which isn't generated automatically.
+///
+/// A one shot entry or a pattern matching no entry is skipped, like the
+/// boot loader does. So is the "@saved" default, as the last booted entry
+/// isn't known. [None] if there are no entries.
+pub fn resolve(
+    entries: &Entries,
+    oneshot: Option<&str>,
+    default: Option<&str>,
+    loader_default: Option<&str>,
+) -> Option<NextBoot> {
+    let matching = |pattern: Option<&str>| {
+        let pattern = pattern.filter(|pattern| *pattern != "@saved")?;
+        entries.iter().find(|entry| glob_match(pattern, entry.id()))
+    };
+    let (entry, reason) = oneshot
+        .and_then(|oneshot| entries.get_by_id(oneshot))
+        .map(|entry| (entry, Reason::Oneshot))
+        .or_else(|| matching(default).map(|entry| (entry, Reason::Default)))
+        .or_else(|| matching(loader_default).map(|entry| (entry, Reason::LoaderConf)))
+        .or_else(|| {
+            entries
+                .iter()
+                .find(|entry| !entry.id().starts_with("auto-"))
+                .or_else(|| entries.iter().next())
+                .map(|entry| (entry, Reason::FirstEntry))
+        })?;
+    Some(NextBoot {
+        entry: entry.clone(),
+        reason,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_resolve() {
+        let entries: Entries = ["auto-windows", "arch.conf", "arch-lts.conf"]
+            .into_iter()
+            .map(Entry::new)
+            .collect();
+        let next = |oneshot, default, loader_default| {
+            resolve(&entries, oneshot, default, loader_default)
+                .map(|next| (next.entry.id().to_string(), next.reason))
+        };
+        assert_eq!(
+            next(Some("arch-lts.conf"), Some("arch.conf"), None),
+            Some(("arch-lts.conf".into(), Reason::Oneshot))
+        );
+        assert_eq!(
+            next(Some("gone.conf"), Some("arch*"), Some("auto-windows")),
+            Some(("arch.conf".into(), Reason::Default))
+        );
+        assert_eq!(
+            next(None, Some("@saved"), Some("auto-*")),
+            Some(("auto-windows".into(), Reason::LoaderConf))
+        );
+        assert_eq!(
+            next(None, Some("nothing"), None),
+            Some(("arch.conf".into(), Reason::FirstEntry))
+        );
+        assert_eq!(resolve(&Entries::default(), None, None, None), None);
+    }
+}
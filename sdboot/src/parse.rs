@@ -0,0 +1,465 @@
+//! Parsing and encoding of the EFI variables contents.
+//!
+//! Nothing in this module talks to the operating system, so it can be used to
+//! analyze captured variable dumps offline.
+
+use std::cmp::Ordering;
+
+use anyhow::{Context, Result};
+
+use crate::{Entries, Entry, LoaderFeatures};
+
+/// Splits the `LoaderEntries` variable contents into the entries.
+///
+/// Entries which are not valid UTF-16 are skipped with a warning.
+pub fn loader_entries(words: &[u16]) -> Entries {
+    words
+        .split(|&word| word == 0)
+        .take_while(|entry| {
+            // Entries must be non-empty.
+            !entry.is_empty()
+        })
+        .filter_map(|entry| match String::from_utf16(entry) {
+            Ok(value) => Some(Entry::new(value)),
+            Err(_) => {
+                log::warn!(
+                    "Discovered an invalid utf16 entry: '{}'; skipping it.",
+                    String::from_utf16_lossy(entry)
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// Decodes a UTF-16 string, trimming the terminating null if present.
+pub fn utf16_string(words: &[u16]) -> Result<String> {
+    let words = if let [text @ .., 0] = words {
+        // Null-terminated string detected. Trim away the final null!
+        text
+    } else {
+        // Convert the bytes as-is.
+        words
+    };
+    String::from_utf16(words)
+        .with_context(|| format!("Non-UTF16 value: {}", String::from_utf16_lossy(words)))
+}
+
+/// Encodes the string as null-terminated little-endian UTF-16 bytes.
+///
+/// The terminating null is not duplicated if the string already ends with it.
+pub fn encode_utf16(value: &str) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for wide_char in value.encode_utf16() {
+        let [first, second] = wide_char.to_le_bytes();
+        if first == 0 && second == 0 {
+            log::warn!("Intermediate null byte in {:?}", value);
+        }
+        buffer.push(first);
+        buffer.push(second);
+    }
+    if let [.., 0, 0] = &buffer[..] {
+        // Already null-terminated. No need to add trailing zeroes.
+    } else {
+        // Not null-terminated. Add trailing zeroes!
+        buffer.extend_from_slice(&[0, 0]);
+    }
+    buffer
+}
+
+/// Splits the bytes into little-endian UTF-16 code units. [None] if the
+/// number of bytes is odd.
+pub fn u16_le_words(bytes: &[u8]) -> Option<Vec<u16>> {
+    (bytes.len() % 2 == 0).then(|| {
+        bytes
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect()
+    })
+}
+
+/// Decodes a little-endian [u64] from the beginning of the bytes.
+pub fn u64_le(bytes: &[u8]) -> Result<u64> {
+    let bytes: [u8; 8] = bytes
+        .get(..8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .context("Value is too short for a 64-bit integer")?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Decodes the `LoaderFeatures` variable contents.
+pub fn loader_features(bytes: &[u8]) -> Result<LoaderFeatures> {
+    u64_le(bytes).map(LoaderFeatures::from_bits)
+}
+
+/// Boot counters encoded in an entry file name, e.g. `+3-1` in
+/// `linux+3-1.conf`.
+///
+/// See https://systemd.io/AUTOMATIC_BOOT_ASSESSMENT/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootCounter {
+    /// Number of boot attempts left before the entry is considered bad.
+    pub tries_left: u32,
+    /// Number of failed boot attempts so far.
+    pub tries_done: Option<u32>,
+}
+
+/// Splits the boot counter off an entry file name.
+///
+/// Returns the file name without the counter (but with the extension, if
+/// any), which is how the boot loader identifies the entry.
+pub fn split_boot_counter(file_name: &str) -> (String, Option<BootCounter>) {
+    let (stem, extension) = match file_name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, Some(extension)),
+        None => (file_name, None),
+    };
+    let with_extension = |stem: &str| match extension {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem.to_string(),
+    };
+
+    let Some((base, counter)) = stem.rsplit_once('+') else {
+        return (file_name.to_string(), None);
+    };
+    let (left, done) = match counter.split_once('-') {
+        Some((left, done)) => (left, Some(done)),
+        None => (counter, None),
+    };
+    let is_number = |value: &str| !value.is_empty() && value.bytes().all(|c| c.is_ascii_digit());
+    if !is_number(left) || !done.map_or(true, is_number) {
+        return (file_name.to_string(), None);
+    }
+    let (Ok(tries_left), Ok(tries_done)) = (left.parse(), done.map(str::parse).transpose()) else {
+        return (file_name.to_string(), None);
+    };
+    (
+        with_extension(base),
+        Some(BootCounter {
+            tries_left,
+            tries_done,
+        }),
+    )
+}
+
+/// Matches the text against a shell-style glob pattern, supporting `*`, `?`
+/// and `[...]` character classes, like the boot loader does for the default
+/// entry.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let text = text.chars().collect::<Vec<_>>();
+    glob_match_chars(&pattern, &text)
+}
+
+fn glob_match_chars(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob_match_chars(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && glob_match_chars(rest, &text[1..]),
+        Some(('[', rest)) => {
+            let Some(end) = rest
+                .iter()
+                .skip(1)
+                .position(|&c| c == ']')
+                .map(|end| end + 1)
+            else {
+                // No closing bracket: match the bracket literally.
+                return text.first() == Some(&'[') && glob_match_chars(rest, &text[1..]);
+            };
+            let (class, rest) = (&rest[..end], &rest[end + 1..]);
+            let (negated, class) = match class.split_first() {
+                Some(('!' | '^', class)) => (true, class),
+                _ => (false, class),
+            };
+            let Some((&c, text)) = text.split_first() else {
+                return false;
+            };
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == '-' {
+                    matched |= class[i] <= c && c <= class[i + 2];
+                    i += 3;
+                } else {
+                    matched |= class[i] == c;
+                    i += 1;
+                }
+            }
+            matched != negated && glob_match_chars(rest, text)
+        }
+        Some((&literal, rest)) => {
+            text.first() == Some(&literal) && glob_match_chars(rest, &text[1..])
+        }
+    }
+}
+
+/// Checks whether the characters of the pattern appear in the text in the
+/// same order, ignoring the case, e.g. "afb" matches "arch-fallback".
+pub fn fuzzy_match(pattern: &str, text: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    pattern
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|c| text.any(|t| t == c))
+}
+
+/// Compares versions like "6.5.3-arch1-1" the way the boot loader sorts the
+/// entries: segment by segment, the digit runs numerically, ignoring the
+/// separators. Numbers are newer than letters, and a "~" marks a
+/// pre-release, older than anything else, e.g. "1.0~rc1" < "1.0".
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let is_separator = |c: char| !c.is_ascii_alphanumeric() && c != '~';
+    let (mut a, mut b) = (a, b);
+    loop {
+        a = a.trim_start_matches(is_separator);
+        b = b.trim_start_matches(is_separator);
+        match (a.chars().next(), b.chars().next()) {
+            (Some('~'), Some('~')) => {
+                a = &a[1..];
+                b = &b[1..];
+                continue;
+            }
+            (Some('~'), _) => return Ordering::Less,
+            (_, Some('~')) => return Ordering::Greater,
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(_), Some(_)) => {}
+        }
+
+        let a_numeric = a.starts_with(|c: char| c.is_ascii_digit());
+        let b_numeric = b.starts_with(|c: char| c.is_ascii_digit());
+        if a_numeric != b_numeric {
+            return a_numeric.cmp(&b_numeric);
+        }
+        let (a_segment, a_rest) = split_segment(a, a_numeric);
+        let (b_segment, b_rest) = split_segment(b, a_numeric);
+        let ordering = if a_numeric {
+            let a_segment = a_segment.trim_start_matches('0');
+            let b_segment = b_segment.trim_start_matches('0');
+            a_segment
+                .len()
+                .cmp(&b_segment.len())
+                .then_with(|| a_segment.cmp(b_segment))
+        } else {
+            a_segment.cmp(b_segment)
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        a = a_rest;
+        b = b_rest;
+    }
+}
+
+/// Splits the leading run of digits (or letters) off the version.
+fn split_segment(version: &str, numeric: bool) -> (&str, &str) {
+    let end = version
+        .find(|c: char| c.is_ascii_digit() != numeric || !c.is_ascii_alphanumeric())
+        .unwrap_or(version.len());
+    version.split_at(end)
+}
+
+/// A decoded `EFI_LOAD_OPTION`, the contents of a `Boot####` variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadOption {
+    /// The load option attributes (`LOAD_OPTION_ACTIVE` and friends).
+    pub attributes: u32,
+    /// The human-readable description shown by the firmware.
+    pub description: String,
+    /// The raw device path list.
+    pub file_path_list: Vec<u8>,
+    /// The optional data passed to the loaded image.
+    pub optional_data: Vec<u8>,
+}
+
+/// cbindgen:ignore
+impl LoadOption {
+    /// The load option is active, i.e. the firmware will try to boot it.
+    pub const ACTIVE: u32 = 0x1;
+
+    /// Checks whether the load option is active.
+    pub fn is_active(&self) -> bool {
+        self.attributes & Self::ACTIVE != 0
+    }
+}
+
+/// Parses the number of a boot option from its variable name, e.g. 3 from
+/// "Boot0003".
+pub fn boot_option_number(name: &str) -> Option<u16> {
+    let digits = name.strip_prefix("Boot")?;
+    if digits.len() != 4 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u16::from_str_radix(digits, 16).ok()
+}
+
+/// Decodes an `EFI_LOAD_OPTION` structure.
+pub fn load_option(bytes: &[u8]) -> Result<LoadOption> {
+    let header = bytes
+        .get(..6)
+        .context("Load option is too short for its header")?;
+    let attributes = u32::from_le_bytes([header[0], header[1], header[2], header[3]]);
+    let file_path_list_length = usize::from(u16::from_le_bytes([header[4], header[5]]));
+
+    let rest = &bytes[6..];
+    let description_words = rest
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|&word| word != 0)
+        .collect::<Vec<_>>();
+    // The description, plus the terminating null.
+    let description_length = (description_words.len() + 1) * 2;
+    let description = String::from_utf16(&description_words)
+        .context("Load option description is not valid UTF-16")?;
+    let rest = rest
+        .get(description_length..)
+        .context("Load option description is not null-terminated")?;
+
+    let file_path_list = rest
+        .get(..file_path_list_length)
+        .context("Load option device path list is truncated")?;
+    let optional_data = &rest[file_path_list_length..];
+    Ok(LoadOption {
+        attributes,
+        description,
+        file_path_list: file_path_list.to_vec(),
+        optional_data: optional_data.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn utf16(value: &str) -> Vec<u16> {
+        value.encode_utf16().collect()
+    }
+
+    #[test]
+    fn check_loader_entries() {
+        let words = utf16("arch.conf\0auto-windows\0\0garbage\0");
+        let entries = loader_entries(&words);
+        assert_eq!(
+            entries.ids().collect::<Vec<_>>(),
+            ["arch.conf", "auto-windows"]
+        );
+
+        let mut words = utf16("arch.conf\0");
+        // Lone surrogate.
+        words.extend([0xD800, 0, b'x'.into(), 0]);
+        assert_eq!(
+            loader_entries(&words).ids().collect::<Vec<_>>(),
+            ["arch.conf", "x"]
+        );
+
+        assert!(loader_entries(&[]).is_empty());
+    }
+
+    #[test]
+    fn check_utf16() {
+        assert_eq!(utf16_string(&utf16("entry\0")).unwrap(), "entry");
+        assert_eq!(utf16_string(&utf16("entry")).unwrap(), "entry");
+        assert!(utf16_string(&[0xD800]).is_err());
+
+        assert_eq!(encode_utf16("ab"), [b'a', 0, b'b', 0, 0, 0]);
+        assert_eq!(encode_utf16("ab\0"), [b'a', 0, b'b', 0, 0, 0]);
+        assert_eq!(encode_utf16(""), [0, 0]);
+    }
+
+    #[test]
+    fn check_features() {
+        let features = loader_features(&[0b1000, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+        assert!(features.contains(LoaderFeatures::ENTRY_ONESHOT));
+        assert!(!features.contains(LoaderFeatures::ENTRY_DEFAULT));
+        assert!(loader_features(&[0; 7]).is_err());
+    }
+
+    #[test]
+    fn check_boot_option_number() {
+        assert_eq!(boot_option_number("Boot0003"), Some(3));
+        assert_eq!(boot_option_number("Boot001F"), Some(0x1f));
+        assert_eq!(boot_option_number("BootOrder"), None);
+        assert_eq!(boot_option_number("BootNext"), None);
+        assert_eq!(boot_option_number("Boot+123"), None);
+    }
+
+    #[test]
+    fn check_boot_counter() {
+        assert_eq!(
+            split_boot_counter("arch+3-1.conf"),
+            (
+                "arch.conf".to_string(),
+                Some(BootCounter {
+                    tries_left: 3,
+                    tries_done: Some(1)
+                })
+            )
+        );
+        assert_eq!(
+            split_boot_counter("linux+2.efi"),
+            (
+                "linux.efi".to_string(),
+                Some(BootCounter {
+                    tries_left: 2,
+                    tries_done: None
+                })
+            )
+        );
+        assert_eq!(
+            split_boot_counter("c++.conf"),
+            ("c++.conf".to_string(), None)
+        );
+        assert_eq!(
+            split_boot_counter("arch+x-1.conf"),
+            ("arch+x-1.conf".to_string(), None)
+        );
+        assert_eq!(split_boot_counter("arch"), ("arch".to_string(), None));
+    }
+
+    #[test]
+    fn check_glob() {
+        assert!(glob_match("arch*", "arch-lts.conf"));
+        assert!(glob_match("*.conf", "arch.conf"));
+        assert!(!glob_match("*.conf", "arch.efi"));
+        assert!(glob_match("arch?.conf", "arch1.conf"));
+        assert!(glob_match("arch[0-9].conf", "arch7.conf"));
+        assert!(!glob_match("arch[!0-9].conf", "arch7.conf"));
+        assert!(glob_match("a[]", "a[]"));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exactly"));
+
+        assert!(fuzzy_match("arch-fb", "arch-fallback.conf"));
+        assert!(fuzzy_match("LTS", "arch-lts.conf"));
+        assert!(fuzzy_match("", "arch.conf"));
+        assert!(!fuzzy_match("fallback-arch", "arch-fallback.conf"));
+    }
+
+    #[test]
+    fn check_compare_versions() {
+        assert_eq!(compare_versions("6.10.1", "6.9.12"), Ordering::Greater);
+        assert_eq!(
+            compare_versions("6.5.3-arch1-1", "6.5.3-arch2-1"),
+            Ordering::Less
+        );
+        assert_eq!(compare_versions("1.0~rc1", "1.0"), Ordering::Less);
+        assert_eq!(compare_versions("1.0", "1.0.1"), Ordering::Less);
+        assert_eq!(compare_versions("1.010", "1.10"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0a", "1.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.a", "1.1"), Ordering::Less);
+    }
+
+    #[test]
+    fn check_load_option() {
+        let mut bytes = vec![1, 0, 0, 0, 2, 0];
+        bytes.extend(encode_utf16("Linux"));
+        bytes.extend([0x7f, 0xff, 0xaa]);
+        let option = load_option(&bytes).unwrap();
+        assert!(option.is_active());
+        assert_eq!(option.description, "Linux");
+        assert_eq!(option.file_path_list, [0x7f, 0xff]);
+        assert_eq!(option.optional_data, [0xaa]);
+
+        assert!(load_option(&bytes[..4]).is_err());
+        assert!(load_option(&bytes[..bytes.len() - 2]).is_err());
+    }
+}
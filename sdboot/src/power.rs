@@ -0,0 +1,100 @@
+//! System power management.
+
+use anyhow::{Context, Result};
+
+/// Calls a method of the logind manager object over D-Bus.
+#[cfg(target_os = "linux")]
+pub(crate) fn logind_call(method: &str, signature: &str, args: &[&str]) -> Result<()> {
+    let output = std::process::Command::new("busctl")
+        .args([
+            "call",
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+            method,
+            signature,
+        ])
+        .args(args)
+        .output()
+        .context("Unable to run busctl")?;
+    anyhow::ensure!(
+        output.status.success(),
+        "logind call {} failed ({}): {}",
+        method,
+        output.status,
+        String::from_utf8_lossy(&output.stderr).trim()
+    );
+    Ok(())
+}
+
+/// Reboots the system via logind.
+#[cfg(target_os = "linux")]
+pub fn reboot() -> Result<()> {
+    logind_call("Reboot", "b", &["false"]).context("Unable to reboot via logind")
+}
+
+/// Reboots the system.
+#[cfg(target_os = "windows")]
+pub fn reboot() -> Result<()> {
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, LUID},
+        Security::{
+            AdjustTokenPrivileges, LookupPrivilegeValueW, LUID_AND_ATTRIBUTES,
+            SE_PRIVILEGE_ENABLED, SE_SHUTDOWN_NAME, TOKEN_ADJUST_PRIVILEGES, TOKEN_PRIVILEGES,
+            TOKEN_QUERY,
+        },
+        System::{
+            Shutdown::{ExitWindowsEx, EWX_REBOOT, SHTDN_REASON_FLAG_PLANNED},
+            Threading::{GetCurrentProcess, OpenProcessToken},
+        },
+    };
+
+    // The shutdown privilege is held by administrators, but it must be enabled
+    // explicitly before calling ExitWindowsEx.
+    let mut privileges = TOKEN_PRIVILEGES {
+        PrivilegeCount: 1,
+        Privileges: [LUID_AND_ATTRIBUTES {
+            Luid: LUID {
+                LowPart: 0,
+                HighPart: 0,
+            },
+            Attributes: SE_PRIVILEGE_ENABLED,
+        }],
+    };
+    // Safety: all the pointers are valid for the duration of the calls, and
+    // the token handle is closed right after use.
+    unsafe {
+        let mut token = 0;
+        if OpenProcessToken(
+            GetCurrentProcess(),
+            TOKEN_ADJUST_PRIVILEGES | TOKEN_QUERY,
+            &mut token,
+        ) == 0
+        {
+            return Err(std::io::Error::last_os_error()).context("Unable to open process token");
+        }
+        let adjusted = LookupPrivilegeValueW(
+            std::ptr::null(),
+            SE_SHUTDOWN_NAME,
+            &mut privileges.Privileges[0].Luid,
+        ) != 0
+            && AdjustTokenPrivileges(
+                token,
+                0,
+                &privileges,
+                0,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+            ) != 0;
+        let error = std::io::Error::last_os_error();
+        CloseHandle(token);
+        if !adjusted {
+            return Err(error).context("Unable to acquire the shutdown privilege");
+        }
+
+        if ExitWindowsEx(EWX_REBOOT, SHTDN_REASON_FLAG_PLANNED) == 0 {
+            return Err(std::io::Error::last_os_error()).context("Unable to reboot");
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,363 @@
+//! Figuring out which privilege is missing when a variable can't be changed.
+//!
+//! A bare "permission denied" doesn't tell much to people unfamiliar with the
+//! EFI variables internals, so on failures the process privileges are probed
+//! to name the exact one lacking.
+
+use std::fmt;
+
+use efivar::efi::VariableName;
+
+use crate::ErrorKind;
+
+/// A privilege required to change the EFI variables, which the process
+/// doesn't hold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissingPrivilege {
+    /// The process is not running as root.
+    NotRoot,
+    /// The `CAP_SYS_ADMIN` capability is not effective, e.g. it has been
+    /// dropped by a container runtime or a service manager. It is required to
+    /// change the "immutable" attribute of the variable files.
+    CapSysAdmin,
+    /// The efivarfs file of the variable is not writable.
+    FileNotWritable(std::path::PathBuf),
+    /// The `SeSystemEnvironmentPrivilege` privilege is not held by the
+    /// process, i.e. it doesn't run as an administrator.
+    SystemEnvironment,
+    /// The process is privileged, but a Linux security module (SELinux,
+    /// AppArmor) is enforcing a policy, which most likely denies the access.
+    DeniedByPolicy {
+        /// The enforcing security module.
+        module: SecurityModule,
+        /// The matching denial from the audit log, if found.
+        denial: Option<String>,
+    },
+}
+
+/// A Linux security module enforcing a policy.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityModule {
+    /// SELinux in the enforcing mode.
+    SELinux,
+    /// AppArmor, with the process confined by a profile.
+    AppArmor {
+        /// The confining profile.
+        profile: String,
+    },
+}
+
+impl fmt::Display for MissingPrivilege {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MissingPrivilege::NotRoot => {
+                f.write_str("Not running as root, try running the command with sudo")
+            }
+            MissingPrivilege::CapSysAdmin => f.write_str(
+                "The CAP_SYS_ADMIN capability is missing, it is required to change the \
+                 \"immutable\" attribute of the variable files",
+            ),
+            MissingPrivilege::FileNotWritable(path) => {
+                write!(f, "The variable file {} is not writable", path.display())
+            }
+            MissingPrivilege::SystemEnvironment => f.write_str(
+                "The SeSystemEnvironmentPrivilege privilege is not held, \
+                 try running the program as an administrator",
+            ),
+            MissingPrivilege::DeniedByPolicy { module, denial } => {
+                match module {
+                    SecurityModule::SELinux => f.write_str(
+                        "SELinux is enforcing and its policy denies access to efivarfs, \
+                         check the denials with `ausearch -m avc -ts recent` and adjust \
+                         the policy (e.g. with audit2allow)",
+                    )?,
+                    SecurityModule::AppArmor { profile } => write!(
+                        f,
+                        "The process is confined by the AppArmor profile {:?}, which denies \
+                         access to efivarfs, check the denials with \
+                         `journalctl -k --grep 'apparmor=\"DENIED\"'` and adjust the profile",
+                        profile
+                    )?,
+                }
+                match denial {
+                    Some(denial) => write!(f, "; the denial: {}", denial),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl std::error::Error for MissingPrivilege {}
+
+/// Checks whether the error is caused by insufficient permissions.
+fn is_permission_error(error: &anyhow::Error) -> bool {
+    ErrorKind::of(error) == Some(ErrorKind::PermissionDenied)
+        || error.chain().any(|cause| {
+            if let Some(error) = cause.downcast_ref::<std::io::Error>() {
+                return error.kind() == std::io::ErrorKind::PermissionDenied;
+            }
+            #[cfg(target_os = "linux")]
+            if let Some(errno) = cause.downcast_ref::<nix::errno::Errno>() {
+                return matches!(errno, nix::errno::Errno::EPERM | nix::errno::Errno::EACCES);
+            }
+            false
+        })
+}
+
+/// Returns the OS error code of the error, if it is known. The efivar
+/// permission errors don't keep it.
+#[cfg(target_os = "linux")]
+fn errno(error: &anyhow::Error) -> Option<i32> {
+    error.chain().find_map(|cause| {
+        if let Some(error) = cause.downcast_ref::<std::io::Error>() {
+            return error.raw_os_error();
+        }
+        cause
+            .downcast_ref::<nix::errno::Errno>()
+            .map(|errno| *errno as i32)
+    })
+}
+
+/// Attaches the missing privilege to a permission error occurred while
+/// changing the variable. Other errors are returned as is.
+pub(crate) fn explain(error: anyhow::Error, var: &VariableName) -> anyhow::Error {
+    if !is_permission_error(&error) {
+        return error;
+    }
+    #[cfg(target_os = "linux")]
+    let missing = missing(var, errno(&error));
+    #[cfg(target_os = "windows")]
+    let missing = missing(var);
+    match missing {
+        Some(privilege) => error.context(privilege),
+        None if ErrorKind::of(&error).is_none() => error.context(ErrorKind::PermissionDenied),
+        None => error,
+    }
+}
+
+/// Probes the privileges needed to change the variable, given the error code
+/// of the failure, if known.
+///
+/// A security module is blamed only for EACCES (or an unknown code), and only
+/// if the audit log has a denial for this very process: SELinux is enforcing
+/// on most Fedora and RHEL installs, and it's not the cause of every failure.
+#[cfg(target_os = "linux")]
+fn missing(var: &VariableName, errno: Option<i32>) -> Option<MissingPrivilege> {
+    use nix::unistd::AccessFlags;
+
+    if !nix::unistd::geteuid().is_root() {
+        return Some(MissingPrivilege::NotRoot);
+    }
+    match std::fs::read_to_string("/proc/self/status") {
+        Ok(status) => {
+            if effective_capabilities(&status).map(|caps| caps & CAP_SYS_ADMIN == 0) == Some(true) {
+                return Some(MissingPrivilege::CapSysAdmin);
+            }
+        }
+        Err(e) => log::debug!("Unable to read the process status: {:#}", e),
+    }
+    let path = crate::efivarfs::variable_path(var);
+    let writable = nix::unistd::access(&path, AccessFlags::W_OK);
+    // EPERM comes from the "immutable" attribute, not from the policies.
+    if writable == Err(nix::errno::Errno::EPERM) {
+        return Some(MissingPrivilege::FileNotWritable(path));
+    }
+    if matches!(errno, None | Some(libc::EACCES)) {
+        if let Some(module) = enforcing_security_module() {
+            let pid = std::process::id();
+            let denial = std::fs::read_to_string(AUDIT_LOG)
+                .ok()
+                .and_then(|log| find_denial(&log, pid).map(str::to_string));
+            match denial {
+                Some(denial) => {
+                    return Some(MissingPrivilege::DeniedByPolicy {
+                        module,
+                        denial: Some(denial),
+                    })
+                }
+                None => log::debug!(
+                    "{:?} is enforcing, but no denial for the process {} is logged",
+                    module,
+                    pid
+                ),
+            }
+        }
+    }
+    match writable {
+        Err(nix::errno::Errno::EACCES) => Some(MissingPrivilege::FileNotWritable(path)),
+        _ => None,
+    }
+}
+
+/// `CAP_SYS_ADMIN` bit in the capabilities mask, see `linux/capability.h`.
+#[cfg(target_os = "linux")]
+const CAP_SYS_ADMIN: u64 = 1 << 21;
+
+/// Parses the effective capabilities mask (`CapEff`) out of the contents of
+/// `/proc/<pid>/status`.
+#[cfg(target_os = "linux")]
+fn effective_capabilities(status: &str) -> Option<u64> {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+}
+
+/// Detects a security module which may deny root access to efivarfs.
+#[cfg(target_os = "linux")]
+fn enforcing_security_module() -> Option<SecurityModule> {
+    let read = |path: &str| std::fs::read_to_string(path).ok();
+    if read("/sys/fs/selinux/enforce").as_deref().map(str::trim) == Some("1") {
+        return Some(SecurityModule::SELinux);
+    }
+    if read("/sys/module/apparmor/parameters/enabled")
+        .as_deref()
+        .map(str::trim)
+        == Some("Y")
+    {
+        let label =
+            read("/proc/self/attr/apparmor/current").or_else(|| read("/proc/self/attr/current"))?;
+        return apparmor_profile(&label).map(|profile| SecurityModule::AppArmor { profile });
+    }
+    None
+}
+
+/// Extracts the confining profile from an AppArmor label, like
+/// `/usr/bin/sdboot-cli (enforce)`. [None] means the process is not confined.
+#[cfg(target_os = "linux")]
+fn apparmor_profile(label: &str) -> Option<String> {
+    let label = label.trim_end_matches(['\n', '\0']).trim();
+    // Anything else is either "unconfined" or a profile in the complain mode,
+    // which doesn't deny anything.
+    label.strip_suffix(" (enforce)").map(str::to_string)
+}
+
+/// The audit daemon log.
+#[cfg(target_os = "linux")]
+const AUDIT_LOG: &str = "/var/log/audit/audit.log";
+
+/// Finds the latest denial related to efivarfs for the process in the audit
+/// log.
+#[cfg(target_os = "linux")]
+fn find_denial(log: &str, pid: u32) -> Option<&str> {
+    let pid = format!("pid={}", pid);
+    log.lines().rev().take(1000).find(|line| {
+        (line.contains("avc:  denied") || line.contains("apparmor=\"DENIED\""))
+            && line.contains("efivar")
+            && line.split_whitespace().any(|field| field == pid)
+    })
+}
+
+/// Probes the privileges needed to change the variable.
+#[cfg(target_os = "windows")]
+fn missing(_var: &VariableName) -> Option<MissingPrivilege> {
+    match holds_system_environment_privilege() {
+        Ok(true) => None,
+        Ok(false) => Some(MissingPrivilege::SystemEnvironment),
+        Err(e) => {
+            log::debug!("Unable to check the process privileges: {:#}", e);
+            None
+        }
+    }
+}
+
+/// Checks whether the process token has `SeSystemEnvironmentPrivilege`
+/// (enabled or not, efivar takes care of enabling it).
+#[cfg(target_os = "windows")]
+fn holds_system_environment_privilege() -> anyhow::Result<bool> {
+    use anyhow::Context;
+    use windows_sys::Win32::{
+        Foundation::{CloseHandle, LUID},
+        Security::{
+            GetTokenInformation, LookupPrivilegeValueW, TokenPrivileges,
+            SE_SYSTEM_ENVIRONMENT_NAME, TOKEN_PRIVILEGES, TOKEN_QUERY,
+        },
+        System::Threading::{GetCurrentProcess, OpenProcessToken},
+    };
+
+    let mut luid = LUID {
+        LowPart: 0,
+        HighPart: 0,
+    };
+    // Safety: all the pointers are valid for the duration of the calls, the
+    // buffer is large enough and suitably aligned for TOKEN_PRIVILEGES, and
+    // the token handle is closed right after use.
+    unsafe {
+        if LookupPrivilegeValueW(std::ptr::null(), SE_SYSTEM_ENVIRONMENT_NAME, &mut luid) == 0 {
+            return Err(std::io::Error::last_os_error())
+                .context("Unable to look up the system environment privilege");
+        }
+
+        let mut token = 0;
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return Err(std::io::Error::last_os_error()).context("Unable to open process token");
+        }
+        let mut size = 0;
+        GetTokenInformation(token, TokenPrivileges, std::ptr::null_mut(), 0, &mut size);
+        let mut buffer = vec![0u32; (size as usize).div_ceil(std::mem::size_of::<u32>())];
+        let queried = GetTokenInformation(
+            token,
+            TokenPrivileges,
+            buffer.as_mut_ptr().cast(),
+            size,
+            &mut size,
+        ) != 0;
+        let error = std::io::Error::last_os_error();
+        CloseHandle(token);
+        if !queried {
+            return Err(error).context("Unable to query the token privileges");
+        }
+
+        let privileges = buffer.as_ptr().cast::<TOKEN_PRIVILEGES>();
+        let privileges = std::slice::from_raw_parts(
+            (*privileges).Privileges.as_ptr(),
+            (*privileges).PrivilegeCount as usize,
+        );
+        Ok(privileges.iter().any(|privilege| {
+            privilege.Luid.LowPart == luid.LowPart && privilege.Luid.HighPart == luid.HighPart
+        }))
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_effective_capabilities() {
+        let status = "Name:\tsdboot\nCapInh:\t0000000000000000\nCapPrm:\t000001ffffffffff\n\
+                      CapEff:\t000001ffffffffff\nCapBnd:\t000001ffffffffff\n";
+        let caps = effective_capabilities(status).unwrap();
+        assert_ne!(caps & CAP_SYS_ADMIN, 0);
+
+        let status = "CapEff:\t0000000000000000\n";
+        assert_eq!(effective_capabilities(status), Some(0));
+
+        assert_eq!(effective_capabilities("Name:\tsdboot\n"), None);
+    }
+
+    #[test]
+    fn check_apparmor_profile() {
+        assert_eq!(
+            apparmor_profile("/usr/bin/sdboot-cli (enforce)\n"),
+            Some("/usr/bin/sdboot-cli".to_string())
+        );
+        assert_eq!(apparmor_profile("unconfined\n"), None);
+        assert_eq!(apparmor_profile("sdboot (complain)"), None);
+    }
+
+    #[test]
+    fn check_find_denial() {
+        let log = "type=AVC msg=audit(1.0:1): avc:  denied  { write } for pid=1 comm=\"sshd\" \
+                   name=\"authorized_keys\"\n\
+                   type=AVC msg=audit(2.0:2): avc:  denied  { write } for pid=2 \
+                   comm=\"sdboot-cli\" scontext=system_u:system_r:sdboot_t:s0 \
+                   tcontext=system_u:object_r:efivarfs_t:s0 tclass=file\n\
+                   type=SYSCALL msg=audit(2.0:2): arch=c000003e syscall=2\n";
+        assert!(find_denial(log, 2).unwrap().contains("comm=\"sdboot-cli\""));
+        // Someone else's denial.
+        assert_eq!(find_denial(log, 3), None);
+        assert_eq!(find_denial("type=SYSCALL msg=audit(2.0:2)\n", 2), None);
+    }
+}
@@ -30,6 +30,27 @@ pub fn read_u16_bytes<T: VarReader + ?Sized>(
     var_manager: &T,
     name: &VariableName,
 ) -> Result<Option<(Vec<u16>, VariableFlags)>> {
+    Ok(read_raw(var_manager, name)?.map(|(buffer, _length, flags)| (buffer, flags)))
+}
+
+/// Reads the value of the given EFI variable as is.
+pub fn read_bytes<T: VarReader + ?Sized>(
+    var_manager: &T,
+    name: &VariableName,
+) -> Result<Option<(Vec<u8>, VariableFlags)>> {
+    Ok(read_raw(var_manager, name)?.map(|(buffer, length, flags)| {
+        let mut bytes = buffer.as_u8().to_vec();
+        bytes.truncate(length);
+        (bytes, flags)
+    }))
+}
+
+/// Reads the value of the given EFI variable into a vector over [u16],
+/// returning the length of the value in bytes along with it.
+fn read_raw<T: VarReader + ?Sized>(
+    var_manager: &T,
+    name: &VariableName,
+) -> Result<Option<(Vec<u16>, usize, VariableFlags)>> {
     // 8 MBs (when applied to u16).
     const MAX_BUFFER: usize = 8 * 512 * 1024;
 
@@ -42,7 +63,7 @@ pub fn read_u16_bytes<T: VarReader + ?Sized>(
                 // twice as short. If read odd number of bytes, add an extra u16
                 // on top of the halved value.
                 buffer.resize(divide_up(length, 2), 0);
-                break Ok(Some((buffer, flags)));
+                break Ok(Some((buffer, length, flags)));
             }
             Err(efivar::Error::VarNotFound { .. }) => break Ok(None),
             #[cfg(target_os = "windows")]
@@ -63,7 +84,7 @@ pub fn read_u16_bytes<T: VarReader + ?Sized>(
                 buffer.resize(buffer.len() * 2, 0);
             }
             Err(e) => {
-                break Err(crate::error::EfiError(e))
+                break Err(crate::error::EfiError(e).into_anyhow())
                     .with_context(|| format!("Reading variable {}", name))
             }
         }
@@ -79,15 +100,53 @@ pub fn read_utf16_string<T: VarReader + ?Sized>(
         Some(data) => data,
         None => return Ok(None),
     };
-    let bytes = bytes.as_slice();
-    let bytes = if let [text @ .., 0] = bytes {
-        // Null-terminated string detected. Trim away the final null!
-        text
-    } else {
-        // Convert the bytes as-is.
-        bytes
+    let value = crate::parse::utf16_string(&bytes)?;
+    Ok(Some((value, flags)))
+}
+
+/// Reads the value of the given EFI variable as a little-endian [u64].
+pub fn read_u64<T: VarReader + ?Sized>(
+    var_manager: &T,
+    name: &VariableName,
+) -> Result<Option<(u64, VariableFlags)>> {
+    let (words, flags) = match read_u16_bytes(var_manager, name)? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let value = crate::parse::u64_le(words.as_u8())
+        .with_context(|| format!("Decoding variable {}", name))?;
+    Ok(Some((value, flags)))
+}
+
+/// Reads the value of the given EFI variable as a little-endian [u16].
+pub fn read_u16<T: VarReader + ?Sized>(
+    var_manager: &T,
+    name: &VariableName,
+) -> Result<Option<(u16, VariableFlags)>> {
+    let (bytes, flags) = match read_bytes(var_manager, name)? {
+        Some(data) => data,
+        None => return Ok(None),
+    };
+    let value = match bytes[..] {
+        [low, high] => u16::from_le_bytes([low, high]),
+        _ => anyhow::bail!("Variable {} has {} bytes instead of 2", name, bytes.len()),
+    };
+    Ok(Some((value, flags)))
+}
+
+/// Reads the value of the given single-byte EFI variable.
+pub fn read_u8<T: VarReader + ?Sized>(
+    var_manager: &T,
+    name: &VariableName,
+) -> Result<Option<(u8, VariableFlags)>> {
+    let (words, flags) = match read_u16_bytes(var_manager, name)? {
+        Some(data) => data,
+        None => return Ok(None),
     };
-    let value = String::from_utf16(bytes)
-        .with_context(|| format!("Non-UTF16 value: {}", String::from_utf16_lossy(bytes)))?;
+    let value = words
+        .as_u8()
+        .first()
+        .copied()
+        .with_context(|| format!("Variable {} is empty", name))?;
     Ok(Some((value, flags)))
 }
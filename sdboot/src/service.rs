@@ -0,0 +1,264 @@
+//! Transport-independent handling of requests to a privileged service.
+//!
+//! A single privileged process may perform the EFI variables writes on behalf
+//! of unprivileged clients. The requests and replies defined here are plain
+//! JSON objects, so any transport (varlink, a local socket, HTTP) only has to
+//! frame them.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{Backend, ErrorKind, Manager};
+
+/// A request to the service.
+///
+/// Serialized as `{"method": "SetOneshot", "parameters": {"entry": "..."}}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "parameters")]
+pub enum Request {
+    /// Returns the [Status].
+    GetStatus {},
+    /// Returns the entries as an [EntriesReply].
+    ListEntries {},
+    /// Returns the oneshot entry as an [EntryReply].
+    GetOneshot {},
+    /// Sets the oneshot entry. The entry must be known to the boot loader.
+    SetOneshot {
+        /// The entry identifier.
+        entry: String,
+    },
+    /// Removes the oneshot entry.
+    RemoveOneshot {},
+    /// Returns the default entry as an [EntryReply].
+    GetDefault {},
+    /// Sets the default entry. The entry must be known to the boot loader.
+    SetDefault {
+        /// The entry identifier.
+        entry: String,
+    },
+    /// Removes the default entry.
+    RemoveDefault {},
+}
+
+impl Request {
+    /// Names of the methods, as used in the `method` field.
+    pub const METHODS: &'static [&'static str] = &[
+        "GetStatus",
+        "ListEntries",
+        "GetOneshot",
+        "SetOneshot",
+        "RemoveOneshot",
+        "GetDefault",
+        "SetDefault",
+        "RemoveDefault",
+    ];
+
+    /// Checks whether the request changes any variables.
+    pub fn is_mutation(&self) -> bool {
+        matches!(
+            self,
+            Request::SetOneshot { .. }
+                | Request::RemoveOneshot {}
+                | Request::SetDefault { .. }
+                | Request::RemoveDefault {}
+        )
+    }
+}
+
+/// The boot loader state.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Status {
+    /// The boot loader name and version.
+    pub loader_info: Option<String>,
+    /// The currently booted entry.
+    pub selected: Option<String>,
+    /// The default entry.
+    pub default: Option<String>,
+    /// The oneshot entry.
+    pub oneshot: Option<String>,
+    /// The available entries, in the menu order.
+    pub entries: Vec<String>,
+}
+
+impl Status {
+    /// Collects the current boot loader state.
+    pub fn current(manager: &Manager) -> Result<Self> {
+        Ok(Self {
+            loader_info: manager.get_loader_info()?,
+            selected: manager.get_selected_entry()?,
+            default: manager.get_default_entry()?,
+            oneshot: manager.get_oneshot()?,
+            entries: manager.entries()?.ids().map(str::to_string).collect(),
+        })
+    }
+
+    /// Collects the state of the boot loader the backend manages. GRUB
+    /// reports no booted entry, and its entries only if its configuration is
+    /// readable.
+    pub fn of(backend: &Backend) -> Result<Self> {
+        match backend {
+            Backend::SystemdBoot(manager) => Self::current(manager),
+            #[cfg(feature = "grub")]
+            Backend::Grub(grub) => Ok(Self {
+                loader_info: Some("GRUB".to_string()),
+                selected: None,
+                default: grub.get_default_entry()?,
+                oneshot: grub.get_oneshot()?,
+                entries: match grub.entries() {
+                    Ok(entries) => entries.ids().map(str::to_string).collect(),
+                    Err(e) => {
+                        log::debug!("{:#}", e);
+                        Vec::new()
+                    }
+                },
+            }),
+        }
+    }
+}
+
+/// Reply to [Request::ListEntries].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntriesReply {
+    /// The available entries, in the menu order.
+    pub entries: Vec<String>,
+}
+
+/// Reply to [Request::GetOneshot] and [Request::GetDefault].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryReply {
+    /// The entry, if set.
+    pub entry: Option<String>,
+}
+
+/// Handles the request, returning the reply parameters. Requests which only
+/// change the state reply with an empty object.
+pub fn handle(backend: &mut Backend, request: Request) -> Result<serde_json::Value> {
+    let reply = match request {
+        Request::GetStatus {} => serde_json::to_value(Status::of(backend)?),
+        Request::ListEntries {} => serde_json::to_value(EntriesReply {
+            entries: backend.entries()?.ids().map(str::to_string).collect(),
+        }),
+        Request::GetOneshot {} => serde_json::to_value(EntryReply {
+            entry: backend.get_oneshot()?,
+        }),
+        Request::GetDefault {} => serde_json::to_value(EntryReply {
+            entry: backend.get_default_entry()?,
+        }),
+        Request::SetOneshot { entry } => {
+            check_entry(backend, &entry)?;
+            backend.set_oneshot(&entry)?;
+            Ok(empty())
+        }
+        Request::RemoveOneshot {} => {
+            backend.remove_oneshot()?;
+            Ok(empty())
+        }
+        Request::SetDefault { entry } => {
+            check_entry(backend, &entry)?;
+            backend.set_default(&entry)?;
+            Ok(empty())
+        }
+        Request::RemoveDefault {} => {
+            backend.remove_default()?;
+            Ok(empty())
+        }
+    };
+    reply.context("Unable to serialize the reply")
+}
+
+/// Outcome of a request, as sent back to the clients over transports without
+/// their own error signalling.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Response {
+    /// The request succeeded, with the given reply parameters.
+    Ok(serde_json::Value),
+    /// The request failed.
+    Error {
+        /// The [ErrorKind::code], if the error kind is known.
+        code: Option<i32>,
+        /// The error message.
+        message: String,
+    },
+}
+
+impl Response {
+    /// Wraps the outcome of [handle].
+    pub fn from_result(result: Result<serde_json::Value>) -> Self {
+        match result {
+            Ok(reply) => Response::Ok(reply),
+            Err(e) => Response::Error {
+                code: ErrorKind::of(&e).map(ErrorKind::code),
+                message: format!("{:#}", e),
+            },
+        }
+    }
+
+    /// Unwraps the response back into a result, restoring the [ErrorKind] of
+    /// the error.
+    pub fn into_result(self) -> Result<serde_json::Value> {
+        match self {
+            Response::Ok(reply) => Ok(reply),
+            Response::Error { code, message } => {
+                let error = anyhow::anyhow!(message);
+                Err(match code.and_then(ErrorKind::from_code) {
+                    Some(kind) => error.context(kind),
+                    None => error,
+                })
+            }
+        }
+    }
+}
+
+fn empty() -> serde_json::Value {
+    serde_json::Value::Object(Default::default())
+}
+
+fn check_entry(backend: &Backend, entry: &str) -> Result<()> {
+    if backend.entries()?.contains(entry) {
+        Ok(())
+    } else {
+        Err(anyhow::Error::new(ErrorKind::EntryNotFound)
+            .context(format!("There is no entry {:?}", entry)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_request_encoding() {
+        let request: Request =
+            serde_json::from_str(r#"{"method":"SetOneshot","parameters":{"entry":"arch.conf"}}"#)
+                .unwrap();
+        assert_eq!(
+            request,
+            Request::SetOneshot {
+                entry: "arch.conf".to_string()
+            }
+        );
+        assert!(request.is_mutation());
+
+        let request: Request =
+            serde_json::from_str(r#"{"method":"GetStatus","parameters":{}}"#).unwrap();
+        assert_eq!(request, Request::GetStatus {});
+        assert!(!request.is_mutation());
+
+        assert!(serde_json::from_str::<Request>(r#"{"method":"Reboot","parameters":{}}"#).is_err());
+    }
+
+    #[test]
+    fn check_response_round_trip() {
+        let error = anyhow::Error::new(ErrorKind::EntryNotFound).context("There is no entry");
+        let response = Response::from_result(Err(error));
+        let encoded = serde_json::to_string(&response).unwrap();
+        let decoded: Response = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded, response);
+        let error = decoded.into_result().unwrap_err();
+        assert_eq!(ErrorKind::of(&error), Some(ErrorKind::EntryNotFound));
+
+        let response = Response::from_result(Ok(serde_json::Value::Null));
+        assert_eq!(response.into_result().unwrap(), serde_json::Value::Null);
+    }
+}
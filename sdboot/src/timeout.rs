@@ -0,0 +1,67 @@
+//! The boot menu timeout values.
+
+use std::{fmt, str::FromStr};
+
+/// A boot menu timeout, as stored in the `LoaderConfigTimeout` and
+/// `LoaderConfigTimeoutOneShot` variables.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timeout {
+    /// Show the menu for the given number of seconds. Zero means the menu is
+    /// shown only if a key is pressed during the boot.
+    Seconds(u32),
+    /// Show the menu and wait for the user indefinitely.
+    MenuForce,
+    /// Don't show the menu unless a key is pressed during the boot.
+    MenuHidden,
+    /// Don't show the menu at all.
+    MenuDisabled,
+}
+
+impl fmt::Display for Timeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timeout::Seconds(seconds) => write!(f, "{}", seconds),
+            Timeout::MenuForce => f.write_str("menu-force"),
+            Timeout::MenuHidden => f.write_str("menu-hidden"),
+            Timeout::MenuDisabled => f.write_str("menu-disabled"),
+        }
+    }
+}
+
+impl FromStr for Timeout {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "menu-force" => Ok(Timeout::MenuForce),
+            "menu-hidden" => Ok(Timeout::MenuHidden),
+            "menu-disabled" => Ok(Timeout::MenuDisabled),
+            seconds => seconds.parse().map(Timeout::Seconds).map_err(|_| {
+                anyhow::anyhow!(
+                    "Invalid timeout {:?}, expected seconds, \
+                     \"menu-force\", \"menu-hidden\" or \"menu-disabled\"",
+                    value
+                )
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn check_parse() {
+        for timeout in [
+            Timeout::Seconds(5),
+            Timeout::MenuForce,
+            Timeout::MenuHidden,
+            Timeout::MenuDisabled,
+        ] {
+            assert_eq!(timeout.to_string().parse::<Timeout>().unwrap(), timeout);
+        }
+        assert!("-1".parse::<Timeout>().is_err());
+        assert!("menu".parse::<Timeout>().is_err());
+    }
+}
@@ -0,0 +1,326 @@
+//! Conformance checks of the boot loader interface variables.
+//!
+//! The checks work on the raw [dump](crate::dump) of the variables, so they
+//! also catch problems the typed accessors of [Manager](crate::Manager)
+//! silently tolerate, like a missing NUL terminator.
+
+use std::fmt;
+
+use crate::dump::Variable;
+
+const NON_VOLATILE: u32 = 0x01;
+const BOOTSERVICE_ACCESS: u32 = 0x02;
+const RUNTIME_ACCESS: u32 = 0x04;
+
+/// How the value of a variable is expected to look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    /// A single entry identifier.
+    EntryId,
+    /// A list of entry identifiers.
+    EntryIds,
+    /// Free-form text.
+    Text,
+    /// A menu timeout: seconds, "menu-force", "menu-hidden" or
+    /// "menu-disabled".
+    Timeout,
+    /// A decimal number of microseconds.
+    Usec,
+    /// A little-endian 64-bit integer.
+    U64,
+}
+
+/// The known variables, their formats and whether the OS sets them, hence
+/// they must be non-volatile.
+const KNOWN: [(&str, Format, bool); 14] = [
+    ("LoaderEntryDefault", Format::EntryId, true),
+    ("LoaderEntryOneShot", Format::EntryId, true),
+    ("LoaderEntrySelected", Format::EntryId, false),
+    ("LoaderEntries", Format::EntryIds, false),
+    ("LoaderInfo", Format::Text, false),
+    ("LoaderFirmwareInfo", Format::Text, false),
+    ("LoaderFirmwareType", Format::Text, false),
+    ("LoaderImageIdentifier", Format::Text, false),
+    ("LoaderConfigTimeout", Format::Timeout, false),
+    ("LoaderConfigTimeoutOneShot", Format::Timeout, true),
+    ("LoaderTimeInitUSec", Format::Usec, false),
+    ("LoaderTimeExecUSec", Format::Usec, false),
+    ("LoaderTimeMenuUSec", Format::Usec, false),
+    ("LoaderFeatures", Format::U64, false),
+];
+
+/// Outcome of a single check of a variable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Check {
+    /// The variable name, e.g. "LoaderEntryDefault".
+    pub variable: String,
+    /// What has been checked, e.g. "NUL termination".
+    pub check: &'static str,
+    /// Why the check failed, [None] if it passed.
+    pub failure: Option<String>,
+}
+
+impl Check {
+    /// Whether the check passed.
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.failure {
+            None => write!(f, "PASS  {}: {}", self.variable, self.check),
+            Some(failure) => write!(f, "FAIL  {}: {} ({})", self.variable, self.check, failure),
+        }
+    }
+}
+
+/// Checks the known variables among the given ones. The unknown variables are
+/// skipped.
+pub fn verify(variables: &[Variable]) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for variable in variables {
+        let Some((_, format, host_set)) = KNOWN.iter().find(|(name, ..)| *name == variable.name)
+        else {
+            continue;
+        };
+        let mut check = |check: &'static str, failure: Option<String>| {
+            checks.push(Check {
+                variable: variable.name.clone(),
+                check,
+                failure,
+            })
+        };
+
+        check(
+            "attributes",
+            check_attributes(variable.attributes, *host_set),
+        );
+
+        if *format == Format::U64 {
+            check(
+                "size",
+                (variable.value.len() != 8)
+                    .then(|| format!("{} bytes instead of 8", variable.value.len())),
+            );
+            continue;
+        }
+
+        let text = match decode(&variable.value) {
+            Ok(text) => {
+                check("UTF-16", None);
+                text
+            }
+            Err(failure) => {
+                check("UTF-16", Some(failure));
+                continue;
+            }
+        };
+        check(
+            "NUL termination",
+            (!text.ends_with('\0')).then(|| "the value is not NUL-terminated".to_string()),
+        );
+        let text = text.strip_suffix('\0').unwrap_or(&text);
+        match format {
+            Format::EntryId => check("identifier", check_identifier(text)),
+            Format::EntryIds => check(
+                "identifiers",
+                text.split('\0')
+                    .find_map(check_identifier)
+                    .or_else(|| text.is_empty().then(|| "no entries".to_string())),
+            ),
+            Format::Timeout => check("timeout", check_timeout(text)),
+            Format::Usec => check(
+                "number",
+                text.parse::<u64>()
+                    .err()
+                    .map(|_| format!("{:?} is not a number", text)),
+            ),
+            Format::Text | Format::U64 => {}
+        }
+    }
+    checks
+}
+
+/// A rewrite fixing a variable the OS is supposed to set, which has wrong
+/// attributes or lacks the NUL terminator, e.g. because a third-party tool
+/// wrote it. The boot loader ignores such variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Repair {
+    /// The variable name, e.g. "LoaderEntryDefault".
+    pub variable: String,
+    /// The value to write, without the NUL terminator.
+    pub value: String,
+    /// The problems the rewrite fixes.
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for Repair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: rewrite {:?} ({})",
+            self.variable,
+            self.value,
+            self.problems.join("; ")
+        )
+    }
+}
+
+/// Finds the variables which can be fixed by rewriting them: the ones the OS
+/// sets with wrong attributes or without the NUL terminator, whose values
+/// are otherwise valid.
+pub fn repairs(variables: &[Variable]) -> Vec<Repair> {
+    variables
+        .iter()
+        .filter_map(|variable| {
+            let (_, format, host_set) = KNOWN.iter().find(|(name, ..)| *name == variable.name)?;
+            if !host_set {
+                return None;
+            }
+            let text = decode(&variable.value).ok()?;
+            let mut problems = Vec::new();
+            problems.extend(check_attributes(variable.attributes, true));
+            let value = match text.strip_suffix('\0') {
+                Some(value) => value.to_string(),
+                None => {
+                    problems.push("the value is not NUL-terminated".to_string());
+                    text
+                }
+            };
+            let invalid = match format {
+                Format::EntryId => check_identifier(&value),
+                Format::Timeout => check_timeout(&value),
+                _ => None,
+            };
+            (!problems.is_empty() && invalid.is_none()).then(|| Repair {
+                variable: variable.name.clone(),
+                value,
+                problems,
+            })
+        })
+        .collect()
+}
+
+fn check_attributes(attributes: u32, host_set: bool) -> Option<String> {
+    let required = BOOTSERVICE_ACCESS | RUNTIME_ACCESS | if host_set { NON_VOLATILE } else { 0 };
+    let allowed = NON_VOLATILE | BOOTSERVICE_ACCESS | RUNTIME_ACCESS;
+    if attributes & required != required {
+        Some(format!(
+            "{:#x} lacks the required {:#x}",
+            attributes, required
+        ))
+    } else if attributes & !allowed != 0 {
+        Some(format!(
+            "{:#x} has unexpected bits {:#x}",
+            attributes,
+            attributes & !allowed
+        ))
+    } else {
+        None
+    }
+}
+
+/// Decodes the little-endian UTF-16 value, keeping the NUL terminator.
+fn decode(value: &[u8]) -> Result<String, String> {
+    let words = crate::parse::u16_le_words(value)
+        .ok_or_else(|| format!("odd size of {} bytes", value.len()))?;
+    String::from_utf16(&words).map_err(|_| "invalid UTF-16".to_string())
+}
+
+/// Entry identifiers are file names of the entries on the ESP, or the names
+/// of the automatic entries.
+fn check_identifier(id: &str) -> Option<String> {
+    if id.is_empty() {
+        Some("empty identifier".into())
+    } else if id.chars().any(|c| c.is_control() || c == '/' || c == '\\') {
+        Some(format!("{:?} is not a valid identifier", id))
+    } else {
+        None
+    }
+}
+
+fn check_timeout(timeout: &str) -> Option<String> {
+    match timeout {
+        "menu-force" | "menu-hidden" | "menu-disabled" => None,
+        seconds if seconds.parse::<u32>().is_ok() => None,
+        other => Some(format!("{:?} is not a valid timeout", other)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn variable(name: &str, attributes: u32, value: &[u8]) -> Variable {
+        Variable {
+            name: name.into(),
+            attributes,
+            value: value.to_vec(),
+        }
+    }
+
+    fn failures(variables: &[Variable]) -> Vec<(&'static str, String)> {
+        verify(variables)
+            .into_iter()
+            .filter_map(|check| Some((check.check, check.failure?)))
+            .collect()
+    }
+
+    #[test]
+    fn check_verify() {
+        let good = [
+            variable("LoaderEntryDefault", 0x7, b"a\0r\0c\0h\0\0\0"),
+            variable("LoaderEntries", 0x6, b"a\0\0\0b\0\0\0"),
+            variable("LoaderConfigTimeout", 0x6, b"5\0\0\0"),
+            variable("LoaderFeatures", 0x6, &[0; 8]),
+            variable("SomethingElse", 0x1, b"?"),
+        ];
+        assert_eq!(failures(&good), []);
+        assert_eq!(verify(&good).len(), 14);
+
+        let bad = [
+            variable("LoaderEntryDefault", 0x6, b"a\0r\0c\0h\0"),
+            variable("LoaderEntryOneShot", 0x7, b"a\0/\0\0\0"),
+            variable("LoaderConfigTimeoutOneShot", 0x7, b"x\0\0\0"),
+            variable("LoaderTimeInitUSec", 0x6, b"1\0"),
+            variable("LoaderFeatures", 0x6, &[0; 4]),
+        ];
+        assert_eq!(
+            failures(&bad),
+            [
+                ("attributes", "0x6 lacks the required 0x7".to_string()),
+                (
+                    "NUL termination",
+                    "the value is not NUL-terminated".to_string()
+                ),
+                (
+                    "identifier",
+                    r#""a/" is not a valid identifier"#.to_string()
+                ),
+                ("timeout", r#""x" is not a valid timeout"#.to_string()),
+                (
+                    "NUL termination",
+                    "the value is not NUL-terminated".to_string()
+                ),
+                ("size", "4 bytes instead of 8".to_string()),
+            ]
+        );
+
+        assert_eq!(repairs(&good), []);
+        assert_eq!(
+            repairs(&bad),
+            [Repair {
+                variable: "LoaderEntryDefault".into(),
+                value: "arch".into(),
+                problems: vec![
+                    "0x6 lacks the required 0x7".into(),
+                    "the value is not NUL-terminated".into()
+                ],
+            }]
+        );
+    }
+}
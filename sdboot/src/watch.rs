@@ -0,0 +1,42 @@
+//! Watching the variables for the changes made by the other processes, e.g.
+//! `bootctl`, to keep the user interfaces up to date.
+
+use anyhow::{Context, Result};
+use nix::{
+    errno::Errno,
+    sys::inotify::{AddWatchFlags, InitFlags, Inotify},
+};
+
+use crate::efivarfs::MOUNT_POINT;
+
+/// Calls `on_change` from a background thread whenever a variable is created,
+/// written or removed, until it returns `false`. A burst of changes may result
+/// in a single call.
+pub fn watch(mut on_change: impl FnMut() -> bool + Send + 'static) -> Result<()> {
+    let inotify = Inotify::init(InitFlags::IN_CLOEXEC).context("Unable to initialize inotify")?;
+    inotify
+        .add_watch(
+            MOUNT_POINT,
+            AddWatchFlags::IN_CREATE | AddWatchFlags::IN_MODIFY | AddWatchFlags::IN_DELETE,
+        )
+        .with_context(|| format!("Unable to watch {}", MOUNT_POINT))?;
+    std::thread::Builder::new()
+        .name("sdboot-watch".to_string())
+        .spawn(move || loop {
+            match inotify.read_events() {
+                Ok(events) if events.is_empty() => {}
+                Ok(_) => {
+                    if !on_change() {
+                        break;
+                    }
+                }
+                Err(Errno::EINTR) => {}
+                Err(e) => {
+                    log::warn!("Stopped watching the variables: {}", e);
+                    break;
+                }
+            }
+        })
+        .context("Unable to spawn the watching thread")?;
+    Ok(())
+}
@@ -13,25 +13,46 @@ pub fn write_utf16_string<T: VarWriter + ?Sized>(
     flags: VariableFlags,
     value: &str,
 ) -> Result<()> {
-    let mut buffer = Vec::with_capacity(value.as_bytes().len() * 2);
-    for wide_char in value.encode_utf16() {
-        let [first, second] = wide_char.to_le_bytes();
-        if first == 0 && second == 0 {
-            log::warn!("Intermediate null byte in {:?}", value);
-        }
-        buffer.push(first);
-        buffer.push(second);
-    }
-    if let [.., 0, 0] = &buffer[..] {
-        // Already null-terminated. No need to add trailing zeroes.
-    } else {
-        // Not null-terminated. Add trailing zeroes!
-        buffer.extend_from_slice(&[0, 0]);
-    }
+    let buffer = crate::parse::encode_utf16(value);
     log::trace!("{} encoded as utf16 {:x?}", value, buffer);
 
     var_manager
         .write(name, flags, &buffer)
-        .map_err(crate::error::EfiError)
+        .map_err(|e| explain(e, name))
         .with_context(|| format!("Unable to set variable '{}' to '{}'", name, value))
 }
+
+/// Writes the value to the given EFI variable as a little-endian [u64].
+pub fn write_u64<T: VarWriter + ?Sized>(
+    var_manager: &mut T,
+    name: &VariableName,
+    flags: VariableFlags,
+    value: u64,
+) -> Result<()> {
+    var_manager
+        .write(name, flags, &value.to_le_bytes())
+        .map_err(|e| explain(e, name))
+        .with_context(|| format!("Unable to set variable '{}' to {:#x}", name, value))
+}
+
+/// Writes the value to the given EFI variable as a little-endian [u16].
+pub fn write_u16<T: VarWriter + ?Sized>(
+    var_manager: &mut T,
+    name: &VariableName,
+    flags: VariableFlags,
+    value: u16,
+) -> Result<()> {
+    var_manager
+        .write(name, flags, &value.to_le_bytes())
+        .map_err(|e| explain(e, name))
+        .with_context(|| format!("Unable to set variable '{}' to {:#06x}", name, value))
+}
+
+/// Attaches the details the user needs to fix the failure.
+fn explain(e: efivar::Error, name: &VariableName) -> anyhow::Error {
+    let error = crate::error::EfiError(e).into_anyhow();
+    let error = crate::privileges::explain(error, name);
+    #[cfg(target_os = "linux")]
+    let error = crate::efivarfs::explain_no_space(error);
+    error
+}